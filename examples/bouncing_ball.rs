@@ -0,0 +1,78 @@
+//! Bounce a ball on the simulated display, a frame every 20ms at most
+//!
+//! The frames are paced as if the display was on a 40MHz bus, where a full
+//! screen takes about 31ms: the pacer stretches the period to that.
+//!
+//! Run with `cargo run --example bouncing_ball --features simulator,animation`,
+//! the frame timing is printed at the end and the last frame is written to
+//! `bouncing_ball.ppm`.
+
+extern crate ili9341;
+
+use std::fs::File;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use ili9341::animation::FramePacer;
+use ili9341::simulator::Ili9341Sim;
+
+/// SPI clock of the bus the transfer time is estimated for
+const SPI_HZ: u32 = 40_000_000;
+const RADIUS: i32 = 12;
+const BALL: u16 = 0xffe0;
+const BACKGROUND: u16 = 0x001f;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut display = Ili9341Sim::new_simulator();
+    let (w, h) = (display.width() as i32, display.height() as i32);
+    let mut frame = vec![0; display.size_in_bytes()];
+
+    let mut pacer = FramePacer::new(20);
+    pacer.set_transfer_time_us(display.transfer_time_us(w as u16, h as u16, SPI_HZ));
+
+    let (mut x, mut y, mut dx, mut dy) = (RADIUS, RADIUS, 3, 2);
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(3) {
+        let now = start.elapsed().as_micros() as u32;
+        if !pacer.present_window(&mut display, now, 1_000_000, &frame)? {
+            continue;
+        }
+        // move the ball for the next frame, bouncing on the edges
+        if x + dx < RADIUS || x + dx >= w - RADIUS {
+            dx = -dx;
+        }
+        if y + dy < RADIUS || y + dy >= h - RADIUS {
+            dy = -dy;
+        }
+        x += dx;
+        y += dy;
+        for (i, pixel) in frame.chunks_mut(2).enumerate() {
+            let (px, py) = (i as i32 % w - x, i as i32 / w - y);
+            let color = if px * px + py * py <= RADIUS * RADIUS {
+                BALL
+            } else {
+                BACKGROUND
+            };
+            pixel.copy_from_slice(&color.to_be_bytes());
+        }
+    }
+
+    let stats = pacer.stats();
+    println!(
+        "{} frames, {} late, longest frame {}us",
+        stats.frames, stats.late_frames, stats.max_frame_us
+    );
+
+    let mut file = File::create("bouncing_ball.ppm")?;
+    writeln!(
+        file,
+        "P6 {} {} 255",
+        ili9341::NATIVE_WIDTH,
+        ili9341::NATIVE_HEIGHT
+    )?;
+    for &color in display.panel().frame() {
+        let (r, g, b) = (color >> 11, color >> 5 & 0x3f, color & 0x1f);
+        file.write_all(&[(r << 3) as u8, (g << 2) as u8, (b << 3) as u8])?;
+    }
+    Ok(())
+}
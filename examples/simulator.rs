@@ -0,0 +1,42 @@
+//! Draw on the simulated display and save the image as a PPM file
+//!
+//! Run with `cargo run --example simulator --features simulator`, the image is
+//! written to `ili9341.ppm`.
+
+extern crate ili9341;
+
+use std::fs::File;
+use std::io::Write;
+
+use ili9341::simulator::Ili9341Sim;
+use ili9341::Orientation;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut display = Ili9341Sim::new_simulator();
+    display.set_orientation(Orientation::Landscape)?;
+
+    // red, green and blue vertical stripes on the top half
+    let (w, h) = (display.width() as u16, display.height() as u16);
+    display.fill_pattern(0, 0, w - 1, h / 2 - 1, &[0xf800, 0x07e0, 0x001f])?;
+    // a horizontal gray gradient on the bottom half
+    let gradient = (0..w).map(|x| {
+        let level = x * 32 / w;
+        level << 11 | level << 6 | level
+    });
+    let rows = (h / 2..h).flat_map(|_| gradient.clone());
+    display.draw_iter(0, h / 2, w - 1, h - 1, rows)?;
+
+    // the panel keeps the image in the native portrait frame
+    let mut file = File::create("ili9341.ppm")?;
+    writeln!(
+        file,
+        "P6 {} {} 255",
+        ili9341::NATIVE_WIDTH,
+        ili9341::NATIVE_HEIGHT
+    )?;
+    for &color in display.panel().frame() {
+        let (r, g, b) = (color >> 11, color >> 5 & 0x3f, color & 0x1f);
+        file.write_all(&[(r << 3) as u8, (g << 2) as u8, (b << 3) as u8])?;
+    }
+    Ok(())
+}
@@ -0,0 +1,207 @@
+//! Frame pacing for animations
+
+use hal::timer::CountDown;
+
+use Ili9341Surface;
+
+/// Calls a drawing closure at a fixed frame rate
+///
+/// The frame period is measured with a `CountDown` timer, started with the
+/// target frame rate at the start of each frame, so its `Time` type must be a
+/// frequency that can be built from a number of hertz (`From<u32>`).
+pub struct AnimationController<TIMER> {
+    timer: TIMER,
+    target_fps: u8,
+    frames_dropped: u32,
+}
+
+impl<TIMER> AnimationController<TIMER>
+where
+    TIMER: CountDown,
+    TIMER::Time: From<u32>,
+{
+    /// Create a controller running at `target_fps` frames per second
+    ///
+    /// A `target_fps` of 0 is treated as 1.
+    pub fn new(timer: TIMER, target_fps: u8) -> Self {
+        AnimationController {
+            timer,
+            target_fps: target_fps.max(1),
+            frames_dropped: 0,
+        }
+    }
+
+    /// Call `frame_fn` once per frame period until it returns `false`
+    ///
+    /// After each frame, the controller waits for the end of the frame period.
+    /// A frame that takes longer than the period is counted as dropped, and
+    /// the next one starts right away.
+    pub fn run<S, F>(&mut self, display: &mut S, mut frame_fn: F)
+    where
+        S: Ili9341Surface,
+        F: FnMut(&mut S) -> bool,
+    {
+        loop {
+            self.timer.start(u32::from(self.target_fps));
+            if !frame_fn(display) {
+                return;
+            }
+            if self.timer.wait().is_ok() {
+                // the frame period was already over
+                self.frames_dropped = self.frames_dropped.wrapping_add(1);
+                continue;
+            }
+            while self.timer.wait().is_err() {}
+        }
+    }
+
+    /// Number of frames that took longer than the frame period
+    pub fn frames_dropped(&self) -> u32 {
+        self.frames_dropped
+    }
+
+    /// Get back the timer
+    pub fn release(self) -> TIMER {
+        self.timer
+    }
+}
+
+/// Frame timing measured by a [FramePacer](struct.FramePacer.html)
+///
+/// Times are in microseconds, and all counters wrap around on overflow.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameStats {
+    /// Frames presented
+    pub frames: u32,
+    /// Calls to `should_present` that did not present a frame
+    pub skipped: u32,
+    /// Frames presented more than one frame period after the target time,
+    /// that is a frame or more too late
+    pub late_frames: u32,
+    /// Time between the last two frames presented
+    pub last_frame_us: u32,
+    /// Longest time between two frames presented
+    pub max_frame_us: u32,
+    /// How late the last frame was presented, after the end of its period
+    pub last_lateness_us: u32,
+}
+
+/// Decides when to present the next frame, without owning a timer
+///
+/// The caller passes the current time of any monotonic counter, in ticks,
+/// with the counter frequency. The counter may wrap around, as long as it
+/// does not wrap twice between two calls. The next frame period starts when
+/// a frame is presented, so a late frame delays the following ones instead
+/// of causing a burst of frames to catch up.
+///
+/// ```ignore
+/// let mut pacer = FramePacer::new(20);
+/// loop {
+///     update(&mut frame);
+///     pacer.present_window(&mut display, timer.now(), TIMER_HZ, &frame)?;
+/// }
+/// ```
+///
+/// The transfer of a frame takes a fixed time, estimated by
+/// [transfer_time_us](../struct.Ili9341.html#method.transfer_time_us): a
+/// period shorter than that cannot be reached, and the frames are then
+/// presented back to back. Given to
+/// [set_transfer_time_us](#method.set_transfer_time_us), it stretches the
+/// period, so those frames are not counted as late.
+pub struct FramePacer {
+    period_us: u32,
+    transfer_us: u32,
+    last_present: Option<u32>,
+    stats: FrameStats,
+}
+
+impl FramePacer {
+    /// Create a pacer presenting a frame at most every `period_ms`
+    /// milliseconds
+    pub fn new(period_ms: u32) -> Self {
+        FramePacer {
+            period_us: period_ms.saturating_mul(1000),
+            transfer_us: 0,
+            last_present: None,
+            stats: FrameStats::default(),
+        }
+    }
+
+    /// Set the time taken by the transfer of a frame, in microseconds
+    ///
+    /// The frame period is never shorter than this. With a display on a
+    /// 40MHz bus:
+    ///
+    /// ```ignore
+    /// let (w, h) = (display.width() as u16, display.height() as u16);
+    /// pacer.set_transfer_time_us(display.transfer_time_us(w, h, 40_000_000));
+    /// ```
+    pub fn set_transfer_time_us(&mut self, transfer_us: u32) {
+        self.transfer_us = transfer_us;
+    }
+
+    /// Whether a frame should be presented at `now_ticks`, from a counter
+    /// running at `tick_hz`
+    ///
+    /// The first call always presents a frame. When this returns `true`, the
+    /// frame is counted as presented and the next period starts.
+    ///
+    /// A `tick_hz` of 0 is treated as 1. The time between two frames saturates
+    /// at `u32::MAX` microseconds, about 71 minutes.
+    pub fn should_present(&mut self, now_ticks: u32, tick_hz: u32) -> bool {
+        let last = match self.last_present {
+            Some(last) => last,
+            None => {
+                self.last_present = Some(now_ticks);
+                self.stats.frames = self.stats.frames.wrapping_add(1);
+                return true;
+            }
+        };
+        let elapsed = u64::from(now_ticks.wrapping_sub(last));
+        let elapsed_us = elapsed * 1_000_000 / u64::from(tick_hz.max(1));
+        let elapsed_us = core::cmp::min(elapsed_us, u64::from(u32::MAX)) as u32;
+        let period_us = self.period_us.max(self.transfer_us);
+        if elapsed_us < period_us {
+            self.stats.skipped = self.stats.skipped.wrapping_add(1);
+            return false;
+        }
+        let lateness = elapsed_us - period_us;
+        self.last_present = Some(now_ticks);
+        self.stats.frames = self.stats.frames.wrapping_add(1);
+        if lateness >= period_us {
+            self.stats.late_frames = self.stats.late_frames.wrapping_add(1);
+        }
+        self.stats.last_frame_us = elapsed_us;
+        self.stats.max_frame_us = self.stats.max_frame_us.max(elapsed_us);
+        self.stats.last_lateness_us = lateness;
+        true
+    }
+
+    /// Draw a full screen of raw pixel bytes with
+    /// [draw_raw](../struct.Ili9341.html#method.draw_raw) if a frame should
+    /// be presented at `now_ticks`, returning whether it was
+    pub fn present_window<S: Ili9341Surface>(
+        &mut self,
+        display: &mut S,
+        now_ticks: u32,
+        tick_hz: u32,
+        data: &[u8],
+    ) -> Result<bool, S::Error> {
+        if !self.should_present(now_ticks, tick_hz) {
+            return Ok(false);
+        }
+        let (w, h) = (display.width() as u16, display.height() as u16);
+        display.draw_raw(0, 0, w - 1, h - 1, data)?;
+        Ok(true)
+    }
+
+    /// Frame timing so far
+    pub fn stats(&self) -> &FrameStats {
+        &self.stats
+    }
+
+    /// Reset the frame timing, keeping the current frame period
+    pub fn reset_stats(&mut self) {
+        self.stats = FrameStats::default();
+    }
+}
@@ -0,0 +1,112 @@
+//! Conversions between color formats
+
+/// Convert a 24 bit color to rgb565, keeping the most significant bits of
+/// each channel
+pub fn rgb888_to_rgb565(r: u8, g: u8, b: u8) -> u16 {
+    (u16::from(r >> 3) << 11) | (u16::from(g >> 2) << 5) | u16::from(b >> 3)
+}
+
+/// Gray rgb565 colors for the 16 levels of a 4-bit luminance
+const GRAY4_TO_RGB565: [u16; 16] = gray4_table();
+
+const fn gray4_table() -> [u16; 16] {
+    let mut table = [0; 16];
+    let mut i = 0;
+    while i < 16 {
+        // spread the 16 levels over 0..=255
+        let v = (i * 17) as u16;
+        let (r, g) = (v >> 3, v >> 2);
+        table[i] = (r << 11) | (g << 5) | r;
+        i += 1;
+    }
+    table
+}
+
+/// Convert a rgb565 color to a gray rgb565 color with 16 levels
+///
+/// The luminance is `(299 * R + 587 * G + 114 * B) / 1000`, with the channels
+/// scaled to 8 bits, quantized to 4 bits.
+pub fn rgb565_to_gray4(color: u16) -> u16 {
+    let r = u32::from((color >> 11) & 0x1f);
+    let g = u32::from((color >> 5) & 0x3f);
+    let b = u32::from(color & 0x1f);
+    // scale to 8 bits by replicating the msbs
+    let (r, g, b) = (
+        (r << 3) | (r >> 2),
+        (g << 2) | (g >> 4),
+        (b << 3) | (b >> 2),
+    );
+    let y = (299 * r + 587 * g + 114 * b) / 1000;
+    GRAY4_TO_RGB565[(y >> 4) as usize]
+}
+
+/// Per-channel lookup tables correcting the brightness response of a panel
+///
+/// Each table maps a channel value of a rgb565 color to the value sent
+/// instead: 32 entries for the 5-bit red and blue channels, 64 for the 6-bit
+/// green one. The values are masked to the width of their channel. It is set
+/// with [set_brightness_lut](../struct.Ili9341.html#method.set_brightness_lut).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BrightnessLut {
+    r_lut: [u8; 32],
+    g_lut: [u8; 64],
+    b_lut: [u8; 32],
+}
+
+impl BrightnessLut {
+    /// Create the tables from their values
+    pub const fn from_arrays(r: [u8; 32], g: [u8; 64], b: [u8; 32]) -> BrightnessLut {
+        BrightnessLut {
+            r_lut: r,
+            g_lut: g,
+            b_lut: b,
+        }
+    }
+
+    /// Tables leaving the colors unchanged
+    pub const fn identity() -> BrightnessLut {
+        let mut lut = BrightnessLut {
+            r_lut: [0; 32],
+            g_lut: [0; 64],
+            b_lut: [0; 32],
+        };
+        let mut i = 0;
+        while i < 64 {
+            if i < 32 {
+                lut.r_lut[i] = i as u8;
+                lut.b_lut[i] = i as u8;
+            }
+            lut.g_lut[i] = i as u8;
+            i += 1;
+        }
+        lut
+    }
+
+    /// Tables applying a power law to each channel: a channel value `v` out
+    /// of `max` becomes `max * (v / max) ^ gamma`, rounded
+    ///
+    /// A gamma above 1 darkens the mid tones, below 1 brightens them.
+    #[cfg(feature = "std")]
+    pub fn gamma_corrected(gamma: f32) -> BrightnessLut {
+        let fill = |table: &mut [u8]| {
+            let max = (table.len() - 1) as f32;
+            for (i, v) in table.iter_mut().enumerate() {
+                *v = (max * (i as f32 / max).powf(gamma)).round() as u8;
+            }
+        };
+        let mut lut = BrightnessLut::identity();
+        fill(&mut lut.r_lut);
+        fill(&mut lut.g_lut);
+        fill(&mut lut.b_lut);
+        lut
+    }
+
+    /// Apply the tables to a rgb565 color
+    #[inline]
+    pub fn apply(&self, color: u16) -> u16 {
+        let r = self.r_lut[usize::from(color >> 11)] & 0x1f;
+        let g = self.g_lut[usize::from((color >> 5) & 0x3f)] & 0x3f;
+        let b = self.b_lut[usize::from(color & 0x1f)] & 0x1f;
+        (u16::from(r) << 11) | (u16::from(g) << 5) | u16::from(b)
+    }
+}
@@ -0,0 +1,99 @@
+//! Pixel buffers aligned to the cache lines, for DMA with a data cache
+//!
+//! On microcontrollers with a data cache, like the Cortex-M7 of the STM32F7
+//! and STM32H7, a DMA transfer reads the memory, not the cache: the cache
+//! lines holding the buffer must be cleaned (written back) before the
+//! transfer starts. Cleaning works on whole cache lines of 32 bytes, so a
+//! buffer sharing a line with other data can have that data written back at
+//! the wrong time. [AlignedSlice](struct.AlignedSlice.html) only accepts data
+//! starting on a line, and [AlignedBuffer](struct.AlignedBuffer.html) is a
+//! buffer always placed on one.
+//!
+//! The sequence around each transfer is:
+//!
+//! 1. fill the buffer,
+//! 2. clean the D-cache over the buffer, for example with
+//!    `SCB::clean_dcache_by_slice` of the `cortex-m` crate,
+//! 3. draw it with
+//!    [draw_raw_aligned](../struct.Ili9341.html#method.draw_raw_aligned),
+//! 4. leave the buffer untouched until the transfer is complete.
+//!
+//! Nothing has to be invalidated after the transfer, the DMA only reads the
+//! buffer. The length of the buffer should also be a multiple of
+//! [DMA_ALIGN](constant.DMA_ALIGN.html), so that its last line holds nothing
+//! else.
+
+use hal::digital::v2::OutputPin;
+
+use {DataCommand, Error, Ili9341};
+
+/// Size of a cache line of the Cortex-M7, the alignment of the DMA buffers
+pub const DMA_ALIGN: usize = 32;
+
+/// Bytes starting on a cache line boundary
+#[derive(Clone, Copy, Debug)]
+pub struct AlignedSlice<'a>(&'a [u8]);
+
+impl<'a> AlignedSlice<'a> {
+    /// Wrap `data`, returning `None` if it doesn't start on a multiple of
+    /// [DMA_ALIGN](constant.DMA_ALIGN.html)
+    pub fn new(data: &'a [u8]) -> Option<Self> {
+        if data.as_ptr() as usize % DMA_ALIGN != 0 {
+            return None;
+        }
+        Some(AlignedSlice(data))
+    }
+    /// The bytes
+    pub fn as_slice(&self) -> &'a [u8] {
+        self.0
+    }
+}
+
+/// Buffer of `N` bytes placed on a cache line boundary, for static or stack
+/// buffers
+///
+/// The alignment is fixed to [DMA_ALIGN](constant.DMA_ALIGN.html), as
+/// `repr(align)` can't take a generic parameter.
+#[repr(C, align(32))]
+#[derive(Clone, Copy, Debug)]
+pub struct AlignedBuffer<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> AlignedBuffer<N> {
+    /// Create a buffer filled with zeros
+    pub const fn new() -> Self {
+        AlignedBuffer([0; N])
+    }
+    /// The whole buffer, as an aligned slice
+    pub fn as_aligned(&self) -> AlignedSlice<'_> {
+        AlignedSlice(&self.0)
+    }
+}
+
+impl<const N: usize> Default for AlignedBuffer<N> {
+    fn default() -> Self {
+        AlignedBuffer::new()
+    }
+}
+
+impl<SpiE, PinE, SPI, CS, DC, RESET, TE> Ili9341<SPI, CS, DC, RESET, TE>
+where
+    CS: OutputPin<Error = PinE>,
+    DC: DataCommand<SPI, PinE, SpiError = SpiE>,
+    RESET: OutputPin<Error = PinE>,
+{
+    /// Draw a rectangle like [blit_dma](#method.blit_dma), from data aligned
+    /// for DMA with a data cache
+    ///
+    /// The data is handed to a single SPI write, starting on a cache line, see
+    /// the [dma](dma/index.html) module for the cache maintenance needed.
+    pub fn draw_raw_aligned(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        data: AlignedSlice,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        self.blit_dma(x0, y0, x1, y1, data.as_slice())
+    }
+}
@@ -1,385 +1,5261 @@
+//! Driver for the ILI9341 TFT LCD controller
+//!
+//! # Byte order
+//!
+//! Pixels given as `u16` (rgb565) values, whether to
+//! [draw_iter](struct.Ili9341.html#method.draw_iter), to the
+//! embedded-graphics target or to any other method, are always sent most
+//! significant byte first, as the controller expects, whatever the byte order
+//! of the host. Methods taking raw bytes, like
+//! [draw_raw](struct.Ili9341.html#method.draw_raw), send them unchanged: the
+//! bytes of each pixel must already be in this order. For example pure red
+//! (0xf800) is sent as `[0xf8, 0x00]` in the rgb565 pixel format, and as
+//! `[0xfc, 0x00, 0x00]` in the rgb666 one.
+//!
+//! # Windows
+//!
+//! Drawing methods take a window as its top-left corner (x0, y0) and
+//! bottom-right corner (x1, y1), both included, so a window with x0 == x1 and
+//! y0 == y1 is a single pixel. A window with x1 < x0 or y1 < y0 is empty:
+//! drawing into it sends nothing, not even the window, and succeeds, as does
+//! drawing no pixels at all (an empty slice or iterator), except with
+//! [draw_iter_rgb565](struct.Ili9341.html#method.draw_iter_rgb565) which
+//! requires exactly one pixel per pixel of the window. Only
+//! [set_column_address](struct.Ili9341.html#method.set_column_address) and
+//! [set_page_address](struct.Ili9341.html#method.set_page_address), which set
+//! a range without drawing, reject inverted ranges with `Error::OutOfBounds`.
+
 #![no_std]
 
 extern crate embedded_hal as hal;
+extern crate nb;
+extern crate void;
 
 #[cfg(feature = "graphics")]
-extern crate embedded_graphics;
+extern crate embedded_graphics_core;
+
+#[cfg(feature = "defmt")]
+extern crate defmt;
+
+#[cfg(feature = "std")]
+#[macro_use]
+extern crate std;
+
+#[cfg(feature = "softdevice")]
+extern crate critical_section;
 
-use hal::blocking::delay::DelayMs;
+#[cfg(feature = "storage")]
+extern crate embedded_storage;
+
+use hal::blocking::delay::{DelayMs, DelayUs};
 use hal::blocking::spi;
-use hal::digital::v2::OutputPin;
+use hal::digital::v2::{InputPin, OutputPin};
 use hal::spi::{Mode, Phase, Polarity};
+use hal::timer::CountDown;
 
 use core::fmt::Debug;
 use core::iter::IntoIterator;
+use core::marker::PhantomData;
+
+#[cfg(feature = "animation")]
+pub mod animation;
+pub mod color;
+#[cfg(feature = "aligned-dma")]
+pub mod dma;
+#[cfg(feature = "text")]
+pub mod font;
+#[cfg(feature = "power")]
+pub mod power;
+#[cfg(feature = "simulator")]
+pub mod simulator;
+#[cfg(feature = "softdevice")]
+pub mod softdevice;
+#[cfg(feature = "storage")]
+mod storage;
+#[cfg(feature = "touch")]
+pub mod touch;
+#[cfg(feature = "transform")]
+pub mod transform;
 
-/// SPI mode
-pub const MODE: Mode = Mode {
+/// SPI mode, the same as [MODE_0](constant.MODE_0.html)
+pub const MODE: Mode = MODE_0;
+
+/// SPI mode 0: clock idle low, data captured on the rising edge
+///
+/// This is the mode of the datasheet, and works with most modules.
+pub const MODE_0: Mode = Mode {
     polarity: Polarity::IdleLow,
     phase: Phase::CaptureOnFirstTransition,
 };
 
-const WIDTH: usize = 240;
-const HEIGHT: usize = 320;
+/// SPI mode 3: clock idle high, data captured on the rising edge
+///
+/// The controller accepts it as well, since it only looks at the rising
+/// edges. Some clone panels only latch the data reliably in this mode: when
+/// the screen shows garbage with mode 0 although the wiring is right, try
+/// this one, and declare it to the driver with
+/// [with_spi_mode](struct.Ili9341.html#method.with_spi_mode).
+pub const MODE_3: Mode = Mode {
+    polarity: Polarity::IdleHigh,
+    phase: Phase::CaptureOnSecondTransition,
+};
+
+/// Whether the data is captured on the rising edges of the clock, the only
+/// ones the controller samples on
+const fn captures_on_rising_edge(mode: Mode) -> bool {
+    matches!(
+        (mode.polarity, mode.phase),
+        (Polarity::IdleLow, Phase::CaptureOnFirstTransition)
+            | (Polarity::IdleHigh, Phase::CaptureOnSecondTransition)
+    )
+}
+
+const _: () = assert!(captures_on_rising_edge(MODE_0) && captures_on_rising_edge(MODE_3));
+
+/// SPI mode the bus is configured for, as declared to the driver
+///
+/// The driver can't check the configuration of the bus, but records the
+/// declared mode, see [with_spi_mode](struct.Ili9341.html#method.with_spi_mode).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SpiMode {
+    /// [MODE_0](constant.MODE_0.html), the default
+    #[default]
+    Mode0,
+    /// [MODE_3](constant.MODE_3.html)
+    Mode3,
+}
+
+impl SpiMode {
+    /// Mode to configure the SPI peripheral with
+    pub const fn mode(self) -> Mode {
+        match self {
+            SpiMode::Mode0 => MODE_0,
+            SpiMode::Mode3 => MODE_3,
+        }
+    }
+}
+
+/// Width of the panel in pixels, in portrait orientation
+pub const NATIVE_WIDTH: usize = 240;
+/// Height of the panel in pixels, in portrait orientation
+pub const NATIVE_HEIGHT: usize = 320;
+
+/// Size of the buffer used to batch the pixels of iterators into SPI writes
+const CHUNK_SIZE: usize = 64;
+
+/// Width and height in pixels of the tiles of
+/// [draw_raw_transposed](struct.Ili9341.html#method.draw_raw_transposed)
+const TRANSPOSE_TILE: usize = 16;
 
 #[derive(Debug)]
 pub enum Error<SpiE, PinE> {
     Spi(SpiE),
     OutputPin(PinE),
+    /// Reading an input pin failed, like the TE pin
+    InputPin(PinE),
+    /// The requested coordinates are outside of the screen
+    OutOfBounds,
+    /// The pixel data ended in the middle of a pixel
+    IncompletePixel,
+    /// No drawing window was set before continuing a memory write
+    NoWindow,
+    /// The buffer given is too small for the requested operation
+    BufferTooSmall,
+    /// The color pattern given is empty
+    EmptyPattern,
+    /// A value of the [Config](struct.Config.html) given is not allowed by
+    /// the datasheet
+    InvalidConfig,
+    /// The display was powered down with
+    /// [power_down](struct.Ili9341.html#method.power_down)
+    PoweredDown,
+    /// A register read back from the controller doesn't hold the value
+    /// written
+    Mismatch,
+    /// The power state can't be reached from the current one, see
+    /// [DisplayStateMachine](power/struct.DisplayStateMachine.html)
+    InvalidStateTransition,
+    /// The number of pixels given doesn't match the size of the window
+    LengthMismatch,
 }
 
-/// The default orientation is Portrait
-pub enum Orientation {
-    Portrait,
-    PortraitFlipped,
-    Landscape,
-    LandscapeFlipped,
+impl<SpiE: Debug, PinE: Debug> core::fmt::Display for Error<SpiE, PinE> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Error::Spi(e) => write!(f, "SPI error: {:?}", e),
+            Error::OutputPin(e) => write!(f, "pin error: {:?}", e),
+            Error::InputPin(e) => write!(f, "input pin error: {:?}", e),
+            Error::OutOfBounds => f.write_str("coordinates outside of the screen"),
+            Error::IncompletePixel => f.write_str("pixel data ended in the middle of a pixel"),
+            Error::NoWindow => f.write_str("no drawing window set"),
+            Error::BufferTooSmall => f.write_str("buffer too small"),
+            Error::EmptyPattern => f.write_str("empty color pattern"),
+            Error::InvalidConfig => f.write_str("invalid config value"),
+            Error::PoweredDown => f.write_str("display powered down"),
+            Error::Mismatch => f.write_str("register read back doesn't match"),
+            Error::InvalidStateTransition => f.write_str("invalid power state transition"),
+            Error::LengthMismatch => f.write_str("pixel count doesn't match the window"),
+        }
+    }
 }
 
-/// There are two method for drawing to the screen:
-/// [draw_raw](struct.Ili9341.html#method.draw_raw) and
-/// [draw_iter](struct.Ili9341.html#method.draw_iter).
-///
-/// In both cases the expected pixel format is rgb565.
+#[cfg(feature = "std")]
+impl<SpiE: Debug, PinE: Debug> std::error::Error for Error<SpiE, PinE> {}
+
+/// Pixel format used for the data written to the video memory
 ///
-/// The hardware makes it efficient to draw rectangles on the screen.
+/// The default pixel format is Rgb565
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PixelFormat {
+    /// 16 bits per pixel, sent as 2 bytes
+    Rgb565,
+    /// 18 bits per pixel, sent as 3 bytes (6 bits per color, left aligned)
+    Rgb666,
+}
+
+impl PixelFormat {
+    /// Number of bytes sent to the display for each pixel
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Rgb565 => 2,
+            PixelFormat::Rgb666 => 3,
+        }
+    }
+    const fn command_arg(self) -> u8 {
+        match self {
+            PixelFormat::Rgb565 => 0x55,
+            PixelFormat::Rgb666 => 0x66,
+        }
+    }
+    /// Encode a rgb565 color in this format, returning the bytes and how many of them are used
+    fn encode(self, color: u16) -> ([u8; 3], usize) {
+        match self {
+            PixelFormat::Rgb565 => {
+                let [hi, lo] = color.to_be_bytes();
+                ([hi, lo, 0], 2)
+            }
+            PixelFormat::Rgb666 => {
+                let r = ((color >> 11) & 0x1f) as u8;
+                let g = ((color >> 5) & 0x3f) as u8;
+                let b = (color & 0x1f) as u8;
+                // expand 5 bit channels to 6 bits by replicating the msb
+                let r = (r << 1) | (r >> 4);
+                let b = (b << 1) | (b >> 4);
+                ([r << 2, g << 2, b << 2], 3)
+            }
+        }
+    }
+}
+
+/// Whether the window from (x0, y0) to (x1, y1) holds no pixel
+fn is_empty_window(x0: u16, y0: u16, x1: u16, y1: u16) -> bool {
+    x1 < x0 || y1 < y0
+}
+
+/// Serialize a pixel in `format` at the start of `buf`, returning the number
+/// of bytes written
 ///
-/// What happens is the following:
+/// All the paths writing pixels go through here, so that the byte order is
+/// decided in a single place.
+fn push_pixel<P: PixelWord>(buf: &mut [u8], pixel: P, format: PixelFormat) -> usize {
+    let (bytes, len) = pixel.encode(format);
+    buf[..len].copy_from_slice(&bytes[..len]);
+    len
+}
+
+/// Buffer batching pixels into SPI writes
 ///
-/// - A drawing window is prepared (with the 2 opposite corner coordinates)
-/// - The starting point for drawint is the top left corner of this window
-/// - Every pair of bytes received is intepreted as a pixel value in rgb565
-/// - As soon as a pixel is received, an internal counter is incremented,
-///   and the next word will fill the next pixel (the adjacent on the right, or
-///   the first of the next row if the row ended)
-pub struct Ili9341<SPI, CS, DC, RESET> {
-    spi: SPI,
-    cs: CS,
-    dc: DC,
-    reset: RESET,
-    width: usize,
-    height: usize,
+/// It only ever holds whole pixels: it is full as soon as there is no room
+/// left for one more, so a pixel is never split across two writes, which
+/// would shift all the following ones by a byte. The brightness tables, if
+/// any, are applied to the pixels pushed, so that every path drawing rgb565
+/// pixels applies them.
+struct PixelBuf {
+    buf: [u8; CHUNK_SIZE],
+    len: usize,
+    format: PixelFormat,
+    lut: Option<color::BrightnessLut>,
 }
 
-impl<SpiE, PinE, SPI, CS, DC, RESET> Ili9341<SPI, CS, DC, RESET>
-where
-    SPI: spi::Transfer<u8, Error = SpiE> + spi::Write<u8, Error = SpiE>,
-    CS: OutputPin<Error = PinE>,
-    DC: OutputPin<Error = PinE>,
-    RESET: OutputPin<Error = PinE>,
-{
-    pub fn new<DELAY: DelayMs<u16>>(
-        spi: SPI,
-        cs: CS,
-        dc: DC,
-        reset: RESET,
-        delay: &mut DELAY,
-    ) -> Result<Self, Error<SpiE, PinE>> {
-        let mut ili9341 = Ili9341 {
-            spi,
-            cs,
-            dc,
-            reset,
-            width: WIDTH,
-            height: HEIGHT,
+impl PixelBuf {
+    fn new(format: PixelFormat, lut: Option<color::BrightnessLut>) -> Self {
+        PixelBuf {
+            buf: [0; CHUNK_SIZE],
+            len: 0,
+            format,
+            lut,
+        }
+    }
+    /// Whether another pixel can't be pushed
+    fn is_full(&self) -> bool {
+        self.len + self.format.bytes_per_pixel() > CHUNK_SIZE
+    }
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// Add a pixel, the buffer must not be full
+    fn push<P: PixelWord>(&mut self, pixel: P) {
+        let pixel = match self.lut {
+            Some(ref lut) => pixel.adjust(lut),
+            None => pixel,
         };
-
-        ili9341.hard_reset(delay)?;
-        ili9341.command(Command::SoftwareReset, &[])?;
-        delay.delay_ms(200);
-
-        ili9341.command(Command::PowerControlA, &[0x39, 0x2c, 0x00, 0x34, 0x02])?;
-        ili9341.command(Command::PowerControlB, &[0x00, 0xc1, 0x30])?;
-        ili9341.command(Command::DriverTimingControlA, &[0x85, 0x00, 0x78])?;
-        ili9341.command(Command::DriverTimingControlB, &[0x00, 0x00])?;
-        ili9341.command(Command::PowerOnSequenceControl, &[0x64, 0x03, 0x12, 0x81])?;
-        ili9341.command(Command::PumpRatioControl, &[0x20])?;
-        ili9341.command(Command::PowerControl1, &[0x23])?;
-        ili9341.command(Command::PowerControl2, &[0x10])?;
-        ili9341.command(Command::VCOMControl1, &[0x3e, 0x28])?;
-        ili9341.command(Command::VCOMControl2, &[0x86])?;
-        ili9341.command(Command::MemoryAccessControl, &[0x48])?;
-        ili9341.command(Command::PixelFormatSet, &[0x55])?;
-        ili9341.command(Command::FrameControlNormal, &[0x00, 0x18])?;
-        ili9341.command(Command::DisplayFunctionControl, &[0x08, 0x82, 0x27])?;
-        ili9341.command(Command::Enable3G, &[0x00])?;
-        ili9341.command(Command::GammaSet, &[0x01])?;
-        ili9341.command(
-            Command::PositiveGammaCorrection,
-            &[
-                0x0f, 0x31, 0x2b, 0x0c, 0x0e, 0x08, 0x4e, 0xf1, 0x37, 0x07, 0x10, 0x03, 0x0e, 0x09,
-                0x00,
-            ],
-        )?;
-        ili9341.command(
-            Command::NegativeGammaCorrection,
-            &[
-                0x00, 0x0e, 0x14, 0x03, 0x11, 0x07, 0x31, 0xc1, 0x48, 0x08, 0x0f, 0x0c, 0x31, 0x36,
-                0x0f,
-            ],
-        )?;
-        ili9341.command(Command::SleepOut, &[])?;
-        delay.delay_ms(120);
-        ili9341.command(Command::DisplayOn, &[])?;
-
-        Ok(ili9341)
+        self.len += push_pixel(&mut self.buf[self.len..], pixel, self.format);
     }
-
-    fn hard_reset<DELAY: DelayMs<u16>>(
-        &mut self,
-        delay: &mut DELAY,
-    ) -> Result<(), Error<SpiE, PinE>> {
-        // set high if previously low
-        self.reset.set_high().map_err(Error::OutputPin)?;
-        delay.delay_ms(200);
-        // set low for reset
-        self.reset.set_low().map_err(Error::OutputPin)?;
-        delay.delay_ms(200);
-        // set high for normal operation
-        self.reset.set_high().map_err(Error::OutputPin)?;
-        delay.delay_ms(200);
-        Ok(())
+    /// Empty the buffer, returning the bytes it held
+    fn take(&mut self) -> &[u8] {
+        let len = self.len;
+        self.len = 0;
+        &self.buf[..len]
     }
-    fn command(&mut self, cmd: Command, args: &[u8]) -> Result<(), Error<SpiE, PinE>> {
-        self.cs.set_low().map_err(Error::OutputPin)?;
+}
 
-        self.dc.set_low().map_err(Error::OutputPin)?;
-        self.spi.write(&[cmd as u8]).map_err(Error::Spi)?;
+/// Init sequence used by [new](struct.Ili9341.html#method.new), in the
+/// format accepted by
+/// [new_with_init_sequence](struct.Ili9341.html#method.new_with_init_sequence)
+///
+/// It is a software reset, followed by
+/// [POWER_INIT_SEQUENCE](constant.POWER_INIT_SEQUENCE.html),
+/// [DISPLAY_INIT_SEQUENCE](constant.DISPLAY_INIT_SEQUENCE.html) and
+/// [GAMMA_INIT_SEQUENCE](constant.GAMMA_INIT_SEQUENCE.html), then Sleep Out
+/// and Display On.
+///
+/// Each entry is a tuple `(command, args, delay_ms)`, see
+/// [new_with_init_sequence](struct.Ili9341.html#method.new_with_init_sequence).
+pub const DEFAULT_INIT_SEQUENCE: &[(u8, &[u8], u16)] = &DEFAULT_INIT;
 
-        self.dc.set_high().map_err(Error::OutputPin)?;
-        self.spi.write(args).map_err(Error::Spi)?;
+/// [DEFAULT_INIT_SEQUENCE](constant.DEFAULT_INIT_SEQUENCE.html) as an array,
+/// to copy it and patch some of its entries rather than writing a whole
+/// sequence
+///
+/// The sequence is patched at compile time, as the driver keeps it to send it
+/// again on [reinit](struct.Ili9341.html#method.reinit):
+///
+/// ```ignore
+/// static INIT: [(u8, &[u8], u16); 21] = {
+///     let mut init = ili9341::DEFAULT_INIT;
+///     let mut i = 0;
+///     while i < init.len() {
+///         if init[i].0 == Command::VCOMControl1 as u8 {
+///             init[i].1 = &[0x35, 0x3e];
+///         }
+///         i += 1;
+///     }
+///     init
+/// };
+/// let display = Ili9341::new_with_init_sequence(spi, cs, dc, reset, &mut delay, &INIT)?;
+/// ```
+pub const DEFAULT_INIT: [(u8, &[u8], u16); 21] = concat_init(&[
+    RESET_INIT_SEQUENCE,
+    POWER_INIT_SEQUENCE,
+    DISPLAY_INIT_SEQUENCE,
+    GAMMA_INIT_SEQUENCE,
+    WAKE_INIT_SEQUENCE,
+]);
 
-        self.cs.set_high().map_err(Error::OutputPin)?;
-        Ok(())
+/// Concatenate the steps of an init sequence, `N` being the total number of
+/// entries
+const fn concat_init<const N: usize>(
+    steps: &[&[(u8, &'static [u8], u16)]],
+) -> [(u8, &'static [u8], u16); N] {
+    let mut seq: [(u8, &[u8], u16); N] = [(0, &[], 0); N];
+    let mut n = 0;
+    let mut i = 0;
+    while i < steps.len() {
+        let mut j = 0;
+        while j < steps[i].len() {
+            seq[n] = steps[i][j];
+            n += 1;
+            j += 1;
+        }
+        i += 1;
     }
-    fn write_iter<I: IntoIterator<Item = u16>>(
-        &mut self,
-        data: I,
-    ) -> Result<(), Error<SpiE, PinE>> {
-        self.cs.set_low().map_err(Error::OutputPin)?;
+    assert!(n == N, "wrong length of the init sequence");
+    seq
+}
 
-        self.dc.set_low().map_err(Error::OutputPin)?;
-        self.spi
-            .write(&[Command::MemoryWrite as u8])
-            .map_err(Error::Spi)?;
+/// First step of the default init sequence, not overridable
+const RESET_INIT_SEQUENCE: &[(u8, &[u8], u16)] = &[(Command::SoftwareReset as u8, &[], 200)];
 
-        self.dc.set_high().map_err(Error::OutputPin)?;
-        for d in data.into_iter() {
-            self.spi
-                .write(&[(d >> 8) as u8, (d & 0xff) as u8])
-                .map_err(Error::Spi)?;
-        }
+/// Power, VCOM and driver timing settings of the default init sequence, see
+/// [InitSteps](trait.InitSteps.html)
+pub const POWER_INIT_SEQUENCE: &[(u8, &[u8], u16)] = &[
+    (
+        Command::PowerControlA as u8,
+        &[0x39, 0x2c, 0x00, 0x34, 0x02],
+        0,
+    ),
+    (Command::PowerControlB as u8, &[0x00, 0xc1, 0x30], 0),
+    (Command::DriverTimingControlA as u8, &[0x85, 0x00, 0x78], 0),
+    (Command::DriverTimingControlB as u8, &[0x00, 0x00], 0),
+    (
+        Command::PowerOnSequenceControl as u8,
+        &[0x64, 0x03, 0x12, 0x81],
+        0,
+    ),
+    (Command::PumpRatioControl as u8, &[0x20], 0),
+    (Command::PowerControl1 as u8, &[0x23], 0),
+    (Command::PowerControl2 as u8, &[0x10], 0),
+    (Command::VCOMControl1 as u8, &[0x3e, 0x28], 0),
+    (Command::VCOMControl2 as u8, &[0x86], 0),
+];
 
-        self.cs.set_high().map_err(Error::OutputPin)?;
-        Ok(())
-    }
-    fn write_raw(&mut self, data: &[u8]) -> Result<(), Error<SpiE, PinE>> {
-        self.cs.set_low().map_err(Error::OutputPin)?;
+/// Orientation, pixel format, frame rate and display function settings of the
+/// default init sequence, see [InitSteps](trait.InitSteps.html)
+pub const DISPLAY_INIT_SEQUENCE: &[(u8, &[u8], u16)] = &[
+    (
+        Command::MemoryAccessControl as u8,
+        &[Orientation::Portrait.madctl()],
+        0,
+    ),
+    (
+        Command::PixelFormatSet as u8,
+        &[PixelFormat::Rgb565.command_arg()],
+        0,
+    ),
+    (Command::FrameControlNormal as u8, &[0x00, 0x18], 0),
+    (
+        Command::DisplayFunctionControl as u8,
+        &DEFAULT_DISPLAY_FUNCTION,
+        0,
+    ),
+];
 
-        self.dc.set_low().map_err(Error::OutputPin)?;
-        self.spi
-            .write(&[Command::MemoryWrite as u8])
-            .map_err(Error::Spi)?;
+/// Parameters of Display Function Control in the default init sequence: scan
+/// of the non-display area at intervals, normally white panel, scan cycle of 5
+/// frames, 320 lines
+const DEFAULT_DISPLAY_FUNCTION: [u8; 3] = [0x08, 0x82, 0x27];
 
-        self.dc.set_high().map_err(Error::OutputPin)?;
-        self.spi.write(data).map_err(Error::Spi)?;
+/// Gamma settings of the default init sequence, see
+/// [InitSteps](trait.InitSteps.html)
+pub const GAMMA_INIT_SEQUENCE: &[(u8, &[u8], u16)] = &[
+    (Command::Enable3G as u8, &[0x00], 0),
+    (Command::GammaSet as u8, &[0x01], 0),
+    (
+        Command::PositiveGammaCorrection as u8,
+        &[
+            0x0f, 0x31, 0x2b, 0x0c, 0x0e, 0x08, 0x4e, 0xf1, 0x37, 0x07, 0x10, 0x03, 0x0e, 0x09,
+            0x00,
+        ],
+        0,
+    ),
+    (
+        Command::NegativeGammaCorrection as u8,
+        &[
+            0x00, 0x0e, 0x14, 0x03, 0x11, 0x07, 0x31, 0xc1, 0x48, 0x08, 0x0f, 0x0c, 0x31, 0x36,
+            0x0f,
+        ],
+        0,
+    ),
+];
 
-        self.cs.set_high().map_err(Error::OutputPin)?;
-        Ok(())
-    }
-    fn set_window(&mut self, x0: u16, y0: u16, x1: u16, y1: u16) -> Result<(), Error<SpiE, PinE>> {
-        self.command(
-            Command::ColumnAddressSet,
-            &[
-                (x0 >> 8) as u8,
-                (x0 & 0xff) as u8,
-                (x1 >> 8) as u8,
-                (x1 & 0xff) as u8,
-            ],
-        )?;
-        self.command(
-            Command::PageAddressSet,
-            &[
-                (y0 >> 8) as u8,
-                (y0 & 0xff) as u8,
-                (y1 >> 8) as u8,
-                (y1 & 0xff) as u8,
-            ],
-        )?;
-        Ok(())
+/// Last step of the default init sequence, not overridable
+const WAKE_INIT_SEQUENCE: &[(u8, &[u8], u16)] = &[
+    (Command::SleepOut as u8, &[], 120),
+    (Command::DisplayOn as u8, &[], 0),
+];
+
+/// Steps of the default init sequence, to replace only some of them
+///
+/// [new_with_init_steps](struct.Ili9341.html#method.new_with_init_steps)
+/// sends a software reset, then the [power](#method.power),
+/// [display](#method.display) and [gamma](#method.gamma) steps in this order,
+/// then Sleep Out and Display On. Each step is a list of commands in the
+/// format of
+/// [new_with_init_sequence](struct.Ili9341.html#method.new_with_init_sequence),
+/// and defaults to the matching part of
+/// [DEFAULT_INIT_SEQUENCE](constant.DEFAULT_INIT_SEQUENCE.html), so a panel
+/// needing different gamma curves only has to provide those:
+///
+/// ```ignore
+/// struct MyPanel;
+///
+/// impl InitSteps for MyPanel {
+///     fn gamma(&self) -> &[(u8, &[u8], u16)] {
+///         &[(Command::GammaSet as u8, &[0x01], 0), /* ... */]
+///     }
+/// }
+///
+/// let display = Ili9341::new_with_init_steps(spi, cs, dc, reset, &mut delay, &MyPanel)?;
+/// ```
+pub trait InitSteps {
+    /// Power, VCOM and driver timing settings
+    fn power(&self) -> &[(u8, &[u8], u16)] {
+        POWER_INIT_SEQUENCE
     }
-    /// Draw a rectangle on the screen, represented by top-left corner (x0, y0)
-    /// and bottom-right corner (x1, y1).
-    ///
-    /// The border is included.
-    ///
-    /// This method accepts an iterator of rgb565 pixel values.
+    /// Orientation, pixel format, frame rate and display function settings
     ///
-    /// The iterator is useful to avoid wasting memory by holding a buffer for
-    /// the whole screen when it is not necessary.
-    pub fn draw_iter<I: IntoIterator<Item = u16>>(
-        &mut self,
-        x0: u16,
-        y0: u16,
-        x1: u16,
-        y1: u16,
-        data: I,
-    ) -> Result<(), Error<SpiE, PinE>> {
-        self.set_window(x0, y0, x1, y1)?;
-        self.write_iter(data)
+    /// The driver assumes the display is left in portrait orientation with
+    /// the rgb565 pixel format.
+    fn display(&self) -> &[(u8, &[u8], u16)] {
+        DISPLAY_INIT_SEQUENCE
     }
-    /// Draw a rectangle on the screen, represented by top-left corner (x0, y0)
-    /// and bottom-right corner (x1, y1).
-    ///
-    /// The border is included.
-    ///
-    /// This method accepts a raw buffer of bytes that will be copied to the screen
-    /// video memory.
-    ///
-    /// The expected format is rgb565, and the two bytes for a pixel
-    /// are in big endian order.
-    pub fn draw_raw(
-        &mut self,
-        x0: u16,
-        y0: u16,
-        x1: u16,
-        y1: u16,
-        data: &[u8],
-    ) -> Result<(), Error<SpiE, PinE>> {
-        self.set_window(x0, y0, x1, y1)?;
-        self.write_raw(data)
+    /// Gamma settings
+    fn gamma(&self) -> &[(u8, &[u8], u16)] {
+        GAMMA_INIT_SEQUENCE
     }
-    /// Change the orientation of the screen
-    pub fn set_orientation(&mut self, mode: Orientation) -> Result<(), Error<SpiE, PinE>> {
-        match mode {
-            Orientation::Portrait => {
-                self.width = WIDTH;
-                self.height = HEIGHT;
-                self.command(Command::MemoryAccessControl, &[0x40 | 0x08])
-            }
-            Orientation::Landscape => {
-                self.width = HEIGHT;
-                self.height = WIDTH;
-                self.command(Command::MemoryAccessControl, &[0x20 | 0x08])
-            }
-            Orientation::PortraitFlipped => {
-                self.width = WIDTH;
-                self.height = HEIGHT;
-                self.command(Command::MemoryAccessControl, &[0x80 | 0x08])
-            }
-            Orientation::LandscapeFlipped => {
-                self.width = HEIGHT;
-                self.height = WIDTH;
-                self.command(Command::MemoryAccessControl, &[0x40 | 0x80 | 0x20 | 0x08])
-            }
+}
+
+/// Gamma curve selected with Gamma Set, among the four stored in the
+/// controller
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GammaCurve {
+    Curve1,
+    Curve2,
+    Curve3,
+    Curve4,
+}
+
+impl GammaCurve {
+    /// Parameter of Gamma Set for this curve
+    fn args(self) -> &'static [u8] {
+        match self {
+            GammaCurve::Curve1 => &[0x01],
+            GammaCurve::Curve2 => &[0x02],
+            GammaCurve::Curve3 => &[0x04],
+            GammaCurve::Curve4 => &[0x08],
         }
     }
-    /// Get the current screen width. It can change based on the current orientation
-    pub fn width(&self) -> usize {
-        self.width
+}
+
+/// Tuning of the power, gamma and timing settings sent by the default init
+/// sequence
+///
+/// Different modules sometimes need other settings than the default ones,
+/// typically when the screen stays white or blank after the init, or when
+/// the colors look wrong. The fields default to the values of
+/// [DEFAULT_INIT_SEQUENCE](constant.DEFAULT_INIT_SEQUENCE.html):
+///
+/// ```ignore
+/// let config = Config {
+///     pump_ratio_control: 0x30,
+///     ..Config::default()
+/// };
+/// let display = Ili9341::new_with_config(spi, cs, dc, reset, &mut delay, config)?;
+/// ```
+///
+/// Some clones of the controller show a garbled image unless the driver
+/// timings are changed. The known values are:
+///
+/// | Values                        | `driver_timing_a`    | `driver_timing_b` |
+/// |-------------------------------|----------------------|-------------------|
+/// | Default of the driver         | `[0x85, 0x00, 0x78]` | `[0x00, 0x00]`    |
+/// | Power-on default of the chip  | `[0x84, 0x11, 0x7a]` | `[0x66, 0x00]`    |
+///
+/// Please report the values needed by other modules, so they can be added
+/// here.
+///
+/// ```ignore
+/// let config = Config {
+///     driver_timing_a: [0x84, 0x11, 0x7a],
+///     driver_timing_b: [0x66, 0x00],
+///     ..Config::default()
+/// };
+/// let display = Ili9341::new_with_config(spi, cs, dc, reset, &mut delay, config)?;
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Config {
+    /// Parameter of Pump Ratio Control (0xF7): 0x20 for a DDVDH of 2xVCI, 0x30
+    /// for 3xVCI
+    pub pump_ratio_control: u8,
+    /// Parameters of Power On Sequence Control (0xED): soft start, then the
+    /// power on sequence of VCL and DDVDH, then of VGH and VGL, then the
+    /// DDVDH enhance mode (bit 7)
+    pub power_on_sequence: [u8; 4],
+    /// Gamma curve selected, some modules are calibrated for another one than
+    /// the first
+    pub gamma_curve: GammaCurve,
+    /// Parameters of Driver Timing Control A (0xE8): gate driver
+    /// non-overlap timing, EQ and CR timings, pre-charge timing
+    pub driver_timing_a: [u8; 3],
+    /// Parameters of Driver Timing Control B (0xEA): gate driver timings
+    pub driver_timing_b: [u8; 2],
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            pump_ratio_control: 0x20,
+            power_on_sequence: [0x64, 0x03, 0x12, 0x81],
+            gamma_curve: GammaCurve::Curve1,
+            driver_timing_a: [0x85, 0x00, 0x78],
+            driver_timing_b: [0x00, 0x00],
+        }
     }
-    /// Get the current screen heighth. It can change based on the current orientation
-    pub fn height(&self) -> usize {
-        self.height
+}
+
+impl Config {
+    /// Whether all the values are allowed by the datasheet
+    ///
+    /// The reserved bits of each parameter must have their fixed values. For
+    /// `pump_ratio_control` only 0x20 (2xVCI) and 0x30 (3xVCI) are accepted:
+    /// the datasheet defines no other ratio, 0x00 and 0x10 are reserved like
+    /// any value with the low bits set.
+    pub fn is_valid(&self) -> bool {
+        let seq = self.power_on_sequence;
+        let timing = self.driver_timing_a;
+        (self.pump_ratio_control == 0x20 || self.pump_ratio_control == 0x30)
+            && seq[0] & 0xcc == 0x44
+            && seq[1] & 0xcc == 0
+            && seq[2] & 0xcc == 0
+            && seq[3] & 0x7f == 0x01
+            && timing[0] & 0xfe == 0x84
+            && timing[1] & 0xee == 0
+            && timing[2] & 0xfc == 0x78
+    }
+    /// Parameters replacing the default ones of a command of the init
+    /// sequence, if any
+    fn init_args(&self, cmd: u8) -> Option<&[u8]> {
+        match cmd {
+            x if x == Command::PumpRatioControl as u8 => {
+                Some(core::slice::from_ref(&self.pump_ratio_control))
+            }
+            x if x == Command::PowerOnSequenceControl as u8 => Some(&self.power_on_sequence),
+            x if x == Command::GammaSet as u8 => Some(self.gamma_curve.args()),
+            x if x == Command::DriverTimingControlA as u8 => Some(&self.driver_timing_a),
+            x if x == Command::DriverTimingControlB as u8 => Some(&self.driver_timing_b),
+            _ => None,
+        }
     }
 }
 
-#[cfg(feature = "graphics")]
-use embedded_graphics::drawable;
-#[cfg(feature = "graphics")]
-use embedded_graphics::{drawable::Pixel, pixelcolor::Rgb565, Drawing};
+/// The steps of [DEFAULT_INIT_SEQUENCE](constant.DEFAULT_INIT_SEQUENCE.html)
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultInitSteps;
 
-#[cfg(feature = "graphics")]
-impl<SpiE, PinE, SPI, CS, DC, RESET> Drawing<Rgb565> for Ili9341<SPI, CS, DC, RESET>
-where
-    SPI: spi::Transfer<u8, Error = SpiE> + spi::Write<u8, Error = SpiE>,
-    CS: OutputPin<Error = PinE>,
-    DC: OutputPin<Error = PinE>,
-    RESET: OutputPin<Error = PinE>,
-    SpiE: Debug,
-    PinE: Debug,
-{
-    fn draw<T>(&mut self, item_pixels: T)
-    where
-        T: IntoIterator<Item = drawable::Pixel<Rgb565>>,
-    {
-        const BUF_SIZE: usize = 64;
+impl InitSteps for DefaultInitSteps {}
 
-        let mut row: [u8; BUF_SIZE] = [0; BUF_SIZE];
-        let mut i = 0;
-        let mut lasty = 0;
-        let mut startx = 0;
-        let mut endx = 0;
-        let width = self.width as i32;
-        let height = self.height as i32;
+/// Init sequence of the [Minimal](enum.InitProfile.html#variant.Minimal) profile
+///
+/// Its only delay is the 120ms after Sleep Out, which with the 15ms of the
+/// reset pulse makes the 135ms of the profile.
+const MINIMAL_INIT_SEQUENCE: &[(u8, &[u8], u16)] = &[
+    (Command::SleepOut as u8, &[], 120),
+    (
+        Command::PixelFormatSet as u8,
+        &[PixelFormat::Rgb565.command_arg()],
+        0,
+    ),
+    (
+        Command::MemoryAccessControl as u8,
+        &[Orientation::Portrait.madctl()],
+        0,
+    ),
+    (Command::DisplayOn as u8, &[], 0),
+];
 
-        // Filter out pixels that are off the screen
-        let on_screen_pixels = item_pixels.into_iter().filter(|drawable::Pixel(point, _)| {
-            point.x >= 0 && point.y >= 0 && point.x < width && point.y < height
-        });
+/// Representation of a pixel value that can be sent in any pixel format
+///
+/// It is implemented for `u16`, holding a rgb565 value, and for `[u8; 3]`,
+/// holding a rgb666 value as sent in the Rgb666 format (6 bits per color,
+/// left aligned).
+pub trait PixelWord: Copy {
+    /// Encode the pixel in the given format, returning the bytes and how many
+    /// of them are used
+    fn encode(self, format: PixelFormat) -> ([u8; 3], usize);
+    /// Apply a brightness lookup table to the pixel, see
+    /// [set_brightness_lut](struct.Ili9341.html#method.set_brightness_lut)
+    ///
+    /// By default the pixel is returned unchanged, which is what `[u8; 3]`
+    /// does.
+    fn adjust(self, _lut: &color::BrightnessLut) -> Self {
+        self
+    }
+}
 
-        for Pixel(pos, color) in on_screen_pixels {
-            use embedded_graphics::pixelcolor::raw::RawData;
-            // Check if pixel is contiguous with previous pixel
-            if i == 0 || (pos.y == lasty && (pos.x == endx + 1) && i < BUF_SIZE - 1) {
-                if i == 0 {
-                    // New line of pixels
-                    startx = pos.x;
-                }
-                // Add pixel color to buffer
-                for b in embedded_graphics::pixelcolor::raw::RawU16::from(color)
-                    .into_inner()
-                    .to_be_bytes()
-                    .iter()
-                {
-                    row[i] = *b;
-                    i += 1;
-                }
-                lasty = pos.y;
-                endx = pos.x;
-            } else {
-                // Line of contiguous pixels has ended, so draw it now
-                self.draw_raw(
-                    startx as u16,
-                    lasty as u16,
-                    endx as u16,
-                    lasty as u16,
-                    &row[0..i],
-                )
-                .expect("Failed to communicate with device");
+impl PixelWord for u16 {
+    fn encode(self, format: PixelFormat) -> ([u8; 3], usize) {
+        format.encode(self)
+    }
+    fn adjust(self, lut: &color::BrightnessLut) -> Self {
+        lut.apply(self)
+    }
+}
 
-                // Start new line of contiguous pixels
-                i = 0;
-                startx = pos.x;
-                for b in embedded_graphics::pixelcolor::raw::RawU16::from(color)
-                    .into_inner()
-                    .to_be_bytes()
-                    .iter()
-                {
-                    row[i] = *b;
-                    i += 1;
-                }
-                lasty = pos.y;
-                endx = pos.x;
+impl PixelWord for [u8; 3] {
+    fn encode(self, format: PixelFormat) -> ([u8; 3], usize) {
+        match format {
+            PixelFormat::Rgb565 => {
+                let color = (u16::from(self[0] >> 3) << 11)
+                    | (u16::from(self[1] >> 2) << 5)
+                    | u16::from(self[2] >> 3);
+                format.encode(color)
             }
-        }
-        if i > 0 {
-            // Draw remaining pixels in buffer
-            self.draw_raw(
-                startx as u16,
-                lasty as u16,
-                endx as u16,
-                lasty as u16,
-                &row[0..i],
-            )
-            .expect("Failed to communicate with device");
+            PixelFormat::Rgb666 => (self, 3),
         }
     }
 }
 
-#[derive(Clone, Copy)]
-enum Command {
+/// Level of the reset line that holds the display in reset
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ResetPolarity {
+    /// The display is held in reset while the line is low, as wired on most
+    /// boards
+    #[default]
+    ActiveLow,
+    /// The display is held in reset while the line is high, for a reset line
+    /// driven through an inverting level shifter
+    ActiveHigh,
+}
+
+/// Reset or wake up sequence started without waiting for it to complete
+///
+/// It is returned by
+/// [start_hard_reset](struct.Ili9341.html#method.start_hard_reset) and
+/// [start_wake](struct.Ili9341.html#method.start_wake), and must be polled with
+/// [is_ready](struct.Ili9341.html#method.is_ready) until it completes. `TIME`
+/// is the time type of the timer used.
+pub struct PendingOp<TIME> {
+    time: TIME,
+    /// Whether the reset line is still to be released
+    in_reset: bool,
+}
+
+/// Timer waiting on a delay, to run the operations polled with
+/// [is_ready](struct.Ili9341.html#method.is_ready) to completion: `wait`
+/// blocks for the whole time, in milliseconds, instead of polling
+struct DelayTimer<'a, DELAY: 'a> {
+    delay: &'a mut DELAY,
+    ms: u16,
+}
+
+impl<'a, DELAY: DelayMs<u16>> CountDown for DelayTimer<'a, DELAY> {
+    type Time = u16;
+
+    fn start<T: Into<u16>>(&mut self, count: T) {
+        self.ms = count.into();
+    }
+    fn wait(&mut self) -> nb::Result<(), void::Void> {
+        self.delay.delay_ms(self.ms);
+        Ok(())
+    }
+}
+
+/// Initialization sequence sent to the display when it is created
+///
+/// The durations below are the sums of the delays inserted by the driver,
+/// not measurements: the time spent on the SPI bus has to be added on top of
+/// them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InitProfile {
+    /// Short reset pulse, then only SleepOut, PixelFormatSet,
+    /// MemoryAccessControl and DisplayOn.
+    ///
+    /// All the power, timing and gamma registers are left at their power-on
+    /// defaults. The driver waits 135ms in total: 15ms around the reset
+    /// pulse and 120ms after Sleep Out.
+    Minimal,
+    /// Conservative reset and software reset delays, and the full set of
+    /// power, timing and gamma settings.
+    ///
+    /// The driver waits 920ms in total: 600ms around the reset pulse, 200ms
+    /// after Software Reset and 120ms after Sleep Out. This is what
+    /// [new](struct.Ili9341.html#method.new) uses.
+    Full,
+}
+
+/// Delays of the reset and of the wake up, in milliseconds, for
+/// [new_with_delays](struct.Ili9341.html#method.new_with_delays)
+///
+/// They are const generic parameters, so they are fixed at compile time:
+/// the wait before the reset pulse, the length of the pulse, the wait after
+/// it, the wait after Software Reset and the wait after Sleep Out. The
+/// defaults are the minimums of the datasheet, rounded up to the millisecond
+/// for the reset pulse: the delays of the whole init then add up to 265ms,
+/// instead of the 920ms of [new](struct.Ili9341.html#method.new). Boards whose reset line
+/// rises slowly, for example through a weak pull-up or an RC circuit, need
+/// longer reset delays.
+///
+/// The delays are the parameters of this marker type rather than of
+/// `Ili9341` itself because only the initialization uses them: this way the
+/// driver type, and every signature naming it, stays the same whatever the
+/// delays.
+///
+/// ```ignore
+/// // datasheet minimums
+/// let display = Ili9341::new_with_delays(spi, cs, dc, reset, &mut delay, DATASHEET_DELAYS)?;
+/// // a slow reset circuit
+/// let display = Ili9341::new_with_delays(
+///     spi, cs, dc, reset, &mut delay, InitDelays::<10, 50, 150, 5, 120>,
+/// )?;
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct InitDelays<
+    const RESET_HIGH_DELAY_MS: u16 = 10,
+    const RESET_LOW_DELAY_MS: u16 = 10,
+    const RESET_RELEASE_DELAY_MS: u16 = 120,
+    const SOFTWARE_RESET_DELAY_MS: u16 = 5,
+    const SLEEP_OUT_DELAY_MS: u16 = 120,
+>;
+
+/// [InitDelays](struct.InitDelays.html) with the minimums of the datasheet
+pub const DATASHEET_DELAYS: InitDelays = InitDelays;
+
+// Memory Access Control bits
+/// Row address order
+const MADCTL_MY: u8 = 0x80;
+/// Column address order
+const MADCTL_MX: u8 = 0x40;
+/// Row / column exchange
+const MADCTL_MV: u8 = 0x20;
+/// Vertical refresh order
+const MADCTL_ML: u8 = 0x10;
+/// BGR color filter panel
+const MADCTL_BGR: u8 = 0x08;
+/// Horizontal refresh order
+const MADCTL_MH: u8 = 0x04;
+
+/// The default orientation is Portrait
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Orientation {
+    Portrait,
+    PortraitFlipped,
+    Landscape,
+    LandscapeFlipped,
+}
+
+impl Orientation {
+    /// Value of the Memory Access Control register for this orientation
+    const fn madctl(self) -> u8 {
+        match self {
+            Orientation::Portrait => MADCTL_MX | MADCTL_BGR,
+            Orientation::Landscape => MADCTL_MV | MADCTL_BGR,
+            Orientation::PortraitFlipped => MADCTL_MY | MADCTL_BGR,
+            Orientation::LandscapeFlipped => MADCTL_MY | MADCTL_MX | MADCTL_MV | MADCTL_BGR,
+        }
+    }
+    /// Orientation obtained by rotating this one by the given angle
+    fn rotated(self, rotation: Rotation) -> Orientation {
+        const ORDER: [Orientation; 4] = [
+            Orientation::Portrait,
+            Orientation::Landscape,
+            Orientation::PortraitFlipped,
+            Orientation::LandscapeFlipped,
+        ];
+        let index = match self {
+            Orientation::Portrait => 0,
+            Orientation::Landscape => 1,
+            Orientation::PortraitFlipped => 2,
+            Orientation::LandscapeFlipped => 3,
+        };
+        ORDER[(index + rotation as usize) % 4]
+    }
+}
+
+/// Order in which the panel is refreshed from the video memory, set by the
+/// ML and MH bits of Memory Access Control
+///
+/// Unlike the orientation, these bits don't change where the pixels are
+/// shown: they only change the order in which the lines and the pixels of a
+/// line are scanned out, in the native portrait frame of the panel whatever
+/// the orientation. Reversing the vertical refresh order can move or hide
+/// tearing, for example when the drawing code fills the screen from the
+/// bottom up, or remove scan artifacts of some panels. The default is top to
+/// bottom and left to right, as after a reset.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AddressMode {
+    /// Refresh the lines from the bottom to the top of the panel instead of
+    /// from the top (ML)
+    pub vertical_refresh_reversed: bool,
+    /// Refresh the pixels of each line from right to left instead of from
+    /// left to right (MH)
+    pub horizontal_refresh_reversed: bool,
+}
+
+impl AddressMode {
+    /// Bits of the Memory Access Control register for this mode
+    const fn madctl(self) -> u8 {
+        let mut bits = 0;
+        if self.vertical_refresh_reversed {
+            bits |= MADCTL_ML;
+        }
+        if self.horizontal_refresh_reversed {
+            bits |= MADCTL_MH;
+        }
+        bits
+    }
+}
+
+/// Rotation of the display as mounted in the enclosure
+///
+/// Each step of 90 degrees moves an orientation to the next one in the order
+/// Portrait, Landscape, PortraitFlipped, LandscapeFlipped, so with `Deg90`
+/// the logical `Portrait` orientation is displayed as `Landscape`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rotation {
+    Deg0 = 0,
+    Deg90 = 1,
+    Deg180 = 2,
+    Deg270 = 3,
+}
+
+/// An image that can be drawn with
+/// [draw_image](struct.Ili9341.html#method.draw_image)
+///
+/// The pixels are given with their coordinates relative to the top left
+/// corner of the image. Pixels outside of the image are skipped. They can come
+/// in any order, but images producing them row by row, from the top left
+/// corner, are drawn with a single window setup.
+///
+/// With the `graphics` feature it is implemented for the embedded-graphics
+/// images (`ImageDrawable`) whose colors convert to `Rgb565`, like `ImageRaw`
+/// or the bitmaps of tinybmp.
+pub trait DrawImage<P> {
+    /// Pixels of the image, as (x, y, color)
+    fn pixels(&self) -> impl Iterator<Item = (u16, u16, P)>;
+    /// Width of the image
+    fn width(&self) -> u16;
+    /// Height of the image
+    fn height(&self) -> u16;
+}
+
+/// Raw rgb565 image, as stored in flash
+///
+/// The data starts with a 4-byte header holding the width and the height of
+/// the image, as big endian `u16`, followed by the pixels row by row, each as
+/// a big endian rgb565 value (the format of [draw_raw](struct.Ili9341.html#method.draw_raw)).
+///
+/// ```ignore
+/// static IMAGE: &[u8] = include_bytes!("image.bin");
+///
+/// let image = RawImage::new(IMAGE).unwrap();
+/// display.draw_image_raw(0, 0, &image)?;
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct RawImage<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> RawImage<'a> {
+    /// Check the header of an image, returning `None` if the data is too short
+    /// for the size it gives
+    pub fn new(data: &'a [u8]) -> Option<RawImage<'a>> {
+        if data.len() < 4 {
+            return None;
+        }
+        let image = RawImage { data };
+        let len = image.width() as usize * image.height() as usize * 2;
+        if data.len() < 4 + len {
+            return None;
+        }
+        Some(image)
+    }
+    /// Width of the image
+    pub fn width(&self) -> u16 {
+        u16::from(self.data[0]) << 8 | u16::from(self.data[1])
+    }
+    /// Height of the image
+    pub fn height(&self) -> u16 {
+        u16::from(self.data[2]) << 8 | u16::from(self.data[3])
+    }
+    /// Pixel data, without the header
+    pub fn data(&self) -> &'a [u8] {
+        let len = self.width() as usize * self.height() as usize * 2;
+        &self.data[4..4 + len]
+    }
+    /// Pixels of the image as rgb565 values, row by row
+    pub fn pixels(&self) -> impl Iterator<Item = u16> + 'a {
+        self.data()
+            .chunks(2)
+            .map(|p| u16::from(p[0]) << 8 | u16::from(p[1]))
+    }
+}
+
+impl<'a> DrawImage<u16> for RawImage<'a> {
+    fn pixels(&self) -> impl Iterator<Item = (u16, u16, u16)> {
+        let w = self.width();
+        RawImage::pixels(self)
+            .enumerate()
+            .map(move |(i, c)| ((i % w as usize) as u16, (i / w as usize) as u16, c))
+    }
+    fn width(&self) -> u16 {
+        RawImage::width(self)
+    }
+    fn height(&self) -> u16 {
+        RawImage::height(self)
+    }
+}
+
+/// The image is read a row at a time with `draw_sub_image`, so only its
+/// first [NATIVE_HEIGHT](constant.NATIVE_HEIGHT.html) columns, the longest
+/// line of the screen, are given.
+#[cfg(feature = "graphics")]
+impl<T> DrawImage<u16> for T
+where
+    T: embedded_graphics_core::image::ImageDrawable,
+    T::Color: Into<Rgb565>,
+{
+    fn pixels(&self) -> impl Iterator<Item = (u16, u16, u16)> {
+        ImageRows {
+            image: self,
+            row: [0; NATIVE_HEIGHT],
+            width: core::cmp::min(DrawImage::width(self), NATIVE_HEIGHT as u16),
+            height: DrawImage::height(self),
+            x: 0,
+            y: 0,
+        }
+    }
+    fn width(&self) -> u16 {
+        core::cmp::min(self.size().width, u32::from(u16::MAX)) as u16
+    }
+    fn height(&self) -> u16 {
+        core::cmp::min(self.size().height, u32::from(u16::MAX)) as u16
+    }
+}
+
+/// Pixels of an embedded-graphics image, drawn a row at a time into `row`
+#[cfg(feature = "graphics")]
+struct ImageRows<'a, T: 'a> {
+    image: &'a T,
+    row: [u16; NATIVE_HEIGHT],
+    width: u16,
+    height: u16,
+    x: u16,
+    y: u16,
+}
+
+#[cfg(feature = "graphics")]
+impl<'a, T> Iterator for ImageRows<'a, T>
+where
+    T: embedded_graphics_core::image::ImageDrawable,
+    T::Color: Into<Rgb565>,
+{
+    type Item = (u16, u16, u16);
+
+    fn next(&mut self) -> Option<(u16, u16, u16)> {
+        use embedded_graphics_core::geometry::{Point, Size};
+
+        if self.width == 0 || self.y >= self.height {
+            return None;
+        }
+        if self.x == 0 {
+            self.row = [0; NATIVE_HEIGHT];
+            let area = Rectangle::new(
+                Point::new(0, i32::from(self.y)),
+                Size::new(u32::from(self.width), 1),
+            );
+            let mut target = RowTarget {
+                row: &mut self.row[..usize::from(self.width)],
+                color: PhantomData,
+            };
+            match self.image.draw_sub_image(&mut target, &area) {
+                Ok(()) => {}
+                Err(e) => match e {},
+            }
+        }
+        let pixel = (self.x, self.y, self.row[usize::from(self.x)]);
+        self.x += 1;
+        if self.x == self.width {
+            self.x = 0;
+            self.y += 1;
+        }
+        Some(pixel)
+    }
+}
+
+/// Drawing target storing a single row of pixels, as rgb565 values
+#[cfg(feature = "graphics")]
+struct RowTarget<'a, C> {
+    row: &'a mut [u16],
+    color: PhantomData<C>,
+}
+
+#[cfg(feature = "graphics")]
+impl<'a, C> embedded_graphics_core::geometry::OriginDimensions for RowTarget<'a, C> {
+    fn size(&self) -> embedded_graphics_core::geometry::Size {
+        embedded_graphics_core::geometry::Size::new(self.row.len() as u32, 1)
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl<'a, C> embedded_graphics_core::draw_target::DrawTarget for RowTarget<'a, C>
+where
+    C: embedded_graphics_core::pixelcolor::PixelColor + Into<Rgb565>,
+{
+    type Color = C;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = embedded_graphics_core::Pixel<C>>,
+    {
+        for embedded_graphics_core::Pixel(pos, color) in pixels {
+            if pos.y == 0 && pos.x >= 0 && (pos.x as usize) < self.row.len() {
+                self.row[pos.x as usize] = color.into().into_storage();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Drawing surface with the core operations of the display, so that drawing
+/// code can be generic over the display and other implementations
+///
+/// It is implemented for [Ili9341](struct.Ili9341.html), including the
+/// simulator, and the helpers of the crate, like
+/// [Window](struct.Window.html) or the frame pacer of the `animation`
+/// feature, take any surface. Implementing it for a framebuffer lets the same
+/// UI code be tested on the host. The methods follow the ones of `Ili9341`
+/// with the same names, and [fill_rect](#tymethod.fill_rect) fills a
+/// rectangle with a single rgb565 color.
+pub trait Ili9341Surface {
+    type Error;
+
+    /// Current width, following the orientation
+    fn width(&self) -> usize;
+    /// Current height, following the orientation
+    fn height(&self) -> usize;
+    /// Change the orientation
+    fn set_orientation(&mut self, orientation: Orientation) -> Result<(), Self::Error>;
+    /// Draw raw pixel bytes, in the current pixel format, into the rectangle
+    /// from (x0, y0) to (x1, y1), both included
+    fn draw_raw(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        data: &[u8],
+    ) -> Result<(), Self::Error>;
+    /// Draw rgb565 pixels into the rectangle from (x0, y0) to (x1, y1), both
+    /// included
+    fn draw_iter<I: IntoIterator<Item = u16>>(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        data: I,
+    ) -> Result<(), Self::Error>;
+    /// Fill the rectangle from (x0, y0) to (x1, y1), both included, with an
+    /// rgb565 color
+    fn fill_rect(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        color: u16,
+    ) -> Result<(), Self::Error>;
+}
+
+/// Rectangle of the screen to draw into, with both corners included
+///
+/// It names the coordinates that the drawing methods take as four positional
+/// arguments:
+///
+/// ```ignore
+/// Window::at(10, 20).size(32, 16).fill(&mut display, 0xf800)?;
+/// Window::from_corners(0, 0, 239, 9).draw_raw(&mut display, &header)?;
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Window {
+    pub x0: u16,
+    pub y0: u16,
+    pub x1: u16,
+    pub y1: u16,
+}
+
+/// A [Window](struct.Window.html) whose top-left corner is known, returned by
+/// [Window::at](struct.Window.html#method.at)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WindowBuilder {
+    x: u16,
+    y: u16,
+}
+
+impl WindowBuilder {
+    /// Window of `w` by `h` pixels from the top-left corner
+    ///
+    /// A width or height of 0 gives the empty window
+    /// [Window::EMPTY](struct.Window.html#associatedconstant.EMPTY).
+    pub fn size(self, w: u16, h: u16) -> Window {
+        if w == 0 || h == 0 {
+            return Window::EMPTY;
+        }
+        Window {
+            x0: self.x,
+            y0: self.y,
+            x1: self.x.saturating_add(w - 1),
+            y1: self.y.saturating_add(h - 1),
+        }
+    }
+    /// Window from the top-left corner to the bottom-right corner (x, y)
+    pub fn to(self, x: u16, y: u16) -> Window {
+        Window::from_corners(self.x, self.y, x, y)
+    }
+}
+
+impl Window {
+    /// Window holding no pixel: drawing into it sends nothing
+    pub const EMPTY: Window = Window {
+        x0: 1,
+        y0: 1,
+        x1: 0,
+        y1: 0,
+    };
+    /// Start a window with its top-left corner at (x, y)
+    pub fn at(x: u16, y: u16) -> WindowBuilder {
+        WindowBuilder { x, y }
+    }
+    /// Window of `w` by `h` pixels at the top-left corner of the screen
+    ///
+    /// A width or height of 0 gives the empty window
+    /// [EMPTY](#associatedconstant.EMPTY).
+    pub fn size(w: u16, h: u16) -> Self {
+        Window::at(0, 0).size(w, h)
+    }
+    /// Window from the top-left corner (x0, y0) to the bottom-right corner
+    /// (x1, y1)
+    pub fn from_corners(x0: u16, y0: u16, x1: u16, y1: u16) -> Self {
+        Window { x0, y0, x1, y1 }
+    }
+    /// Whether the window holds no pixel, its corners being inverted
+    pub fn is_empty(&self) -> bool {
+        is_empty_window(self.x0, self.y0, self.x1, self.y1)
+    }
+    /// Draw raw pixel bytes into the window, see
+    /// [Ili9341::draw_raw](struct.Ili9341.html#method.draw_raw)
+    pub fn draw_raw<S: Ili9341Surface>(
+        &self,
+        display: &mut S,
+        data: &[u8],
+    ) -> Result<(), S::Error> {
+        display.draw_raw(self.x0, self.y0, self.x1, self.y1, data)
+    }
+    /// Draw rgb565 pixels into the window, see
+    /// [Ili9341::draw_iter](struct.Ili9341.html#method.draw_iter)
+    pub fn draw_iter<S, I>(&self, display: &mut S, data: I) -> Result<(), S::Error>
+    where
+        S: Ili9341Surface,
+        I: IntoIterator<Item = u16>,
+    {
+        display.draw_iter(self.x0, self.y0, self.x1, self.y1, data)
+    }
+    /// Fill the window with a rgb565 color
+    pub fn fill<S: Ili9341Surface>(&self, display: &mut S, color: u16) -> Result<(), S::Error> {
+        display.fill_rect(self.x0, self.y0, self.x1, self.y1, color)
+    }
+}
+
+/// Clear of the whole screen spread over several calls
+///
+/// Clearing the 76800 pixels of the screen takes about 31ms at 40MHz in
+/// rgb565, which is too long for a watchdog with a short window, or for a
+/// main loop with other work to do. Each [step](#method.step) clears a
+/// limited number of pixels, continuing where the previous one stopped:
+///
+/// ```ignore
+/// let mut clear = ClearOperation::new(0x0000);
+/// while !clear.step(&mut display, 8000)? {
+///     watchdog.feed();
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClearOperation {
+    color: u16,
+    /// Pixels cleared so far, row by row from the top left corner
+    cleared: usize,
+}
+
+impl ClearOperation {
+    /// Start clearing the screen with an rgb565 color
+    pub fn new(color: u16) -> Self {
+        ClearOperation { color, cleared: 0 }
+    }
+    /// Number of pixels cleared so far
+    pub fn pixels_cleared(&self) -> usize {
+        self.cleared
+    }
+    /// Clear up to `max_pixels` more pixels, and return whether the whole
+    /// screen is cleared
+    ///
+    /// Each step sets its own windows, at most three: the end of the row
+    /// left by the previous step, whole rows, then the start of the next row.
+    /// So the display can be drawn to between the steps, but its orientation
+    /// must not change until the clear is done. A step that fails is not
+    /// counted, calling it again goes on from the same place.
+    pub fn step<S: Ili9341Surface>(
+        &mut self,
+        display: &mut S,
+        max_pixels: usize,
+    ) -> Result<bool, S::Error> {
+        let (w, h) = (display.width(), display.height());
+        let total = w * h;
+        let mut n = max_pixels.min(total.saturating_sub(self.cleared));
+        while n > 0 {
+            let (x, y) = (self.cleared % w, self.cleared / w);
+            let (x1, y1, count) = if x > 0 || n < w {
+                let len = n.min(w - x);
+                (x + len - 1, y, len)
+            } else {
+                let rows = n / w;
+                (w - 1, y + rows - 1, rows * w)
+            };
+            display.fill_rect(x as u16, y as u16, x1 as u16, y1 as u16, self.color)?;
+            self.cleared += count;
+            n -= count;
+        }
+        Ok(self.cleared >= total)
+    }
+}
+
+/// Number of pixels drawn by a draw fed by an iterator, compared to the size
+/// of its window
+///
+/// It is returned by [draw_iter_counted](struct.Ili9341.html#method.draw_iter_counted)
+/// and [draw_iter_bytes_counted](struct.Ili9341.html#method.draw_iter_bytes_counted),
+/// so that an iterator ending early, which leaves the end of the window with
+/// its previous content, can be detected.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DrawOutcome {
+    /// Pixels sent
+    pub pixels_written: u32,
+    /// Pixels in the window
+    pub window_capacity: u32,
+}
+
+impl DrawOutcome {
+    fn new(x0: u16, y0: u16, x1: u16, y1: u16, pixels_written: usize) -> Self {
+        let w = if x1 >= x0 { u32::from(x1 - x0) + 1 } else { 0 };
+        let h = if y1 >= y0 { u32::from(y1 - y0) + 1 } else { 0 };
+        DrawOutcome {
+            pixels_written: pixels_written as u32,
+            window_capacity: w * h,
+        }
+    }
+    /// Whether the whole window was drawn
+    ///
+    /// Pixels beyond the capacity wrap around to the start of the window, so
+    /// `pixels_written` can also be larger than `window_capacity`.
+    pub fn is_complete(&self) -> bool {
+        self.pixels_written >= self.window_capacity
+    }
+}
+
+/// Counters of the traffic sent to the display, for profiling
+///
+/// Only available with the `stats` feature. All counters wrap around on
+/// overflow.
+#[cfg(feature = "stats")]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// Command bytes sent, including the memory writes
+    pub commands: u32,
+    /// Parameter and pixel bytes sent after the commands
+    pub data_bytes: u32,
+    /// Write calls issued by the driver to the SPI bus
+    pub spi_writes: u32,
+    /// Drawing windows set up
+    pub window_setups: u32,
+}
+
+/// Signalling of the data/command distinction to the display controller
+///
+/// This is implemented for every `OutputPin`, used as the D/C line of a
+/// 4-wire SPI bus, and for [NoDc](struct.NoDc.html), used with a 3-wire SPI
+/// bus where the D/C bit is sent as the ninth bit of every word.
+pub trait DataCommand<SPI, PinE> {
+    type SpiError;
+
+    /// Send a command byte, leaving the bus ready to receive its parameters
+    fn write_command(&mut self, spi: &mut SPI, cmd: u8) -> Result<(), Error<Self::SpiError, PinE>>;
+    /// Send parameter or pixel bytes following a command
+    fn write_data(&mut self, spi: &mut SPI, data: &[u8])
+        -> Result<(), Error<Self::SpiError, PinE>>;
+    /// Send rgb565 pixels following a memory write command
+    ///
+    /// The default sends each pixel as two bytes, most significant first,
+    /// with `write_data`. A transport sending 16-bit words sends the pixels
+    /// as they are.
+    fn write_pixels(
+        &mut self,
+        spi: &mut SPI,
+        pixels: &[u16],
+    ) -> Result<(), Error<Self::SpiError, PinE>> {
+        let mut buf = [0; CHUNK_SIZE];
+        for chunk in pixels.chunks(CHUNK_SIZE / 2) {
+            for (bytes, pixel) in buf.chunks_mut(2).zip(chunk) {
+                bytes.copy_from_slice(&pixel.to_be_bytes());
+            }
+            self.write_data(spi, &buf[..chunk.len() * 2])?;
+        }
+        Ok(())
+    }
+    /// Wait for the bytes written so far to be completely sent
+    ///
+    /// The driver calls it before releasing CS. With the blocking SPI traits
+    /// the bytes are sent when `write` returns, so the default does nothing,
+    /// but a transport for an SPI bus completing its writes in the background
+    /// (for example with DMA) must wait here, otherwise CS would be released
+    /// in the middle of the transfer.
+    fn flush(&mut self, _spi: &mut SPI) -> Result<(), Error<Self::SpiError, PinE>> {
+        Ok(())
+    }
+}
+
+impl<SPI, DC> DataCommand<SPI, DC::Error> for DC
+where
+    SPI: spi::Write<u8>,
+    DC: OutputPin,
+{
+    type SpiError = <SPI as spi::Write<u8>>::Error;
+
+    fn write_command(
+        &mut self,
+        spi: &mut SPI,
+        cmd: u8,
+    ) -> Result<(), Error<Self::SpiError, DC::Error>> {
+        self.set_low().map_err(Error::OutputPin)?;
+        spi.write(&[cmd]).map_err(Error::Spi)?;
+        self.set_high().map_err(Error::OutputPin)
+    }
+    fn write_data(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), Error<Self::SpiError, DC::Error>> {
+        spi.write(data).map_err(Error::Spi)
+    }
+}
+
+/// D/C pin of a 4-wire SPI bus running with 16-bit frames
+///
+/// Some SPI peripherals can send 16-bit words (`spi::Write<u16>`) with less
+/// overhead per byte than 8-bit ones, and an rgb565 pixel is exactly one such
+/// word. With this wrapper around the D/C pin, everything is sent as 16-bit
+/// words, most significant byte first:
+///
+/// - a command byte is preceded by a zero byte, which the controller takes as
+///   a No Operation command
+/// - parameter and pixel bytes are sent in pairs, and an odd number of bytes
+///   is completed with a zero byte, which the controller ignores as an extra
+///   parameter
+///
+/// The pixels sent by the driver in the Rgb565 format always come in whole
+/// words, so this should be used with that pixel format only.
+///
+/// ```ignore
+/// let display = Ili9341::new_16bit(spi, cs, dc, reset, &mut delay)?;
+/// ```
+pub struct Dc16<DC>(pub DC);
+
+/// Display on a 4-wire SPI bus with 16-bit frames, see [Dc16](struct.Dc16.html)
+pub type Ili9341Spi16<SPI, CS, DC, RESET> = Ili9341<SPI, CS, Dc16<DC>, RESET>;
+
+impl<SPI, DC> DataCommand<SPI, DC::Error> for Dc16<DC>
+where
+    SPI: spi::Write<u16>,
+    DC: OutputPin,
+{
+    type SpiError = <SPI as spi::Write<u16>>::Error;
+
+    fn write_command(
+        &mut self,
+        spi: &mut SPI,
+        cmd: u8,
+    ) -> Result<(), Error<Self::SpiError, DC::Error>> {
+        self.0.set_low().map_err(Error::OutputPin)?;
+        spi.write(&[u16::from(cmd)]).map_err(Error::Spi)?;
+        self.0.set_high().map_err(Error::OutputPin)
+    }
+    fn write_data(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), Error<Self::SpiError, DC::Error>> {
+        const BUF_SIZE: usize = 32;
+
+        let mut buf = [0u16; BUF_SIZE];
+        for chunk in data.chunks(BUF_SIZE * 2) {
+            let len = chunk.len().div_ceil(2);
+            for (word, pair) in buf.iter_mut().zip(chunk.chunks(2)) {
+                let lo = pair.get(1).cloned().unwrap_or(0);
+                *word = u16::from(pair[0]) << 8 | u16::from(lo);
+            }
+            spi.write(&buf[..len]).map_err(Error::Spi)?;
+        }
+        Ok(())
+    }
+    fn write_pixels(
+        &mut self,
+        spi: &mut SPI,
+        pixels: &[u16],
+    ) -> Result<(), Error<Self::SpiError, DC::Error>> {
+        spi.write(pixels).map_err(Error::Spi)
+    }
+}
+
+/// SPI bus whose writes can return before the bytes are sent, for example
+/// when they are queued for DMA
+///
+/// See [FlushDc](struct.FlushDc.html).
+pub trait BusFlush {
+    type Error;
+
+    /// Wait for all the bytes written so far to be sent
+    fn flush(&mut self) -> Result<(), Self::Error>;
+}
+
+/// D/C pin of a 4-wire SPI bus implementing [BusFlush](trait.BusFlush.html)
+///
+/// The plain D/C pin assumes that the bytes are sent when `spi::Write::write`
+/// returns. When the writes complete in the background instead, the D/C line
+/// must not change while bytes are still queued, and CS must not be released
+/// either. With this wrapper the bus is flushed before every change of the D/C
+/// line, and, through [DataCommand::flush](trait.DataCommand.html#method.flush),
+/// before the driver releases CS.
+///
+/// ```ignore
+/// let display = Ili9341::new(dma_spi, cs, FlushDc(dc), reset, &mut delay)?;
+/// ```
+pub struct FlushDc<DC>(pub DC);
+
+impl<SPI, DC> DataCommand<SPI, DC::Error> for FlushDc<DC>
+where
+    SPI: spi::Write<u8> + BusFlush<Error = <SPI as spi::Write<u8>>::Error>,
+    DC: OutputPin,
+{
+    type SpiError = <SPI as spi::Write<u8>>::Error;
+
+    fn write_command(
+        &mut self,
+        spi: &mut SPI,
+        cmd: u8,
+    ) -> Result<(), Error<Self::SpiError, DC::Error>> {
+        BusFlush::flush(spi).map_err(Error::Spi)?;
+        self.0.set_low().map_err(Error::OutputPin)?;
+        spi.write(&[cmd]).map_err(Error::Spi)?;
+        BusFlush::flush(spi).map_err(Error::Spi)?;
+        self.0.set_high().map_err(Error::OutputPin)
+    }
+    fn write_data(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), Error<Self::SpiError, DC::Error>> {
+        spi.write(data).map_err(Error::Spi)
+    }
+    fn flush(&mut self, spi: &mut SPI) -> Result<(), Error<Self::SpiError, DC::Error>> {
+        BusFlush::flush(spi).map_err(Error::Spi)
+    }
+}
+
+/// D/C pin of a 4-wire SPI bus that can only write one byte at a time
+///
+/// The driver hands whole buffers of parameters or pixels to
+/// `spi::Write::write`. Some SPI implementations can't take more than a byte,
+/// or a few bytes of hardware FIFO, per call: with this wrapper every byte is
+/// written with its own call. This is much slower, so only use it when the
+/// bus needs it. embedded-hal has no error telling that a write was too long,
+/// so the driver can't fall back to it by itself.
+///
+/// ```ignore
+/// let display = Ili9341::new(spi, cs, StreamingDc(dc), reset, &mut delay)?;
+/// ```
+pub struct StreamingDc<DC>(pub DC);
+
+impl<SPI, DC> DataCommand<SPI, DC::Error> for StreamingDc<DC>
+where
+    SPI: spi::Write<u8>,
+    DC: OutputPin,
+{
+    type SpiError = <SPI as spi::Write<u8>>::Error;
+
+    fn write_command(
+        &mut self,
+        spi: &mut SPI,
+        cmd: u8,
+    ) -> Result<(), Error<Self::SpiError, DC::Error>> {
+        self.0.write_command(spi, cmd)
+    }
+    fn write_data(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), Error<Self::SpiError, DC::Error>> {
+        for &byte in data {
+            spi.write(&[byte]).map_err(Error::Spi)?;
+        }
+        Ok(())
+    }
+}
+
+/// Hook called around the read transactions, to change the SPI clock
+///
+/// The controller only supports reading at much lower clock rates than
+/// writing: about 6.6MHz, against 10MHz (and often more in practice) for
+/// writes. The read methods call [before_read](#tymethod.before_read) before
+/// sending the read command and [after_read](#tymethod.after_read) once CS is
+/// released, so the bus can be slowed down for the read only:
+///
+/// ```ignore
+/// let id = display.read_display_id(&mut |spi: &mut Spi, reading: bool| {
+///     spi.set_frequency(if reading { 4.MHz() } else { 20.MHz() });
+/// })?;
+/// ```
+///
+/// It is implemented for `()`, doing nothing, for a bus already slow enough,
+/// and for closures taking the bus and `true` before the read, `false` after.
+pub trait ReadClock<SPI> {
+    /// Prepare the bus for a read
+    fn before_read(&mut self, spi: &mut SPI);
+    /// Bring the bus back to its normal settings after a read
+    fn after_read(&mut self, spi: &mut SPI);
+}
+
+impl<SPI> ReadClock<SPI> for () {
+    fn before_read(&mut self, _spi: &mut SPI) {}
+    fn after_read(&mut self, _spi: &mut SPI) {}
+}
+
+impl<SPI, F> ReadClock<SPI> for F
+where
+    F: FnMut(&mut SPI, bool),
+{
+    fn before_read(&mut self, spi: &mut SPI) {
+        self(spi, true)
+    }
+    fn after_read(&mut self, spi: &mut SPI) {
+        self(spi, false)
+    }
+}
+
+/// Placeholder for the missing D/C pin of a 3-wire SPI bus
+///
+/// In this mode the SPI peripheral must be configured for 9-bit words and
+/// implement `spi::Write<u16>`: the D/C bit is sent as the most significant
+/// of the 9 bits (0 for a command, 1 for data), followed by the command or
+/// data byte.
+///
+/// Not every microcontroller can do 9-bit SPI words, and not every HAL
+/// exposes it: for example the SPI of the STM32F0/F3/F7/L4 families can (9-bit
+/// data size in CR2), while the one of the STM32F1/F4 families only does 8 or
+/// 16 bits. On the rp2040 the word size is a parameter of the `Spi` type:
+///
+/// ```ignore
+/// let spi = rp2040_hal::Spi::<_, _, _, 9>::new(pac.SPI0, (mosi, sclk))
+///     .init(&mut pac.RESETS, 125.MHz(), 10.MHz(), ili9341::MODE);
+/// let display = Ili9341::new_3wire(spi, cs, reset, &mut delay)?;
+/// ```
+pub struct NoDc;
+
+/// TE pin of a display that is not given to the driver, see
+/// [with_te_pin](struct.Ili9341.html#method.with_te_pin)
+pub struct NoTe;
+
+/// CS pin of a display whose CS line is driven by the SPI device itself
+///
+/// Bus sharing crates hand out one SPI device per chip on the bus, which
+/// selects its chip around each of its transactions. The driver must then
+/// leave CS alone, otherwise the line would be driven from two places. Unlike
+/// a missing CS line, the display is still deselected between the writes,
+/// by the device. Setting this pin does nothing, `E` is the error type of
+/// the other pins.
+///
+/// The driver uses the embedded-hal 0.2 `spi::Write` trait, so a device
+/// implementing the embedded-hal 1.0 `SpiDevice` trait, like the ones of
+/// `embedded-hal-bus`, has to be wrapped in an adapter to it. See
+/// [with_spi_device](struct.Ili9341.html#method.with_spi_device).
+pub struct ExternalCs<E>(PhantomData<fn() -> E>);
+
+impl<E> ExternalCs<E> {
+    pub fn new() -> Self {
+        ExternalCs(PhantomData)
+    }
+}
+
+impl<E> Default for ExternalCs<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> OutputPin for ExternalCs<E> {
+    type Error = E;
+
+    fn set_low(&mut self) -> Result<(), E> {
+        Ok(())
+    }
+    fn set_high(&mut self) -> Result<(), E> {
+        Ok(())
+    }
+}
+
+/// Display on an SPI device managing its CS line, see
+/// [ExternalCs](struct.ExternalCs.html)
+pub type Ili9341SpiDevice<SPI, DC, RESET, E> = Ili9341<SPI, ExternalCs<E>, DC, RESET>;
+
+/// Display on a 3-wire SPI bus, see [NoDc](struct.NoDc.html)
+pub type Ili9341ThreeWire<SPI, CS, RESET> = Ili9341<SPI, CS, NoDc, RESET>;
+
+impl<SPI, PinE> DataCommand<SPI, PinE> for NoDc
+where
+    SPI: spi::Write<u16>,
+{
+    type SpiError = <SPI as spi::Write<u16>>::Error;
+
+    fn write_command(&mut self, spi: &mut SPI, cmd: u8) -> Result<(), Error<Self::SpiError, PinE>> {
+        spi.write(&[u16::from(cmd)]).map_err(Error::Spi)
+    }
+    fn write_data(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), Error<Self::SpiError, PinE>> {
+        const BUF_SIZE: usize = 32;
+
+        let mut buf = [0u16; BUF_SIZE];
+        for chunk in data.chunks(BUF_SIZE) {
+            for (word, byte) in buf.iter_mut().zip(chunk) {
+                *word = 0x100 | u16::from(*byte);
+            }
+            spi.write(&buf[..chunk.len()]).map_err(Error::Spi)?;
+        }
+        Ok(())
+    }
+}
+
+/// There are two method for drawing to the screen:
+/// [draw_raw](struct.Ili9341.html#method.draw_raw) and
+/// [draw_iter](struct.Ili9341.html#method.draw_iter).
+///
+/// In both cases the expected pixel format is rgb565 by default, see
+/// [set_pixel_format](struct.Ili9341.html#method.set_pixel_format).
+///
+/// The hardware makes it efficient to draw rectangles on the screen.
+///
+/// What happens is the following:
+///
+/// - A drawing window is prepared (with the 2 opposite corner coordinates)
+/// - The starting point for drawint is the top left corner of this window
+/// - Every pair of bytes received is intepreted as a pixel value in rgb565
+/// - As soon as a pixel is received, an internal counter is incremented,
+///   and the next word will fill the next pixel (the adjacent on the right, or
+///   the first of the next row if the row ended)
+///
+/// # Sharing between tasks
+///
+/// The driver holds no pointer nor interior mutability of its own, so it is
+/// `Send` whenever the bus, the pins and their error types are, which lets it
+/// be moved into a task or an RTIC resource. A bus shared with other devices
+/// through `Rc` or `RefCell` is not `Send`, and neither is the display then:
+/// share it through a `Mutex` or the RTIC resource locks instead. The same
+/// goes for `Sync`, which is harmless to derive: every method talking to the
+/// controller takes `&mut self`, so two transactions can never run at the
+/// same time through a shared reference. A `&Ili9341` only reaches getters
+/// like [scroll_offset](#method.scroll_offset) reading plain fields, which
+/// is why the display is safe to share even though the bus and pins it
+/// drives are not themselves made for concurrent use.
+pub struct Ili9341<SPI, CS, DC, RESET, TE = NoTe>
+where
+    CS: OutputPin,
+    DC: DataCommand<SPI, CS::Error>,
+{
+    spi: SPI,
+    cs: CS,
+    dc: DC,
+    reset: RESET,
+    /// TE pin, given with `with_te_pin`
+    te: TE,
+    state: State,
+}
+
+/// Init sequence sent by the constructor, kept to send it again on
+/// [reinit](struct.Ili9341.html#method.reinit)
+#[derive(Clone, Copy)]
+enum InitSource {
+    /// [DEFAULT_INIT_SEQUENCE](constant.DEFAULT_INIT_SEQUENCE.html) with the
+    /// settings of the config
+    Default,
+    /// The [Minimal](enum.InitProfile.html#variant.Minimal) profile
+    Minimal,
+    /// A sequence given to `new_with_init_sequence`
+    Sequence(InitSequence),
+    /// The power, display and gamma steps given to `new_with_init_steps`
+    Steps(InitSequence, InitSequence, InitSequence),
+}
+
+/// Init sequence as accepted by
+/// [new_with_init_sequence](struct.Ili9341.html#method.new_with_init_sequence)
+type InitSequence = &'static [(u8, &'static [u8], u16)];
+
+/// Everything the driver tracks besides the bus and the pins, so that it
+/// moves as a whole when [with_te_pin](struct.Ili9341.html#method.with_te_pin)
+/// changes the type of the display
+struct State {
+    reset_polarity: ResetPolarity,
+    /// SPI mode of the bus, as declared by the user
+    spi_mode: SpiMode,
+    width: usize,
+    height: usize,
+    pixel_format: PixelFormat,
+    /// Logical orientation, as set by the user
+    orientation: Orientation,
+    /// Rotation of the display in the enclosure
+    rotation: Rotation,
+    /// Refresh order of the panel, as set by the user
+    address_mode: AddressMode,
+    /// Whether the x and the y axes of the image are mirrored, as set by the
+    /// user
+    mirror: (bool, bool),
+    /// Column range of the window, if it was set since the last reset
+    columns: Option<(u16, u16)>,
+    /// Page range of the window, if it was set since the last reset
+    pages: Option<(u16, u16)>,
+    /// Pixels written into the window since it was set or since the last
+    /// Memory Write
+    written: usize,
+    /// Maximum number of pixel bytes sent while CS is held low, if limited
+    max_transfer: Option<usize>,
+    /// Whether CS is kept low between the transactions by `with_cs_held`
+    cs_held: bool,
+    /// Whether the display is in sleep mode, tracked from the commands sent
+    sleeping: bool,
+    /// Whether the display output is on, tracked from the commands sent
+    display_on: bool,
+    /// Whether the panel supply may be cut, nothing is sent until `power_up`
+    powered_down: bool,
+    /// Whether the tearing effect output was enabled by the user
+    tearing_effect: bool,
+    /// Settings sent with the default init sequence
+    config: Config,
+    /// Init sequence chosen at construction, sent again by `reinit`
+    init: InitSource,
+    /// Parameters of Display Function Control, as set by the user
+    display_function: [u8; 3],
+    /// Lines fixed at the top and at the bottom of the vertical scrolling area
+    scroll_fixed: (u16, u16),
+    /// Vertical scroll offset, within the scrolling area
+    scroll_offset: u16,
+    /// Lookup tables applied to the rgb565 pixels of `draw_words`, if set
+    brightness_lut: Option<color::BrightnessLut>,
+    /// The part of the window left open by the last `set_pixel` call, starting
+    /// at the current position of the address counter
+    #[cfg(feature = "window-cache")]
+    last_window: Option<(u16, u16, u16, u16)>,
+    #[cfg(feature = "stats")]
+    stats: Stats,
+    /// Position of the next character written with `core::fmt::Write`
+    #[cfg(feature = "text")]
+    cursor: (u16, u16),
+    /// Foreground and background colors of the text
+    #[cfg(feature = "text")]
+    text_color: (u16, u16),
+    #[cfg(feature = "text")]
+    font: font::Font,
+}
+
+impl<SpiE, PinE, SPI, CS, DC, RESET> Ili9341<SPI, CS, DC, RESET>
+where
+    CS: OutputPin<Error = PinE>,
+    DC: DataCommand<SPI, PinE, SpiError = SpiE>,
+    RESET: OutputPin<Error = PinE>,
+{
+    /// Create and initialize the display with the
+    /// [Full](enum.InitProfile.html#variant.Full) init profile, that is
+    /// with [DEFAULT_INIT_SEQUENCE](constant.DEFAULT_INIT_SEQUENCE.html)
+    pub fn new<DELAY: DelayMs<u16>>(
+        spi: SPI,
+        cs: CS,
+        dc: DC,
+        reset: RESET,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SpiE, PinE>> {
+        Self::new_with_init_source(spi, cs, dc, reset, delay, InitSource::Default)
+    }
+
+    /// Create and initialize the display with the default init sequence,
+    /// with some of its steps replaced, see [InitSteps](trait.InitSteps.html)
+    ///
+    /// The steps are kept to be sent again by [reinit](#method.reinit), so
+    /// they must live for the whole program, like the `&MyPanel` of a unit
+    /// struct.
+    pub fn new_with_init_steps<DELAY: DelayMs<u16>, S: InitSteps>(
+        spi: SPI,
+        cs: CS,
+        dc: DC,
+        reset: RESET,
+        delay: &mut DELAY,
+        steps: &'static S,
+    ) -> Result<Self, Error<SpiE, PinE>> {
+        let init = InitSource::Steps(steps.power(), steps.display(), steps.gamma());
+        Self::new_with_init_source(spi, cs, dc, reset, delay, init)
+    }
+
+    /// Create and initialize the display with the given init profile
+    pub fn new_with_profile<DELAY: DelayMs<u16>>(
+        spi: SPI,
+        cs: CS,
+        dc: DC,
+        reset: RESET,
+        delay: &mut DELAY,
+        profile: InitProfile,
+    ) -> Result<Self, Error<SpiE, PinE>> {
+        match profile {
+            InitProfile::Full => Self::new(spi, cs, dc, reset, delay),
+            InitProfile::Minimal => {
+                let mut ili9341 = Self::from_parts(spi, cs, dc, reset);
+                ili9341.state.init = InitSource::Minimal;
+                // the datasheet asks for 5ms after the reset is released
+                ili9341.hard_reset_with_delays(delay, 5, 5, 5)?;
+                ili9341.run_init(delay)?;
+                Ok(ili9341)
+            }
+        }
+    }
+
+    /// Create the display, reset it and initialize it with a custom sequence
+    ///
+    /// Each entry of `init` is a tuple `(command, args, delay_ms)`: the command
+    /// byte is sent followed by the argument bytes, then the driver waits for
+    /// `delay_ms` milliseconds (0 means no wait) before sending the next entry.
+    ///
+    /// The driver assumes the sequence leaves the display in the same state as
+    /// [DEFAULT_INIT_SEQUENCE](constant.DEFAULT_INIT_SEQUENCE.html): portrait
+    /// orientation and rgb565 pixel format. The sequence is kept to be sent
+    /// again by [reinit](#method.reinit), so it must be a `static` or a
+    /// `const`, see [DEFAULT_INIT](constant.DEFAULT_INIT.html).
+    pub fn new_with_init_sequence<DELAY: DelayMs<u16>>(
+        spi: SPI,
+        cs: CS,
+        dc: DC,
+        reset: RESET,
+        delay: &mut DELAY,
+        init: &'static [(u8, &'static [u8], u16)],
+    ) -> Result<Self, Error<SpiE, PinE>> {
+        Self::new_with_init_source(spi, cs, dc, reset, delay, InitSource::Sequence(init))
+    }
+
+    fn new_with_init_source<DELAY: DelayMs<u16>>(
+        spi: SPI,
+        cs: CS,
+        dc: DC,
+        reset: RESET,
+        delay: &mut DELAY,
+        init: InitSource,
+    ) -> Result<Self, Error<SpiE, PinE>> {
+        let mut ili9341 = Self::from_parts(spi, cs, dc, reset);
+        ili9341.state.init = init;
+        ili9341.hard_reset(delay)?;
+        ili9341.run_init(delay)?;
+        Ok(ili9341)
+    }
+
+    /// Create and initialize the display like [new](#method.new), with the
+    /// given polarity of the reset line
+    pub fn new_with_reset_polarity<DELAY: DelayMs<u16>>(
+        spi: SPI,
+        cs: CS,
+        dc: DC,
+        reset: RESET,
+        delay: &mut DELAY,
+        polarity: ResetPolarity,
+    ) -> Result<Self, Error<SpiE, PinE>> {
+        let mut ili9341 = Self::from_parts(spi, cs, dc, reset);
+        ili9341.state.reset_polarity = polarity;
+        ili9341.hard_reset(delay)?;
+        ili9341.run_default_init(delay)?;
+        Ok(ili9341)
+    }
+
+    /// Create and initialize the display like [new](#method.new), with the
+    /// given power and gamma settings
+    ///
+    /// If a value of `config` is not allowed by the datasheet,
+    /// `Error::InvalidConfig` is returned and nothing is sent. The settings
+    /// are sent again by [reinit](#method.reinit).
+    pub fn new_with_config<DELAY: DelayMs<u16>>(
+        spi: SPI,
+        cs: CS,
+        dc: DC,
+        reset: RESET,
+        delay: &mut DELAY,
+        config: Config,
+    ) -> Result<Self, Error<SpiE, PinE>> {
+        if !config.is_valid() {
+            return Err(Error::InvalidConfig);
+        }
+        let mut ili9341 = Self::from_parts(spi, cs, dc, reset);
+        ili9341.state.config = config;
+        ili9341.hard_reset(delay)?;
+        ili9341.run_default_init(delay)?;
+        Ok(ili9341)
+    }
+
+    /// Create and initialize the display like [new](#method.new), with the
+    /// reset and wake up delays given at compile time, see
+    /// [InitDelays](struct.InitDelays.html)
+    ///
+    /// Only the initialization uses these delays,
+    /// [hard_reset](#method.hard_reset) and [reinit](#method.reinit) keep
+    /// the conservative ones.
+    pub fn new_with_delays<
+        DELAY: DelayMs<u16>,
+        const RESET_HIGH_DELAY_MS: u16,
+        const RESET_LOW_DELAY_MS: u16,
+        const RESET_RELEASE_DELAY_MS: u16,
+        const SOFTWARE_RESET_DELAY_MS: u16,
+        const SLEEP_OUT_DELAY_MS: u16,
+    >(
+        spi: SPI,
+        cs: CS,
+        dc: DC,
+        reset: RESET,
+        delay: &mut DELAY,
+        _delays: InitDelays<
+            RESET_HIGH_DELAY_MS,
+            RESET_LOW_DELAY_MS,
+            RESET_RELEASE_DELAY_MS,
+            SOFTWARE_RESET_DELAY_MS,
+            SLEEP_OUT_DELAY_MS,
+        >,
+    ) -> Result<Self, Error<SpiE, PinE>> {
+        let mut ili9341 = Self::from_parts(spi, cs, dc, reset);
+        ili9341.hard_reset_with_delays(
+            delay,
+            RESET_HIGH_DELAY_MS,
+            RESET_LOW_DELAY_MS,
+            RESET_RELEASE_DELAY_MS,
+        )?;
+        ili9341.run_default_init_with_delays(delay, |cmd, ms| match cmd {
+            x if x == Command::SoftwareReset as u8 => SOFTWARE_RESET_DELAY_MS,
+            x if x == Command::SleepOut as u8 => SLEEP_OUT_DELAY_MS,
+            _ => ms,
+        })?;
+        Ok(ili9341)
+    }
+
+    fn from_parts(spi: SPI, cs: CS, dc: DC, reset: RESET) -> Self {
+        Ili9341 {
+            spi,
+            cs,
+            dc,
+            reset,
+            te: NoTe,
+            state: State {
+                reset_polarity: ResetPolarity::ActiveLow,
+                spi_mode: SpiMode::default(),
+                width: NATIVE_WIDTH,
+                height: NATIVE_HEIGHT,
+                pixel_format: PixelFormat::Rgb565,
+                orientation: Orientation::Portrait,
+                rotation: Rotation::Deg0,
+                address_mode: AddressMode::default(),
+                mirror: (false, false),
+                columns: None,
+                pages: None,
+                written: 0,
+                max_transfer: None,
+                cs_held: false,
+                sleeping: true,
+                display_on: false,
+                powered_down: false,
+                tearing_effect: false,
+                config: Config::default(),
+                init: InitSource::Default,
+                display_function: DEFAULT_DISPLAY_FUNCTION,
+                scroll_fixed: (0, 0),
+                scroll_offset: 0,
+                brightness_lut: None,
+                #[cfg(feature = "window-cache")]
+                last_window: None,
+                #[cfg(feature = "stats")]
+                stats: Stats::default(),
+                #[cfg(feature = "text")]
+                cursor: (0, 0),
+                #[cfg(feature = "text")]
+                text_color: (0xffff, 0x0000),
+                #[cfg(feature = "text")]
+                font: font::Font::default(),
+            },
+        }
+    }
+}
+
+impl<SpiE, PinE, SPI, CS, DC, RESET, TE> Ili9341<SPI, CS, DC, RESET, TE>
+where
+    CS: OutputPin<Error = PinE>,
+    DC: DataCommand<SPI, PinE, SpiError = SpiE>,
+    RESET: OutputPin<Error = PinE>,
+{
+    /// Send [DEFAULT_INIT_SEQUENCE](constant.DEFAULT_INIT_SEQUENCE.html), with
+    /// the settings of the config
+    fn run_default_init<DELAY: DelayMs<u16>>(
+        &mut self,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        self.run_default_init_with_delays(delay, |_, ms| ms)
+    }
+    /// Send [DEFAULT_INIT_SEQUENCE](constant.DEFAULT_INIT_SEQUENCE.html) like
+    /// `run_default_init`, waiting `delay_ms(cmd, ms)` after each command
+    /// instead of its delay `ms` in the sequence
+    fn run_default_init_with_delays<DELAY, F>(
+        &mut self,
+        delay: &mut DELAY,
+        delay_ms: F,
+    ) -> Result<(), Error<SpiE, PinE>>
+    where
+        DELAY: DelayMs<u16>,
+        F: Fn(u8, u16) -> u16,
+    {
+        let config = self.state.config;
+        for &(cmd, args, ms) in DEFAULT_INIT_SEQUENCE {
+            self.raw_command(cmd, config.init_args(cmd).unwrap_or(args))?;
+            let ms = delay_ms(cmd, ms);
+            if ms > 0 {
+                delay.delay_ms(ms);
+            }
+        }
+        Ok(())
+    }
+    fn run_init_sequence<DELAY: DelayMs<u16>>(
+        &mut self,
+        delay: &mut DELAY,
+        init: &[(u8, &[u8], u16)],
+    ) -> Result<(), Error<SpiE, PinE>> {
+        for &(cmd, args, ms) in init {
+            self.raw_command(cmd, args)?;
+            if ms > 0 {
+                delay.delay_ms(ms);
+            }
+        }
+        Ok(())
+    }
+
+    /// Send the init sequence chosen at construction
+    ///
+    /// Apart from the default one, the sequences don't know about
+    /// [set_gamma_curve](#method.set_gamma_curve), so a curve other than the
+    /// first is selected again after them.
+    fn run_init<DELAY: DelayMs<u16>>(
+        &mut self,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        match self.state.init {
+            InitSource::Default => return self.run_default_init(delay),
+            InitSource::Minimal => self.run_init_sequence(delay, MINIMAL_INIT_SEQUENCE)?,
+            InitSource::Sequence(init) => self.run_init_sequence(delay, init)?,
+            InitSource::Steps(power, display, gamma) => {
+                self.run_init_sequence(delay, RESET_INIT_SEQUENCE)?;
+                self.run_init_sequence(delay, power)?;
+                self.run_init_sequence(delay, display)?;
+                self.run_init_sequence(delay, gamma)?;
+                self.run_init_sequence(delay, WAKE_INIT_SEQUENCE)?;
+            }
+        }
+        let curve = self.state.config.gamma_curve;
+        if curve != GammaCurve::Curve1 {
+            self.command(Command::GammaSet, curve.args())?;
+        }
+        Ok(())
+    }
+    /// Pulse the reset line of the display
+    ///
+    /// This brings all the registers of the controller back to their power-on
+    /// defaults and leaves it sleeping with the output off, so the display
+    /// must be initialized again afterwards, for example with
+    /// [reinit](#method.reinit).
+    pub fn hard_reset<DELAY: DelayMs<u16>>(
+        &mut self,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        self.hard_reset_with_delays(delay, 200, 200, 200)
+    }
+    /// Reset the display and send it the init sequence chosen at
+    /// construction again, with the settings given to
+    /// [new_with_config](#method.new_with_config) and
+    /// [set_gamma_curve](#method.set_gamma_curve) if any, then restore the
+    /// current orientation, pixel format, display function control, tearing
+    /// effect output and vertical scrolling
+    ///
+    /// The init sequence is the one of [new](#method.new),
+    /// [new_with_profile](#method.new_with_profile),
+    /// [new_with_init_sequence](#method.new_with_init_sequence) or
+    /// [new_with_init_steps](#method.new_with_init_steps), whichever created
+    /// the display.
+    pub fn reinit<DELAY: DelayMs<u16>>(
+        &mut self,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        self.hard_reset(delay)?;
+        self.run_init(delay)?;
+        self.resync()?;
+        if self.state.display_function != DEFAULT_DISPLAY_FUNCTION {
+            let args = self.state.display_function;
+            self.set_display_function(args)?;
+        }
+        if self.state.tearing_effect {
+            self.set_tearing_effect(true)?;
+        }
+        if self.state.scroll_fixed != (0, 0) || self.state.scroll_offset != 0 {
+            let (top, bottom) = self.state.scroll_fixed;
+            self.set_scroll_area(top, bottom)?;
+        }
+        Ok(())
+    }
+    /// Turn the display off and put it to sleep, so that its supply can be
+    /// cut
+    ///
+    /// Display Off and Sleep In are sent, stopping the booster, then the driver
+    /// refuses to send anything, returning `Error::PoweredDown`, until
+    /// [power_up](#method.power_up) is called. The supply of the panel can be
+    /// cut once this returns: the controller loses all its registers, which
+    /// `power_up` sends again.
+    pub fn power_down<DELAY: DelayMs<u16>>(
+        &mut self,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        self.command(Command::DisplayOff, &[])?;
+        self.sleep(delay)?;
+        self.state.powered_down = true;
+        Ok(())
+    }
+    /// Initialize the display again after [power_down](#method.power_down),
+    /// once its supply is back
+    ///
+    /// This is a [reinit](#method.reinit): the display is reset and
+    /// initialized with the init sequence it was created with, then the
+    /// orientation, pixel
+    /// format, display function control, tearing effect output and vertical
+    /// scrolling set before powering down are restored. If this fails, the
+    /// display stays powered down and this can be called again.
+    pub fn power_up<DELAY: DelayMs<u16>>(
+        &mut self,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        // the commands of the init sequence are refused while powered down
+        self.state.powered_down = false;
+        let result = self.reinit(delay);
+        self.state.powered_down = result.is_err();
+        result
+    }
+    /// Whether the display was powered down with
+    /// [power_down](#method.power_down) and not powered up since
+    pub fn is_powered_down(&self) -> bool {
+        self.state.powered_down
+    }
+    /// Pulse the reset line, waiting `high_ms` before the pulse, `low_ms`
+    /// during it and `release_ms` after it
+    ///
+    /// The pulse itself is [start_hard_reset](#method.start_hard_reset)
+    /// polled to completion, with a timer blocking on `delay`.
+    fn hard_reset_with_delays<DELAY: DelayMs<u16>>(
+        &mut self,
+        delay: &mut DELAY,
+        high_ms: u16,
+        low_ms: u16,
+        release_ms: u16,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        // release if previously held in reset
+        self.release_reset()?;
+        delay.delay_ms(high_ms);
+        let mut timer = DelayTimer { delay, ms: 0 };
+        let mut pending = self.start_reset_pulse(&mut timer, low_ms, release_ms)?;
+        while !self.is_ready(&mut pending, &mut timer)? {}
+        Ok(())
+    }
+    /// Start pulsing the reset line, without blocking
+    ///
+    /// The reset line is asserted and `timer` is started with `time`. The
+    /// returned operation must then be polled with
+    /// [is_ready](#method.is_ready), which releases the reset line when the
+    /// timer expires and waits for `time` again before reporting the display
+    /// ready. The datasheet asks for at least 10µs of reset pulse and 5ms
+    /// after the release before sending commands,
+    /// [hard_reset](#method.hard_reset) uses 200ms for both.
+    ///
+    /// Like after [hard_reset](#method.hard_reset), the display must be
+    /// initialized again once ready.
+    pub fn start_hard_reset<T>(
+        &mut self,
+        timer: &mut T,
+        time: T::Time,
+    ) -> Result<PendingOp<T::Time>, Error<SpiE, PinE>>
+    where
+        T: CountDown,
+        T::Time: Clone,
+    {
+        self.start_reset_pulse(timer, time.clone(), time)
+    }
+    /// Assert the reset line for `pulse`, the returned operation then waits
+    /// `release` after releasing it
+    fn start_reset_pulse<T: CountDown>(
+        &mut self,
+        timer: &mut T,
+        pulse: T::Time,
+        release: T::Time,
+    ) -> Result<PendingOp<T::Time>, Error<SpiE, PinE>> {
+        if let Err(e) = self.assert_reset() {
+            // don't leave the display held in reset
+            let _ = self.release_reset();
+            return Err(e);
+        }
+        timer.start(pulse);
+        self.reset_state();
+        Ok(PendingOp {
+            time: release,
+            in_reset: true,
+        })
+    }
+    /// Start bringing the display out of sleep mode, without blocking
+    ///
+    /// Sleep Out is sent and `timer` is started with `time`, which should be
+    /// at least 120ms for the supply and oscillator to stabilize. The returned
+    /// operation must then be polled with [is_ready](#method.is_ready).
+    pub fn start_wake<T>(
+        &mut self,
+        timer: &mut T,
+        time: T::Time,
+    ) -> Result<PendingOp<T::Time>, Error<SpiE, PinE>>
+    where
+        T: CountDown,
+        T::Time: Clone,
+    {
+        self.command(Command::SleepOut, &[])?;
+        timer.start(time.clone());
+        Ok(PendingOp {
+            time,
+            in_reset: false,
+        })
+    }
+    /// Poll an operation started with
+    /// [start_hard_reset](#method.start_hard_reset) or
+    /// [start_wake](#method.start_wake)
+    ///
+    /// Returns `Ok(true)` when the display can accept commands again. `timer`
+    /// must be the one given when starting the operation, and must not be used
+    /// for anything else until then.
+    pub fn is_ready<T>(
+        &mut self,
+        pending: &mut PendingOp<T::Time>,
+        timer: &mut T,
+    ) -> Result<bool, Error<SpiE, PinE>>
+    where
+        T: CountDown,
+        T::Time: Clone,
+    {
+        if timer.wait().is_err() {
+            return Ok(false);
+        }
+        if pending.in_reset {
+            pending.in_reset = false;
+            self.release_reset()?;
+            timer.start(pending.time.clone());
+            return Ok(false);
+        }
+        Ok(true)
+    }
+    fn reset_state(&mut self) {
+        // the display comes out of reset sleeping, with the output off
+        self.state.sleeping = true;
+        self.state.display_on = false;
+        self.state.columns = None;
+        self.state.pages = None;
+        self.state.written = 0;
+        #[cfg(feature = "window-cache")]
+        {
+            self.state.last_window = None;
+        }
+    }
+    fn assert_reset(&mut self) -> Result<(), Error<SpiE, PinE>> {
+        match self.state.reset_polarity {
+            ResetPolarity::ActiveLow => self.reset.set_low(),
+            ResetPolarity::ActiveHigh => self.reset.set_high(),
+        }
+        .map_err(Error::OutputPin)
+    }
+    fn release_reset(&mut self) -> Result<(), Error<SpiE, PinE>> {
+        match self.state.reset_polarity {
+            ResetPolarity::ActiveLow => self.reset.set_high(),
+            ResetPolarity::ActiveHigh => self.reset.set_low(),
+        }
+        .map_err(Error::OutputPin)
+    }
+    /// Send a command with its parameters
+    ///
+    /// This is meant for the commands the driver does not wrap yet. The driver
+    /// tracks the sleep and display on/off state from the commands sent, but
+    /// is not aware of other state changes, like orientation or pixel format.
+    pub fn send_command(&mut self, cmd: Command, args: &[u8]) -> Result<(), Error<SpiE, PinE>> {
+        self.command(cmd, args)
+    }
+    /// Send a No Operation command
+    ///
+    /// It has no effect on the controller, except ending a memory write or
+    /// read in progress, so it can be used as a harmless transaction, for
+    /// timing or to keep a bus alive, or to cleanly end a pixel stream before
+    /// the next command.
+    pub fn nop(&mut self) -> Result<(), Error<SpiE, PinE>> {
+        self.command(Command::Nop, &[])
+    }
+    fn command(&mut self, cmd: Command, args: &[u8]) -> Result<(), Error<SpiE, PinE>> {
+        self.raw_command(cmd as u8, args)
+    }
+    /// Send a command with its parameters coming from an iterator
+    ///
+    /// This is [send_command](#method.send_command) for parameters computed on
+    /// the fly, like gamma curves, which don't have to be collected in a
+    /// buffer first.
+    pub fn send_command_iter<I: IntoIterator<Item = u8>>(
+        &mut self,
+        cmd: Command,
+        args: I,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        self.begin_command(cmd as u8)?;
+        let mut buf = [0; CHUNK_SIZE];
+        let mut i = 0;
+        for b in args {
+            buf[i] = b;
+            i += 1;
+            if i == CHUNK_SIZE {
+                self.write_data(&buf)?;
+                i = 0;
+            }
+        }
+        if i > 0 {
+            self.write_data(&buf[..i])?;
+        }
+        self.end_command(cmd as u8)
+    }
+    fn raw_command(&mut self, cmd: u8, args: &[u8]) -> Result<(), Error<SpiE, PinE>> {
+        self.begin_command(cmd)?;
+        self.write_data(args)?;
+        self.end_command(cmd)
+    }
+    /// Assert CS and send a command byte, the parameters following with
+    /// `write_data`
+    fn begin_command(&mut self, cmd: u8) -> Result<(), Error<SpiE, PinE>> {
+        if self.state.powered_down {
+            return Err(Error::PoweredDown);
+        }
+        // any command can move the address counter or change the window
+        #[cfg(feature = "window-cache")]
+        {
+            self.state.last_window = None;
+        }
+
+        self.select()?;
+
+        self.write_command(cmd)
+    }
+    /// Release CS after a command and its parameters, and track the state
+    /// changed by the command
+    fn end_command(&mut self, cmd: u8) -> Result<(), Error<SpiE, PinE>> {
+        self.end_transaction()?;
+
+        match cmd {
+            x if x == Command::SleepIn as u8 => self.state.sleeping = true,
+            x if x == Command::SleepOut as u8 => self.state.sleeping = false,
+            x if x == Command::DisplayOff as u8 => self.state.display_on = false,
+            x if x == Command::DisplayOn as u8 => self.state.display_on = true,
+            _ => {}
+        }
+        Ok(())
+    }
+    /// Buffer for the pixels of a memory write, in the current pixel format
+    /// and with the brightness tables
+    fn pixel_buf(&self) -> PixelBuf {
+        PixelBuf::new(self.state.pixel_format, self.state.brightness_lut)
+    }
+    fn write_iter<P: PixelWord, I: IntoIterator<Item = P>>(
+        &mut self,
+        data: I,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        self.write_memory_iter(Command::MemoryWrite, data)
+    }
+    fn write_memory_iter<P: PixelWord, I: IntoIterator<Item = P>>(
+        &mut self,
+        cmd: Command,
+        data: I,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        self.begin_memory_write(cmd)?;
+
+        let mut buf = self.pixel_buf();
+        let mut sent = 0;
+        for d in data.into_iter() {
+            if buf.is_full() {
+                self.write_pixel_data(buf.take(), &mut sent)?;
+            }
+            buf.push(d);
+        }
+        if !buf.is_empty() {
+            self.write_pixel_data(buf.take(), &mut sent)?;
+        }
+
+        self.end_transaction()?;
+        Ok(())
+    }
+    fn write_raw(&mut self, data: &[u8]) -> Result<(), Error<SpiE, PinE>> {
+        self.write_memory(Command::MemoryWrite, data)
+    }
+    fn write_memory_continue(&mut self, data: &[u8]) -> Result<(), Error<SpiE, PinE>> {
+        self.write_memory(Command::MemoryWriteContinue, data)
+    }
+    fn write_memory(&mut self, cmd: Command, data: &[u8]) -> Result<(), Error<SpiE, PinE>> {
+        self.begin_memory_write(cmd)?;
+        self.write_pixel_data(data, &mut 0)?;
+
+        self.end_transaction()?;
+        Ok(())
+    }
+    /// Assert CS and send a memory write command, the pixels following with
+    /// `write_pixel_data`
+    fn begin_memory_write(&mut self, cmd: Command) -> Result<(), Error<SpiE, PinE>> {
+        if self.state.powered_down {
+            return Err(Error::PoweredDown);
+        }
+        self.select()?;
+
+        self.write_command(cmd as u8)?;
+        if cmd == Command::MemoryWrite {
+            self.state.written = 0;
+        }
+        Ok(())
+    }
+    /// Write pixel data after a memory write command, `sent` being the number
+    /// of bytes already sent since CS was asserted
+    ///
+    /// With a transfer size limit, CS is released when the limit is reached,
+    /// then asserted again and the write goes on with Memory Write Continue.
+    fn write_pixel_data(&mut self, data: &[u8], sent: &mut usize) -> Result<(), Error<SpiE, PinE>> {
+        let bpp = self.state.pixel_format.bytes_per_pixel();
+        let max = match self.state.max_transfer {
+            Some(max) => max,
+            None => {
+                self.write_data(data)?;
+                self.state.written += data.len() / bpp;
+                return Ok(());
+            }
+        };
+        // only split between pixels
+        let max = core::cmp::max(max - max % bpp, bpp);
+        let mut data = data;
+        while !data.is_empty() {
+            if *sent >= max {
+                self.end_transaction()?;
+                self.select()?;
+                self.write_command(Command::MemoryWriteContinue as u8)?;
+                *sent = 0;
+            }
+            let n = core::cmp::min(data.len(), max - *sent);
+            self.write_data(&data[..n])?;
+            self.state.written += n / bpp;
+            *sent += n;
+            data = &data[n..];
+        }
+        Ok(())
+    }
+    /// Write rgb565 pixels after a memory write command, as they are, like
+    /// [write_pixel_data](#method.write_pixel_data) does with bytes
+    fn write_pixel_words(&mut self, pixels: &[u16]) -> Result<(), Error<SpiE, PinE>> {
+        // only split between pixels
+        let max = self
+            .state
+            .max_transfer
+            .map_or(pixels.len(), |max| core::cmp::max(max / 2, 1));
+        for (i, chunk) in pixels.chunks(max).enumerate() {
+            if i > 0 {
+                self.end_transaction()?;
+                self.select()?;
+                self.write_command(Command::MemoryWriteContinue as u8)?;
+            }
+            #[cfg(feature = "stats")]
+            {
+                let bytes = (chunk.len() * 2) as u32;
+                self.state.stats.data_bytes = self.state.stats.data_bytes.wrapping_add(bytes);
+                self.state.stats.spi_writes = self.state.stats.spi_writes.wrapping_add(1);
+            }
+            self.dc.write_pixels(&mut self.spi, chunk)?;
+            self.state.written += chunk.len();
+        }
+        Ok(())
+    }
+    /// Assert CS, unless it is held by `with_cs_held`
+    fn select(&mut self) -> Result<(), Error<SpiE, PinE>> {
+        if self.state.cs_held {
+            return Ok(());
+        }
+        self.cs.set_low().map_err(Error::OutputPin)
+    }
+    /// Wait for the transfer to complete, then release CS unless it is held
+    fn end_transaction(&mut self) -> Result<(), Error<SpiE, PinE>> {
+        self.flush()?;
+        if self.state.cs_held {
+            return Ok(());
+        }
+        self.cs.set_high().map_err(Error::OutputPin)
+    }
+    fn write_command(&mut self, cmd: u8) -> Result<(), Error<SpiE, PinE>> {
+        #[cfg(feature = "stats")]
+        {
+            self.state.stats.commands = self.state.stats.commands.wrapping_add(1);
+            self.state.stats.spi_writes = self.state.stats.spi_writes.wrapping_add(1);
+        }
+        self.dc.write_command(&mut self.spi, cmd)
+    }
+    fn write_data(&mut self, data: &[u8]) -> Result<(), Error<SpiE, PinE>> {
+        // commands without parameters: some SPI implementations reject
+        // zero-length writes
+        if data.is_empty() {
+            return Ok(());
+        }
+        #[cfg(feature = "stats")]
+        {
+            self.state.stats.data_bytes =
+                self.state.stats.data_bytes.wrapping_add(data.len() as u32);
+            self.state.stats.spi_writes = self.state.stats.spi_writes.wrapping_add(1);
+        }
+        self.dc.write_data(&mut self.spi, data)
+    }
+    fn set_window(&mut self, x0: u16, y0: u16, x1: u16, y1: u16) -> Result<(), Error<SpiE, PinE>> {
+        #[cfg(feature = "stats")]
+        {
+            self.state.stats.window_setups = self.state.stats.window_setups.wrapping_add(1);
+        }
+        self.address_set(Command::ColumnAddressSet, x0, x1)?;
+        self.address_set(Command::PageAddressSet, y0, y1)
+    }
+    fn address_set(&mut self, cmd: Command, start: u16, end: u16) -> Result<(), Error<SpiE, PinE>> {
+        self.command(
+            cmd,
+            &[
+                (start >> 8) as u8,
+                (start & 0xff) as u8,
+                (end >> 8) as u8,
+                (end & 0xff) as u8,
+            ],
+        )?;
+        match cmd {
+            Command::ColumnAddressSet => self.state.columns = Some((start, end)),
+            _ => self.state.pages = Some((start, end)),
+        }
+        self.state.written = 0;
+        Ok(())
+    }
+    /// Set only the column range (x0 to x1, both included) of the drawing window
+    ///
+    /// The range must be on the screen for the current orientation, otherwise
+    /// `Error::OutOfBounds` is returned and nothing is sent.
+    pub fn set_column_address(&mut self, x0: u16, x1: u16) -> Result<(), Error<SpiE, PinE>> {
+        if x0 > x1 || x1 as usize >= self.state.width {
+            return Err(Error::OutOfBounds);
+        }
+        self.address_set(Command::ColumnAddressSet, x0, x1)
+    }
+    /// Set only the page (row) range (y0 to y1, both included) of the drawing window
+    ///
+    /// The range must be on the screen for the current orientation, otherwise
+    /// `Error::OutOfBounds` is returned and nothing is sent.
+    pub fn set_page_address(&mut self, y0: u16, y1: u16) -> Result<(), Error<SpiE, PinE>> {
+        if y0 > y1 || y1 as usize >= self.state.height {
+            return Err(Error::OutOfBounds);
+        }
+        self.address_set(Command::PageAddressSet, y0, y1)
+    }
+    /// Draw a rectangle on the screen, represented by top-left corner (x0, y0)
+    /// and bottom-right corner (x1, y1).
+    ///
+    /// The border is included.
+    ///
+    /// This method accepts an iterator of rgb565 pixel values. They are
+    /// converted to the current pixel format before being sent.
+    ///
+    /// The iterator is useful to avoid wasting memory by holding a buffer for
+    /// the whole screen when it is not necessary.
+    ///
+    /// If the iterator is empty or the window is empty (x1 < x0 or y1 < y0)
+    /// nothing is sent, not even the window.
+    ///
+    /// An iterator ending before the window is full leaves the rest of the
+    /// window unchanged, see [draw_iter_counted](#method.draw_iter_counted) to
+    /// detect it.
+    pub fn draw_iter<I: IntoIterator<Item = u16>>(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        data: I,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        self.draw_words(x0, y0, x1, y1, data)
+    }
+    /// Same as [draw_iter](#method.draw_iter), checking first that the
+    /// iterator has exactly one pixel for each pixel of the window
+    ///
+    /// If it has fewer or more, `Error::LengthMismatch` is returned and
+    /// nothing is sent. This catches a wrong number of pixels before it
+    /// leaves part of the window unchanged or wraps around to its start;
+    /// `draw_iter` remains for the iterators whose length isn't known.
+    pub fn draw_iter_rgb565<I>(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        data: I,
+    ) -> Result<(), Error<SpiE, PinE>>
+    where
+        I: IntoIterator<Item = u16>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        if is_empty_window(x0, y0, x1, y1) {
+            return Ok(());
+        }
+        let data = data.into_iter();
+        let area = (usize::from(x1 - x0) + 1) * (usize::from(y1 - y0) + 1);
+        if data.len() != area {
+            return Err(Error::LengthMismatch);
+        }
+        self.draw_iter(x0, y0, x1, y1, data)
+    }
+    /// Same as [draw_iter](#method.draw_iter), for any pixel representation
+    ///
+    /// The pixels are converted to the current pixel format by their
+    /// [PixelWord](trait.PixelWord.html) implementation.
+    pub fn draw_words<P: PixelWord, I: IntoIterator<Item = P>>(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        data: I,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        self.draw_iter_counted(x0, y0, x1, y1, data).map(|_| ())
+    }
+    /// Draw a rectangle like [draw_iter](#method.draw_iter), from palette
+    /// indices
+    ///
+    /// Each index is mapped to the rgb565 color at that position in `palette`
+    /// while streaming, so a palettized image takes a byte per pixel and needs
+    /// no buffer to be expanded. Indices outside of the palette are drawn
+    /// black.
+    pub fn draw_indexed<I: IntoIterator<Item = u8>>(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        indices: I,
+        palette: &[u16],
+    ) -> Result<(), Error<SpiE, PinE>> {
+        let pixels = indices
+            .into_iter()
+            .map(|i| palette.get(usize::from(i)).cloned().unwrap_or(0));
+        self.draw_iter(x0, y0, x1, y1, pixels)
+    }
+    /// Same as [draw_words](#method.draw_words), also returning how many
+    /// pixels were drawn compared to the size of the window
+    pub fn draw_iter_counted<P: PixelWord, I: IntoIterator<Item = P>>(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        data: I,
+    ) -> Result<DrawOutcome, Error<SpiE, PinE>> {
+        let mut data = data.into_iter().peekable();
+        if is_empty_window(x0, y0, x1, y1) || data.peek().is_none() {
+            return Ok(DrawOutcome::new(x0, y0, x1, y1, 0));
+        }
+        self.set_window(x0, y0, x1, y1)?;
+        self.write_iter(data)?;
+        Ok(DrawOutcome::new(x0, y0, x1, y1, self.state.written))
+    }
+    /// Draw a rectangle on the screen, represented by top-left corner (x0, y0)
+    /// and bottom-right corner (x1, y1).
+    ///
+    /// The border is included.
+    ///
+    /// This method accepts an iterator of bytes already in the current pixel
+    /// format, with the bytes of a pixel in big endian order, like
+    /// [draw_raw](#method.draw_raw). It is useful for data coming from
+    /// decoders or external memory a byte at a time.
+    ///
+    /// The number of bytes must be a multiple of the pixel size. If the
+    /// iterator ends in the middle of a pixel, the bytes of that last pixel are
+    /// dropped and `Error::IncompletePixel` is returned. If the iterator or
+    /// the window is empty nothing is sent, not even the window.
+    pub fn draw_iter_bytes<I: IntoIterator<Item = u8>>(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        data: I,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        self.draw_iter_bytes_counted(x0, y0, x1, y1, data)
+            .map(|_| ())
+    }
+    /// Same as [draw_iter_bytes](#method.draw_iter_bytes), also returning how
+    /// many pixels were drawn compared to the size of the window
+    pub fn draw_iter_bytes_counted<I: IntoIterator<Item = u8>>(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        data: I,
+    ) -> Result<DrawOutcome, Error<SpiE, PinE>> {
+        let mut data = data.into_iter().peekable();
+        if is_empty_window(x0, y0, x1, y1) || data.peek().is_none() {
+            return Ok(DrawOutcome::new(x0, y0, x1, y1, 0));
+        }
+        self.set_window(x0, y0, x1, y1)?;
+
+        self.begin_memory_write(Command::MemoryWrite)?;
+
+        // flush only whole pixels, so a pixel is never split across writes
+        let bpp = self.state.pixel_format.bytes_per_pixel();
+        let chunk = CHUNK_SIZE - CHUNK_SIZE % bpp;
+        let mut buf = [0; CHUNK_SIZE];
+        let mut i = 0;
+        let mut sent = 0;
+        for b in data {
+            buf[i] = b;
+            i += 1;
+            if i == chunk {
+                self.write_pixel_data(&buf[..i], &mut sent)?;
+                i = 0;
+            }
+        }
+        let whole = i - i % bpp;
+        if whole > 0 {
+            self.write_pixel_data(&buf[..whole], &mut sent)?;
+        }
+
+        self.end_transaction()?;
+        if whole != i {
+            return Err(Error::IncompletePixel);
+        }
+        Ok(DrawOutcome::new(x0, y0, x1, y1, self.state.written))
+    }
+    /// Draw a rectangle on the screen, represented by top-left corner (x0, y0)
+    /// and bottom-right corner (x1, y1).
+    ///
+    /// The border is included.
+    ///
+    /// This method accepts a raw buffer of bytes that will be copied to the screen
+    /// video memory.
+    ///
+    /// The expected format is the current pixel format (rgb565 by default), and
+    /// the bytes for a pixel are in big endian order.
+    ///
+    /// If `data` or the window is empty nothing is sent, not even the window.
+    pub fn draw_raw(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        data: &[u8],
+    ) -> Result<(), Error<SpiE, PinE>> {
+        if is_empty_window(x0, y0, x1, y1) || data.is_empty() {
+            return Ok(());
+        }
+        self.set_window(x0, y0, x1, y1)?;
+        self.write_raw(data)
+    }
+    /// Draw a rectangle like [draw_raw](#method.draw_raw), from raw bytes
+    /// coming in chunks
+    ///
+    /// The window is set once and all the chunks are sent in order after a
+    /// single Memory Write, so data streamed in blocks, for example from an
+    /// SD card, can be drawn without being gathered in one buffer. The chunks
+    /// may have any length: a pixel split between two chunks is sent whole.
+    ///
+    /// The caller is responsible for the total length, like with `draw_raw`:
+    /// more bytes than the window holds wrap around to its start, fewer leave
+    /// the end of the window unchanged. If the chunks end in the middle of a
+    /// pixel, the bytes of that last pixel are dropped and
+    /// `Error::IncompletePixel` is returned. If there are no bytes or the
+    /// window is empty nothing is sent, not even the window.
+    pub fn draw_raw_chunks<'a, I: IntoIterator<Item = &'a [u8]>>(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        chunks: I,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        let mut chunks = chunks.into_iter().filter(|c| !c.is_empty()).peekable();
+        if is_empty_window(x0, y0, x1, y1) || chunks.peek().is_none() {
+            return Ok(());
+        }
+        self.set_window(x0, y0, x1, y1)?;
+
+        self.begin_memory_write(Command::MemoryWrite)?;
+        let bpp = self.state.pixel_format.bytes_per_pixel();
+        // bytes of a pixel split between two chunks
+        let mut pixel = [0; 3];
+        let mut n = 0;
+        let mut sent = 0;
+        for mut chunk in chunks {
+            if n > 0 {
+                let take = core::cmp::min(bpp - n, chunk.len());
+                pixel[n..n + take].copy_from_slice(&chunk[..take]);
+                n += take;
+                chunk = &chunk[take..];
+                if n < bpp {
+                    continue;
+                }
+                self.write_pixel_data(&pixel[..bpp], &mut sent)?;
+            }
+            let whole = chunk.len() - chunk.len() % bpp;
+            if whole > 0 {
+                self.write_pixel_data(&chunk[..whole], &mut sent)?;
+            }
+            n = chunk.len() - whole;
+            pixel[..n].copy_from_slice(&chunk[whole..]);
+        }
+
+        self.end_transaction()?;
+        if n > 0 {
+            return Err(Error::IncompletePixel);
+        }
+        Ok(())
+    }
+    /// Draw a rectangle like [draw_raw](#method.draw_raw), handing the whole
+    /// data to a single SPI write
+    ///
+    /// This is the fastest path with an SPI HAL sending each write as one DMA
+    /// transfer: the window is set, Memory Write is sent, then `data` is
+    /// written at once, ignoring the limit set with
+    /// [set_max_transfer_size](#method.set_max_transfer_size). The HAL must
+    /// accept writes of that length: DMA controllers often limit the size of
+    /// a transfer (for example 65535 bytes on the STM32 and the nRF52840,
+    /// 255 bytes on the nRF52832), while a full screen is 153600 bytes in the
+    /// rgb565 pixel format. A HAL exceeding its limit may fail, or silently
+    /// send only part of the data; use [draw_raw](#method.draw_raw) with a
+    /// transfer size limit for those. The 3-wire and 16-bit transports still
+    /// convert the data in chunks.
+    pub fn blit_dma(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        data: &[u8],
+    ) -> Result<(), Error<SpiE, PinE>> {
+        if is_empty_window(x0, y0, x1, y1) || data.is_empty() {
+            return Ok(());
+        }
+        self.set_window(x0, y0, x1, y1)?;
+        self.begin_memory_write(Command::MemoryWrite)?;
+        self.write_data(data)?;
+        self.state.written += data.len() / self.state.pixel_format.bytes_per_pixel();
+        self.end_transaction()
+    }
+    /// Draw a rectangle like [draw_raw](#method.draw_raw), from an image
+    /// whose rows become the columns of the rectangle
+    ///
+    /// `data` holds `src_rows` rows of `src_cols` pixels, in the current pixel
+    /// format, and the pixel at row `r` and column `c` is drawn at
+    /// (x0 + r, y0 + c). This turns a frame arriving row by row along the
+    /// wrong axis, like the landscape frames of a camera on a portrait panel,
+    /// into the order of the display without a second buffer. Together with
+    /// the orientation, it gives any rotation or mirroring of the image.
+    ///
+    /// The image is transposed in tiles of 16x16 pixels, so that each tile
+    /// is read from 16 short runs of `data` rather than from a pixel of every
+    /// row, and each tile is drawn with its own window, in destination row
+    /// order. The tiles on the right and bottom edges are smaller when the
+    /// sizes are not multiples of 16.
+    ///
+    /// The window must be `src_rows` pixels wide and `src_cols` pixels high,
+    /// otherwise `Error::OutOfBounds` is returned, and `data` must hold the
+    /// whole image, otherwise `Error::BufferTooSmall` is. If the window is
+    /// empty nothing is sent.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_raw_transposed(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        data: &[u8],
+        src_rows: u16,
+        src_cols: u16,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        if is_empty_window(x0, y0, x1, y1) {
+            return Ok(());
+        }
+        let (rows, cols) = (usize::from(src_rows), usize::from(src_cols));
+        let bpp = self.state.pixel_format.bytes_per_pixel();
+        if data.len() < rows * cols * bpp {
+            return Err(Error::BufferTooSmall);
+        }
+        if usize::from(x1 - x0) + 1 != rows || usize::from(y1 - y0) + 1 != cols {
+            return Err(Error::OutOfBounds);
+        }
+
+        let stride = cols * bpp;
+        // room for a whole tile in rgb666, the largest format
+        let mut tile = [0; TRANSPOSE_TILE * TRANSPOSE_TILE * 3];
+        // a band of destination rows is a band of columns of the image
+        for c0 in (0..cols).step_by(TRANSPOSE_TILE) {
+            let h = core::cmp::min(TRANSPOSE_TILE, cols - c0);
+            for r0 in (0..rows).step_by(TRANSPOSE_TILE) {
+                let w = core::cmp::min(TRANSPOSE_TILE, rows - r0);
+                for j in 0..w {
+                    let src = &data[(r0 + j) * stride + c0 * bpp..][..h * bpp];
+                    for (i, pixel) in src.chunks_exact(bpp).enumerate() {
+                        let d = (i * w + j) * bpp;
+                        tile[d..d + bpp].copy_from_slice(pixel);
+                    }
+                }
+                let (tx, ty) = (x0 + r0 as u16, y0 + c0 as u16);
+                self.set_window(tx, ty, tx + w as u16 - 1, ty + h as u16 - 1)?;
+                self.begin_memory_write(Command::MemoryWrite)?;
+                self.write_pixel_data(&tile[..w * h * bpp], &mut 0)?;
+                self.end_transaction()?;
+            }
+        }
+        Ok(())
+    }
+    /// Draw `n_columns` columns of pixels from row y0 to row y1, starting at
+    /// column x0, with a single window
+    ///
+    /// `columns` holds the raw pixel bytes column after column, each from y0
+    /// down to y1, in the current pixel format. This is the order of plots
+    /// adding a column per sample, like waterfall displays, which would
+    /// otherwise need a window per column. The row / column exchange bit of
+    /// Memory Access Control is flipped for the transfer, so that the
+    /// controller fills the window column by column, then restored, even if
+    /// the transfer fails.
+    ///
+    /// If the columns are not inside the screen, `Error::OutOfBounds` is
+    /// returned, and if `columns` holds less than `n_columns` columns,
+    /// `Error::BufferTooSmall` is. If there are no columns nothing is sent.
+    pub fn draw_columns(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        y1: u16,
+        columns: &[u8],
+        n_columns: u16,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        let n = usize::from(n_columns);
+        if usize::from(x0) + n > self.state.width || usize::from(y1) >= self.state.height {
+            return Err(Error::OutOfBounds);
+        }
+        if n == 0 || y1 < y0 {
+            return Ok(());
+        }
+        let len = n * usize::from(y1 - y0 + 1) * self.state.pixel_format.bytes_per_pixel();
+        if columns.len() < len {
+            return Err(Error::BufferTooSmall);
+        }
+        let madctl = self.madctl();
+        self.command(Command::MemoryAccessControl, &[madctl ^ MADCTL_MV])?;
+        // the rows of the exchanged addressing are the columns of the screen
+        let res = self
+            .set_window(y0, x0, y1, x0 + n_columns - 1)
+            .and_then(|_| self.write_raw(&columns[..len]));
+        let restored = self.command(Command::MemoryAccessControl, &[madctl]);
+        res.and(restored)
+    }
+    /// Draw a rectangle like [draw_raw](#method.draw_raw), from a slice of
+    /// rgb565 pixels
+    ///
+    /// The pixels are converted to the current pixel format like with
+    /// [draw_iter](#method.draw_iter). In the Rgb565 format without
+    /// [brightness tables](#method.set_brightness_lut) there is nothing to
+    /// convert, and the slice is given as is to
+    /// [DataCommand::write_pixels](trait.DataCommand.html#method.write_pixels):
+    /// with 16-bit SPI frames (see [Dc16](struct.Dc16.html)) it goes straight
+    /// to `spi::Write<u16>`, each pixel as a single word.
+    pub fn draw_raw_slice(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        data: &[u16],
+    ) -> Result<(), Error<SpiE, PinE>> {
+        if self.state.pixel_format != PixelFormat::Rgb565 || self.state.brightness_lut.is_some() {
+            return self.draw_iter(x0, y0, x1, y1, data.iter().cloned());
+        }
+        if is_empty_window(x0, y0, x1, y1) || data.is_empty() {
+            return Ok(());
+        }
+        self.set_window(x0, y0, x1, y1)?;
+        self.begin_memory_write(Command::MemoryWrite)?;
+        self.write_pixel_words(data)?;
+        self.end_transaction()
+    }
+    /// Draw a rectangle like [draw_raw](#method.draw_raw), converting the
+    /// pixels to 16 levels of gray
+    ///
+    /// `data` holds rgb565 pixels, as big endian bytes, whatever the current
+    /// pixel format, see [color::rgb565_to_gray4](color/fn.rgb565_to_gray4.html)
+    /// for the conversion. If its length is odd, `Error::IncompletePixel` is
+    /// returned and nothing is drawn.
+    pub fn draw_raw_as_grayscale(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        data: &[u8],
+    ) -> Result<(), Error<SpiE, PinE>> {
+        if data.len() % 2 != 0 {
+            return Err(Error::IncompletePixel);
+        }
+        if is_empty_window(x0, y0, x1, y1) || data.is_empty() {
+            return Ok(());
+        }
+        self.set_window(x0, y0, x1, y1)?;
+        self.write_iter(
+            data.chunks(2)
+                .map(|p| color::rgb565_to_gray4(u16::from_be_bytes([p[0], p[1]]))),
+        )
+    }
+    /// Draw a rectangle like [draw_iter](#method.draw_iter), leaving the
+    /// pixels equal to `transparent` untouched
+    ///
+    /// Up to the first row holding a transparent pixel, the rows are sent
+    /// into a single window, as with `draw_iter`. From there on, each run of
+    /// non-transparent pixels on a row is sent with its own window, so
+    /// sprites with few transparent pixels are drawn almost as fast.
+    pub fn draw_iter_with_color_key<I: IntoIterator<Item = u16>>(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        data: I,
+        transparent: u16,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        if is_empty_window(x0, y0, x1, y1) {
+            return Ok(());
+        }
+        self.draw_keyed(x0, y0, x1, y1, data.into_iter(), transparent)
+    }
+    /// Draw the pixels of the window (x0, y0)-(x1, y1), row by row, leaving
+    /// the ones equal to `key` untouched, the window must not be empty
+    ///
+    /// The rows are read into a buffer, by segments of at most
+    /// `NATIVE_HEIGHT` pixels. Up to the first segment holding a key pixel,
+    /// they are all sent into a single window over the whole rectangle, so
+    /// pixels without any key are drawn as fast as with `draw_iter`. From
+    /// there on, each run of non-key pixels of a segment is sent with its own
+    /// window.
+    fn draw_keyed<I: Iterator<Item = u16>>(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        mut data: I,
+        key: u16,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        let mut segment = [0u16; NATIVE_HEIGHT];
+        // whether no key pixel was found yet, and if so the buffer of the
+        // window over the whole rectangle, once opened
+        let mut single_window = true;
+        let mut open: Option<PixelBuf> = None;
+        let mut sent = 0;
+        let (mut x, mut y) = (x0, y0);
+        loop {
+            let len = core::cmp::min(usize::from(x1 - x) + 1, NATIVE_HEIGHT);
+            let mut n = 0;
+            for (slot, color) in segment[..len].iter_mut().zip(&mut data) {
+                *slot = color;
+                n += 1;
+            }
+            if n == 0 {
+                break;
+            }
+            let segment = &segment[..n];
+            if single_window && !segment.contains(&key) {
+                let buf = match open {
+                    Some(ref mut buf) => buf,
+                    None => {
+                        self.set_window(x0, y0, x1, y1)?;
+                        self.begin_memory_write(Command::MemoryWrite)?;
+                        open.get_or_insert(self.pixel_buf())
+                    }
+                };
+                for &color in segment {
+                    if buf.is_full() {
+                        self.write_pixel_data(buf.take(), &mut sent)?;
+                    }
+                    buf.push(color);
+                }
+            } else {
+                if let Some(mut buf) = open.take() {
+                    self.write_pixel_data(buf.take(), &mut sent)?;
+                    self.end_transaction()?;
+                }
+                single_window = false;
+                let mut start = 0;
+                while start < n {
+                    // skip the key pixels, then find the end of the run
+                    match segment[start..].iter().position(|&c| c != key) {
+                        Some(i) => start += i,
+                        None => break,
+                    }
+                    let end = segment[start..]
+                        .iter()
+                        .position(|&c| c == key)
+                        .map_or(n, |i| start + i);
+                    let rx = x + start as u16;
+                    self.set_window(rx, y, rx + (end - start) as u16 - 1, y)?;
+                    self.write_iter(segment[start..end].iter().cloned())?;
+                    start = end;
+                }
+            }
+            if n < len {
+                break;
+            }
+            if x + (len as u16 - 1) == x1 {
+                if y == y1 {
+                    break;
+                }
+                x = x0;
+                y += 1;
+            } else {
+                x += len as u16;
+            }
+        }
+        if let Some(mut buf) = open.take() {
+            self.write_pixel_data(buf.take(), &mut sent)?;
+            self.end_transaction()?;
+        }
+        Ok(())
+    }
+    /// Draw a bitmap of size `w` x `h` at (x, y), with 1 bit per pixel: the
+    /// pixels whose bit is set in `fg` and the others in `bg`
+    ///
+    /// `bits` holds the rows one after the other, each starting on a new
+    /// byte, with the leftmost pixel in the most significant bit. A row takes
+    /// `(w + 7) / 8` bytes, and the padding bits after its last pixel are
+    /// ignored. This is the usual layout of the glyphs of bitmap fonts: a
+    /// glyph or a whole run of text is drawn with a single window, its bits
+    /// expanded to the pixel format as they are sent, without a buffer of
+    /// pixels in between.
+    ///
+    /// If `bits` holds less than `h` rows, `Error::BufferTooSmall` is
+    /// returned, and if the bitmap doesn't fit on the screen,
+    /// `Error::OutOfBounds`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_bitmap_1bpp(
+        &mut self,
+        x: u16,
+        y: u16,
+        w: u16,
+        h: u16,
+        bits: &[u8],
+        fg: u16,
+        bg: u16,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        let (w, h) = (usize::from(w), usize::from(h));
+        let stride = w.div_ceil(8);
+        if bits.len() < stride * h {
+            return Err(Error::BufferTooSmall);
+        }
+        if usize::from(x) + w > self.state.width || usize::from(y) + h > self.state.height {
+            return Err(Error::OutOfBounds);
+        }
+        if w == 0 || h == 0 {
+            return Ok(());
+        }
+        let pixels = bits[..stride * h]
+            .chunks(stride)
+            .flat_map(|row| (0..w).map(move |i| row[i / 8] & (0x80 >> (i % 8)) != 0))
+            .map(|on| if on { fg } else { bg });
+        self.draw_iter(x, y, x + w as u16 - 1, y + h as u16 - 1, pixels)
+    }
+    /// Draw an image of size `w` x `h` at (x, y), leaving the pixels equal to
+    /// `key` untouched
+    ///
+    /// `data` holds the pixels row by row, as big endian rgb565 values. The
+    /// rows are drawn like with
+    /// [draw_iter_with_color_key](#method.draw_iter_with_color_key): up to the
+    /// first row holding a key pixel, they go into a single window, so an
+    /// image without any key pixel is drawn as fast as with
+    /// [draw_iter](#method.draw_iter). The following rows are split into
+    /// their runs of non-key pixels, each drawn with its own window, so rows
+    /// without any of them send nothing.
+    ///
+    /// If `data` holds less than `w * h` pixels, `Error::BufferTooSmall` is
+    /// returned, and if the image doesn't fit on the screen,
+    /// `Error::OutOfBounds`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_image_keyed(
+        &mut self,
+        x: u16,
+        y: u16,
+        w: u16,
+        h: u16,
+        data: &[u8],
+        key: u16,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        let (w, h) = (usize::from(w), usize::from(h));
+        if data.len() < w * h * 2 {
+            return Err(Error::BufferTooSmall);
+        }
+        if usize::from(x) + w > self.state.width || usize::from(y) + h > self.state.height {
+            return Err(Error::OutOfBounds);
+        }
+        if w == 0 || h == 0 {
+            return Ok(());
+        }
+        let pixels = data[..w * h * 2]
+            .chunks(2)
+            .map(|p| u16::from_be_bytes([p[0], p[1]]));
+        let (x1, y1) = (x + w as u16 - 1, y + h as u16 - 1);
+        self.draw_keyed(x, y, x1, y1, pixels, key)
+    }
+    /// Fill a rectangle by repeating a pattern of rgb565 colors
+    ///
+    /// The rectangle is represented by top-left corner (x0, y0) and
+    /// bottom-right corner (x1, y1), the border is included. Every row starts
+    /// with the first color of the pattern at x0 and repeats it up to x1, so
+    /// for example a pattern of two colors draws vertical stripes.
+    ///
+    /// If `pattern` is empty, `Error::EmptyPattern` is returned. If x1 < x0 or
+    /// y1 < y0 nothing is drawn.
+    pub fn fill_pattern(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        pattern: &[u16],
+    ) -> Result<(), Error<SpiE, PinE>> {
+        if pattern.is_empty() {
+            return Err(Error::EmptyPattern);
+        }
+        if is_empty_window(x0, y0, x1, y1) {
+            return Ok(());
+        }
+        let w = usize::from(x1 - x0) + 1;
+        let h = usize::from(y1 - y0) + 1;
+        self.set_window(x0, y0, x1, y1)?;
+        self.write_iter((0..h).flat_map(|_| pattern.iter().cycle().take(w).cloned()))
+    }
+    /// Draw a region of a framebuffer holding the whole screen
+    ///
+    /// `fb` holds `fb_width` pixels per row, in the current pixel format, and
+    /// the region of size `w` x `h` at (x, y) in it is drawn at the same
+    /// position on the screen. Only the region is sent, row by row, with a
+    /// single window setup.
+    ///
+    /// If the region is not inside both the framebuffer and the screen,
+    /// `Error::OutOfBounds` is returned. An empty region draws nothing.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_framebuffer_region(
+        &mut self,
+        fb: &[u8],
+        fb_width: u16,
+        x: u16,
+        y: u16,
+        w: u16,
+        h: u16,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        let bpp = self.state.pixel_format.bytes_per_pixel();
+        let stride = fb_width as usize * bpp;
+        let fb_height = fb.len().checked_div(stride).unwrap_or(0);
+        let (x, y, w, h) = (x as usize, y as usize, w as usize, h as usize);
+        if x + w > fb_width as usize
+            || y + h > fb_height
+            || x + w > self.state.width
+            || y + h > self.state.height
+        {
+            return Err(Error::OutOfBounds);
+        }
+        if w == 0 || h == 0 {
+            return Ok(());
+        }
+
+        self.set_window(x as u16, y as u16, (x + w - 1) as u16, (y + h - 1) as u16)?;
+
+        self.begin_memory_write(Command::MemoryWrite)?;
+        let mut sent = 0;
+        for row in y..y + h {
+            let start = row * stride + x * bpp;
+            self.write_pixel_data(&fb[start..start + w * bpp], &mut sent)?;
+        }
+
+        self.end_transaction()?;
+        Ok(())
+    }
+    /// Draw a rectangle cut out of a larger image, like a tile of a tilemap
+    ///
+    /// `src` holds rgb565 pixels, `src_width` per row, and `region` is the
+    /// rectangle `(x, y, w, h)` of it to draw, with its top-left corner at
+    /// `dst` on the screen. Only the pixels of the region are sent, row by
+    /// row, with a single window setup.
+    ///
+    /// If the region is not inside the image, or its destination not inside
+    /// the screen, `Error::OutOfBounds` is returned. An empty region draws
+    /// nothing.
+    pub fn draw_sub_image(
+        &mut self,
+        dst: (u16, u16),
+        src: &[u16],
+        src_width: u16,
+        region: (u16, u16, u16, u16),
+    ) -> Result<(), Error<SpiE, PinE>> {
+        let stride = src_width as usize;
+        let src_height = src.len().checked_div(stride).unwrap_or(0);
+        let (x, y, w, h) = (
+            region.0 as usize,
+            region.1 as usize,
+            region.2 as usize,
+            region.3 as usize,
+        );
+        let (dx, dy) = (dst.0 as usize, dst.1 as usize);
+        if x + w > stride
+            || y + h > src_height
+            || dx + w > self.state.width
+            || dy + h > self.state.height
+        {
+            return Err(Error::OutOfBounds);
+        }
+        if w == 0 || h == 0 {
+            return Ok(());
+        }
+
+        self.set_window(dst.0, dst.1, (dx + w - 1) as u16, (dy + h - 1) as u16)?;
+        self.write_iter(
+            (y..y + h).flat_map(|row| src[row * stride + x..row * stride + x + w].iter().cloned()),
+        )
+    }
+    /// Draw an image with its top left corner at (x, y)
+    ///
+    /// The colors of the image are converted to rgb565 values with `Into`.
+    /// If the image does not fit on the screen, `Error::OutOfBounds` is
+    /// returned and nothing is drawn.
+    pub fn draw_image<P, I>(&mut self, x: u16, y: u16, image: &I) -> Result<(), Error<SpiE, PinE>>
+    where
+        P: Into<u16>,
+        I: DrawImage<P>,
+    {
+        let (w, h) = (image.width(), image.height());
+        if x as usize + w as usize > self.state.width || y as usize + h as usize > self.state.height
+        {
+            return Err(Error::OutOfBounds);
+        }
+
+        let mut buf = self.pixel_buf();
+        // position of the next pixel in the open window, if it is contiguous
+        let mut next = None;
+        // whether the open window spans whole rows of the image
+        let mut whole_rows = false;
+        let mut open = false;
+        let mut sent = 0;
+        for (px, py, color) in image.pixels() {
+            if px >= w || py >= h {
+                continue;
+            }
+            if next != Some((px, py)) {
+                if open {
+                    self.write_pixel_data(buf.take(), &mut sent)?;
+                    self.end_transaction()?;
+                }
+                // a window starting at the first column can wrap to the next rows
+                whole_rows = px == 0;
+                let y1 = if whole_rows { y + h - 1 } else { y + py };
+                self.set_window(x + px, y + py, x + w - 1, y1)?;
+                self.begin_memory_write(Command::MemoryWrite)?;
+                sent = 0;
+                open = true;
+            }
+
+            if buf.is_full() {
+                self.write_pixel_data(buf.take(), &mut sent)?;
+            }
+            let color: u16 = color.into();
+            buf.push(color);
+
+            next = if px + 1 < w {
+                Some((px + 1, py))
+            } else if whole_rows && py + 1 < h {
+                Some((0, py + 1))
+            } else {
+                None
+            };
+        }
+        if open {
+            self.write_pixel_data(buf.take(), &mut sent)?;
+            self.end_transaction()?;
+        }
+        Ok(())
+    }
+    /// Draw a [RawImage](struct.RawImage.html) with its top left corner at
+    /// (x, y)
+    ///
+    /// With the rgb565 pixel format the data is sent as is, straight from
+    /// where the image is stored. If the image does not fit on the screen,
+    /// `Error::OutOfBounds` is returned and nothing is drawn.
+    pub fn draw_image_raw(
+        &mut self,
+        x: u16,
+        y: u16,
+        image: &RawImage,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        let (w, h) = (image.width(), image.height());
+        if x as usize + w as usize > self.state.width || y as usize + h as usize > self.state.height
+        {
+            return Err(Error::OutOfBounds);
+        }
+        if w == 0 || h == 0 {
+            return Ok(());
+        }
+        let (x1, y1) = (x + w - 1, y + h - 1);
+        match self.state.pixel_format {
+            PixelFormat::Rgb565 => self.draw_raw(x, y, x1, y1, image.data()),
+            _ => self.draw_iter(x, y, x1, y1, image.pixels()),
+        }
+    }
+    /// Append pixels to the current window, after the last pixel written
+    ///
+    /// This sends Memory Write Continue followed by `data`, in the current
+    /// pixel format, without setting the window again: the pixels go where
+    /// the previous draw call stopped, wrapping to the next row of the window
+    /// as usual.
+    ///
+    /// A window must have been set before, by a draw call or by
+    /// [set_column_address](#method.set_column_address) and
+    /// [set_page_address](#method.set_page_address), otherwise
+    /// `Error::NoWindow` is returned. If `data` is empty nothing is sent.
+    pub fn continue_pixel_write(&mut self, data: &[u8]) -> Result<(), Error<SpiE, PinE>> {
+        if self.state.columns.is_none() || self.state.pages.is_none() {
+            return Err(Error::NoWindow);
+        }
+        if data.is_empty() {
+            return Ok(());
+        }
+        // the address counter moves away from the one cached by set_pixel
+        #[cfg(feature = "window-cache")]
+        {
+            self.state.last_window = None;
+        }
+        self.write_memory_continue(data)
+    }
+    /// Write pixels to the current window, starting from its top left corner
+    ///
+    /// This sends Memory Write followed by `data`, in the current pixel
+    /// format, without setting the window: the caller is responsible for
+    /// having set a valid window first, for example with
+    /// [set_column_address](#method.set_column_address) and
+    /// [set_page_address](#method.set_page_address). After a reset the window
+    /// is the whole screen.
+    ///
+    /// If `data` is empty nothing is sent.
+    pub fn memory_write_raw(&mut self, data: &[u8]) -> Result<(), Error<SpiE, PinE>> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        #[cfg(feature = "window-cache")]
+        {
+            self.state.last_window = None;
+        }
+        self.write_raw(data)
+    }
+    /// Write rgb565 pixels to the current window, starting from its top left
+    /// corner
+    ///
+    /// Same as [memory_write_raw](#method.memory_write_raw), with the pixels
+    /// converted to the current pixel format like in
+    /// [draw_iter](#method.draw_iter).
+    pub fn memory_write_iter<I: IntoIterator<Item = u16>>(
+        &mut self,
+        data: I,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        let mut data = data.into_iter().peekable();
+        if data.peek().is_none() {
+            return Ok(());
+        }
+        #[cfg(feature = "window-cache")]
+        {
+            self.state.last_window = None;
+        }
+        self.write_iter(data)
+    }
+    /// Draw a single pixel with the given rgb565 color
+    ///
+    /// With the `window-cache` feature, the window is opened up to the end of
+    /// the row, so that drawing the pixel right after the previous one
+    /// (the adjacent on the right) just appends to the open window, without
+    /// sending the window setup commands again.
+    pub fn set_pixel(&mut self, x: u16, y: u16, color: u16) -> Result<(), Error<SpiE, PinE>> {
+        let mut buf = self.pixel_buf();
+        buf.push(color);
+        let bytes = buf.take();
+
+        #[cfg(feature = "window-cache")]
+        {
+            if let Some((x0, y0, x1, y1)) = self.state.last_window.take() {
+                if x == x0 && y == y0 {
+                    self.write_memory_continue(bytes)?;
+                    if x < x1 {
+                        self.state.last_window = Some((x + 1, y0, x1, y1));
+                    }
+                    return Ok(());
+                }
+            }
+
+            let x1 = core::cmp::max(x, self.state.width as u16 - 1);
+            self.set_window(x, y, x1, y)?;
+            self.write_raw(bytes)?;
+            if x < x1 {
+                self.state.last_window = Some((x + 1, y, x1, y));
+            }
+            Ok(())
+        }
+        #[cfg(not(feature = "window-cache"))]
+        {
+            self.set_window(x, y, x, y)?;
+            self.write_raw(bytes)
+        }
+    }
+    /// Set the rotation of the display as mounted in the enclosure
+    ///
+    /// The orientations passed to
+    /// [set_orientation](#method.set_orientation), including the current one,
+    /// are composed with this rotation, and so are `width()` and `height()`.
+    pub fn with_display_rotation(mut self, rotation: Rotation) -> Result<Self, Error<SpiE, PinE>> {
+        self.state.rotation = rotation;
+        let orientation = self.state.orientation;
+        self.set_orientation(orientation)?;
+        Ok(self)
+    }
+    /// Declare the SPI mode the bus is configured for
+    ///
+    /// The controller accepts both [MODE_0](constant.MODE_0.html) and
+    /// [MODE_3](constant.MODE_3.html), and nothing is sent: the mode is
+    /// recorded so that the code holding the display, and the read paths,
+    /// know which one the bus uses. The driver assumes mode 0 otherwise.
+    ///
+    /// ```ignore
+    /// let spi = Spi::new(dp.SPI1, pins, ili9341::MODE_3, 40.MHz(), &clocks);
+    /// let display = Ili9341::new(spi, cs, dc, reset, &mut delay)?.with_spi_mode(SpiMode::Mode3);
+    /// ```
+    pub fn with_spi_mode(mut self, mode: SpiMode) -> Self {
+        self.state.spi_mode = mode;
+        self
+    }
+    /// Get the SPI mode declared with [with_spi_mode](#method.with_spi_mode)
+    pub fn spi_mode(&self) -> SpiMode {
+        self.state.spi_mode
+    }
+    /// Hide or show the content of the screen
+    ///
+    /// This only turns the output of the display off (DisplayOff) or back on
+    /// (DisplayOn): the video memory is retained, and can still be drawn to
+    /// while blanked, so showing the screen again displays exactly what was
+    /// drawn before.
+    pub fn blank(&mut self, blank: bool) -> Result<(), Error<SpiE, PinE>> {
+        if blank {
+            self.command(Command::DisplayOff, &[])
+        } else {
+            self.command(Command::DisplayOn, &[])
+        }
+    }
+    /// Put the display in sleep mode
+    ///
+    /// This turns the panel and the oscillator off to save power. The video
+    /// memory is retained, but draw commands sent while sleeping are ignored.
+    pub fn sleep<DELAY: DelayMs<u16>>(
+        &mut self,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        self.command(Command::SleepIn, &[])?;
+        // the datasheet asks for 5ms before the next command
+        delay.delay_ms(5);
+        Ok(())
+    }
+    /// Bring the display out of sleep mode
+    pub fn wake<DELAY: DelayMs<u16>>(
+        &mut self,
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        self.command(Command::SleepOut, &[])?;
+        // the supply and oscillator need 120ms to stabilize
+        delay.delay_ms(120);
+        Ok(())
+    }
+    /// Whether the display is in sleep mode
+    ///
+    /// This is tracked from the commands sent by the driver, including the
+    /// ones sent with [send_command](#method.send_command).
+    pub fn is_asleep(&self) -> bool {
+        self.state.sleeping
+    }
+    /// Enable or disable the tearing effect output (TE pin)
+    ///
+    /// When enabled, the TE pin of the controller goes high during the
+    /// vertical blanking of each frame, see [present](#method.present).
+    pub fn set_tearing_effect(&mut self, enable: bool) -> Result<(), Error<SpiE, PinE>> {
+        if enable {
+            // mode 0: V-blanking information only
+            self.command(Command::TearingEffectLineOn, &[0x00])?;
+        } else {
+            self.command(Command::TearingEffectLineOff, &[])?;
+        }
+        self.state.tearing_effect = enable;
+        Ok(())
+    }
+    /// Give the pin wired to the TE output of the display to the driver, for
+    /// [wait_for_tearing_effect](#method.wait_for_tearing_effect)
+    pub fn with_te_pin<P>(self, te: P) -> Ili9341<SPI, CS, DC, RESET, P> {
+        Ili9341 {
+            spi: self.spi,
+            cs: self.cs,
+            dc: self.dc,
+            reset: self.reset,
+            te,
+            state: self.state,
+        }
+    }
+    /// Wait for the vertical blanking and draw a whole frame
+    ///
+    /// `fb` holds the pixels of the whole screen in rgb565, row by row, for
+    /// the current orientation. The TE pin of the display must be wired to
+    /// `te_pin` and enabled with
+    /// [set_tearing_effect](#method.set_tearing_effect). `te_pin` is polled
+    /// every 10µs for the start of the blanking (rising edge); if it doesn't
+    /// come within 50ms, the frame is drawn anyway.
+    ///
+    /// The update only avoids tearing if the write stays ahead of the refresh
+    /// of the panel, which scans it from the top: the orientation must be
+    /// portrait, so that the write goes in the same direction, and the frame
+    /// must be written faster than the panel is refreshed. At the default
+    /// 70Hz a refresh takes about 14ms, while a full frame is 153600 bytes,
+    /// about 31ms at 40MHz, so the frame rate has to be lowered with Frame
+    /// Rate Control (below 30Hz in this example).
+    ///
+    /// If `fb` is smaller than the screen, `Error::BufferTooSmall` is returned.
+    pub fn present<P, DELAY>(
+        &mut self,
+        te_pin: &mut P,
+        fb: &[u16],
+        delay: &mut DELAY,
+    ) -> Result<(), Error<SpiE, PinE>>
+    where
+        P: InputPin<Error = PinE>,
+        DELAY: DelayUs<u16>,
+    {
+        let len = self.state.width * self.state.height;
+        if fb.len() < len {
+            return Err(Error::BufferTooSmall);
+        }
+        let mut was_high = true;
+        for _ in 0..5000 {
+            let high = te_pin.is_high().map_err(Error::InputPin)?;
+            if high && !was_high {
+                break;
+            }
+            was_high = high;
+            delay.delay_us(10);
+        }
+        self.set_window(
+            0,
+            0,
+            self.state.width as u16 - 1,
+            self.state.height as u16 - 1,
+        )?;
+        self.write_iter(fb[..len].iter().cloned())
+    }
+    /// Change the orientation of the screen
+    ///
+    /// The orientation is relative to the rotation set with
+    /// [with_display_rotation](#method.with_display_rotation), if any.
+    pub fn set_orientation(&mut self, mode: Orientation) -> Result<(), Error<SpiE, PinE>> {
+        self.state.orientation = mode;
+        let mode = mode.rotated(self.state.rotation);
+        match mode {
+            Orientation::Portrait | Orientation::PortraitFlipped => {
+                self.state.width = NATIVE_WIDTH;
+                self.state.height = NATIVE_HEIGHT;
+            }
+            Orientation::Landscape | Orientation::LandscapeFlipped => {
+                self.state.width = NATIVE_HEIGHT;
+                self.state.height = NATIVE_WIDTH;
+            }
+        }
+        self.command(Command::MemoryAccessControl, &[self.madctl()])
+    }
+    /// Set the refresh order of the panel, see
+    /// [AddressMode](struct.AddressMode.html)
+    ///
+    /// It is kept when the orientation changes.
+    pub fn set_address_mode(&mut self, mode: AddressMode) -> Result<(), Error<SpiE, PinE>> {
+        self.state.address_mode = mode;
+        self.command(Command::MemoryAccessControl, &[self.madctl()])
+    }
+    /// Get the refresh order of the panel
+    pub fn address_mode(&self) -> AddressMode {
+        self.state.address_mode
+    }
+    /// Mirror the image horizontally, or back, without changing the
+    /// orientation
+    ///
+    /// This is for modules seen through a mirror or mounted reversed, which
+    /// none of the orientations can fix. The x coordinates of the drawing
+    /// methods still go from the left to the right of the screen, but the
+    /// image shows mirrored. The mirroring is kept when the orientation
+    /// changes.
+    pub fn set_h_mirror(&mut self, mirror: bool) -> Result<(), Error<SpiE, PinE>> {
+        self.state.mirror.0 = mirror;
+        self.command(Command::MemoryAccessControl, &[self.madctl()])
+    }
+    /// Mirror the image vertically, or back, without changing the
+    /// orientation, see [set_h_mirror](#method.set_h_mirror)
+    pub fn set_v_mirror(&mut self, mirror: bool) -> Result<(), Error<SpiE, PinE>> {
+        self.state.mirror.1 = mirror;
+        self.command(Command::MemoryAccessControl, &[self.madctl()])
+    }
+    /// Whether the image is mirrored horizontally and vertically
+    pub fn mirror(&self) -> (bool, bool) {
+        self.state.mirror
+    }
+    /// Value of Memory Access Control for the current orientation, rotation,
+    /// mirroring and address mode
+    fn madctl(&self) -> u8 {
+        let mut madctl = self.state.orientation.rotated(self.state.rotation).madctl();
+        // with the row / column exchange, the x axis of the image is the one
+        // of the pages
+        let (h, v) = if madctl & MADCTL_MV != 0 {
+            (MADCTL_MY, MADCTL_MX)
+        } else {
+            (MADCTL_MX, MADCTL_MY)
+        };
+        if self.state.mirror.0 {
+            madctl ^= h;
+        }
+        if self.state.mirror.1 {
+            madctl ^= v;
+        }
+        madctl | self.state.address_mode.madctl()
+    }
+    /// Send the orientation and pixel format set through the driver to the
+    /// controller again
+    ///
+    /// This repairs the registers after [verify_state](#method.verify_state)
+    /// found them corrupted.
+    pub fn resync(&mut self) -> Result<(), Error<SpiE, PinE>> {
+        let orientation = self.state.orientation;
+        self.set_orientation(orientation)?;
+        let pixel_format = self.state.pixel_format;
+        self.set_pixel_format(pixel_format)
+    }
+    /// Set the parameters of Display Function Control (0xB6)
+    ///
+    /// They set the scan of the non-display area (first byte), the scan
+    /// directions of the gate and source outputs (GS and SS, bits 6 and 5 of
+    /// the second byte) and the number of lines driven (third byte, 8 lines
+    /// per step, 0x27 for 320 lines). The default is `[0x08, 0x82, 0x27]`;
+    /// some panels need other values, for example when a few lines at the
+    /// edge show garbage. The parameters are sent again by
+    /// [reinit](#method.reinit).
+    pub fn set_display_function(&mut self, args: [u8; 3]) -> Result<(), Error<SpiE, PinE>> {
+        self.command(Command::DisplayFunctionControl, &args)?;
+        self.state.display_function = args;
+        Ok(())
+    }
+    /// Select one of the four gamma curves stored in the controller
+    ///
+    /// The curve is selected again by [reinit](#method.reinit).
+    pub fn set_gamma_curve(&mut self, curve: GammaCurve) -> Result<(), Error<SpiE, PinE>> {
+        self.command(Command::GammaSet, curve.args())?;
+        self.state.config.gamma_curve = curve;
+        Ok(())
+    }
+    /// Define the vertical scrolling area, between `top_fixed` lines at the
+    /// top and `bottom_fixed` lines at the bottom which don't scroll
+    ///
+    /// Scrolling is done by the controller along the 320 lines of the panel in
+    /// portrait orientation, whatever the orientation set: in landscape the
+    /// display scrolls horizontally. At least one line must be left to scroll,
+    /// otherwise `Error::OutOfBounds` is returned and nothing is sent.
+    ///
+    /// The current scroll offset is applied again within the new area, see
+    /// [set_vertical_scroll_offset](#method.set_vertical_scroll_offset).
+    pub fn set_scroll_area(
+        &mut self,
+        top_fixed: u16,
+        bottom_fixed: u16,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        let height = NATIVE_HEIGHT as u16;
+        if top_fixed as usize + bottom_fixed as usize >= NATIVE_HEIGHT {
+            return Err(Error::OutOfBounds);
+        }
+        let scroll_height = height - top_fixed - bottom_fixed;
+        self.command(
+            Command::VerticalScrollingDefinition,
+            &[
+                (top_fixed >> 8) as u8,
+                (top_fixed & 0xff) as u8,
+                (scroll_height >> 8) as u8,
+                (scroll_height & 0xff) as u8,
+                (bottom_fixed >> 8) as u8,
+                (bottom_fixed & 0xff) as u8,
+            ],
+        )?;
+        self.state.scroll_fixed = (top_fixed, bottom_fixed);
+        let offset = self.state.scroll_offset;
+        self.set_vertical_scroll_offset(offset)
+    }
+    /// Scroll the vertical scrolling area by `offset` lines
+    ///
+    /// The line shown at the top of the area is the one `offset` lines below
+    /// it in memory, the lines scrolled out at the top coming back at the
+    /// bottom. Like on the controller, the offset wraps around the height of
+    /// the area (320 lines unless set with
+    /// [set_scroll_area](#method.set_scroll_area)): an offset of that height
+    /// or more is taken modulo the height, so incrementing the offset scrolls
+    /// smoothly past the end of the area.
+    pub fn set_vertical_scroll_offset(&mut self, offset: u16) -> Result<(), Error<SpiE, PinE>> {
+        let (top, bottom) = self.state.scroll_fixed;
+        let offset = offset % (NATIVE_HEIGHT as u16 - top - bottom);
+        let start = top + offset;
+        self.command(
+            Command::VerticalScrollingStartAddress,
+            &[(start >> 8) as u8, (start & 0xff) as u8],
+        )?;
+        self.state.scroll_offset = offset;
+        Ok(())
+    }
+    /// Current vertical scroll offset, always less than the height of the
+    /// scrolling area
+    pub fn scroll_offset(&self) -> u16 {
+        self.state.scroll_offset
+    }
+    /// Set lookup tables correcting the brightness of each channel, or remove
+    /// them with `None`
+    ///
+    /// The tables are applied to every rgb565 pixel drawn: by
+    /// [draw_iter](#method.draw_iter), [draw_words](#method.draw_words),
+    /// [set_pixel](#method.set_pixel), [fill_pattern](#method.fill_pattern),
+    /// [draw_image](#method.draw_image) and the embedded-graphics drawing
+    /// target among others. Only the raw bytes, given to
+    /// [draw_raw](#method.draw_raw) and the like, and the `[u8; 3]` words are
+    /// sent unchanged.
+    pub fn set_brightness_lut(&mut self, lut: Option<color::BrightnessLut>) {
+        self.state.brightness_lut = lut;
+    }
+    /// Limit the number of pixel bytes sent while CS is held low
+    ///
+    /// By default a memory write keeps CS low for the whole transfer. With a
+    /// limit, longer transfers are split: CS is released and asserted again,
+    /// and the write goes on with Memory Write Continue, at the cost of a few
+    /// more bytes on the bus. This is for buses that release CS when it is
+    /// held low for too long. The limit is rounded down to whole pixels.
+    pub fn set_max_transfer_size(&mut self, max: Option<usize>) {
+        self.state.max_transfer = max;
+    }
+    /// Change the pixel format used for the data written to the screen
+    ///
+    /// Subsequent calls to [draw_iter](#method.draw_iter) convert the rgb565
+    /// values to the new format, while [draw_raw](#method.draw_raw) expects
+    /// the data to already be in the new format.
+    pub fn set_pixel_format(&mut self, fmt: PixelFormat) -> Result<(), Error<SpiE, PinE>> {
+        self.command(Command::PixelFormatSet, &[fmt.command_arg()])?;
+        self.state.pixel_format = fmt;
+        Ok(())
+    }
+    /// Set the colors used by `core::fmt::Write`, as rgb565 values
+    ///
+    /// The default is white text on a black background.
+    #[cfg(feature = "text")]
+    pub fn set_text_color(&mut self, fg: u16, bg: u16) {
+        self.state.text_color = (fg, bg);
+    }
+    /// Set the font used by [draw_char](#method.draw_char),
+    /// [draw_str](#method.draw_str) and `core::fmt::Write`
+    ///
+    /// The default is the 8x8 font.
+    #[cfg(feature = "text")]
+    pub fn set_font(&mut self, font: font::Font) {
+        self.state.font = font;
+    }
+    /// Move the position of the next character written by `core::fmt::Write`
+    ///
+    /// `x` and `y` are the coordinates of the top left corner of the character.
+    #[cfg(feature = "text")]
+    pub fn set_cursor(&mut self, x: u16, y: u16) {
+        self.state.cursor = (x, y);
+    }
+    #[cfg(feature = "text")]
+    fn write_char_at_cursor(&mut self, c: char) -> Result<(), Error<SpiE, PinE>> {
+        let (glyph_width, glyph_height) = (self.state.font.width(), self.state.font.height());
+
+        if c == '\n' {
+            self.state.cursor = (0, self.state.cursor.1.saturating_add(glyph_height));
+            return Ok(());
+        }
+        if c == '\r' {
+            self.state.cursor.0 = 0;
+            return Ok(());
+        }
+        // the cursor can be set anywhere, up to u16::MAX
+        if self.state.cursor.0.saturating_add(glyph_width) > self.state.width as u16 {
+            self.state.cursor = (0, self.state.cursor.1.saturating_add(glyph_height));
+        }
+        if self.state.cursor.1.saturating_add(glyph_height) > self.state.height as u16 {
+            self.state.cursor.1 = 0;
+        }
+        let (x, y) = self.state.cursor;
+        let (fg, bg) = self.state.text_color;
+        self.draw_char(x, y, c, fg, bg)?;
+        self.state.cursor.0 = self.state.cursor.0.saturating_add(glyph_width);
+        Ok(())
+    }
+    /// Draw a character of the current [font](font/index.html), see
+    /// [set_font](#method.set_font), with the top left corner of its cell at
+    /// (x, y), in the `fg` color on a `bg` background
+    ///
+    /// Characters missing from the font are drawn as `'?'`. If the character
+    /// doesn't fit on the screen, `Error::OutOfBounds` is returned and nothing
+    /// is drawn.
+    #[cfg(feature = "text")]
+    pub fn draw_char(
+        &mut self,
+        x: u16,
+        y: u16,
+        c: char,
+        fg: u16,
+        bg: u16,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        let font = self.state.font;
+        let (w, h) = (font.width(), font.height());
+        if x as usize + w as usize > self.state.width || y as usize + h as usize > self.state.height
+        {
+            return Err(Error::OutOfBounds);
+        }
+        let pixels = (0..h)
+            .flat_map(|row| (0..w).map(move |col| font.pixel(c, col, row)))
+            .map(|on| if on { fg } else { bg });
+        self.set_window(x, y, x + w - 1, y + h - 1)?;
+        self.write_iter(pixels)
+    }
+    /// Draw a string with [draw_char](#method.draw_char), starting at (x, y)
+    ///
+    /// `'\n'` starts a new line below, at `x` again. There is no wrapping: if
+    /// a character doesn't fit on the screen, `Error::OutOfBounds` is returned
+    /// and the rest of the string is not drawn. See the `core::fmt::Write`
+    /// implementation for text flowing over the whole screen.
+    #[cfg(feature = "text")]
+    pub fn draw_str(
+        &mut self,
+        x: u16,
+        y: u16,
+        s: &str,
+        fg: u16,
+        bg: u16,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        let (w, h) = (self.state.font.width(), self.state.font.height());
+        let (mut cx, mut cy) = (x, y);
+        for c in s.chars() {
+            if c == '\n' {
+                cx = x;
+                cy = cy.saturating_add(h);
+                continue;
+            }
+            self.draw_char(cx, cy, c, fg, bg)?;
+            cx += w;
+        }
+        Ok(())
+    }
+    /// Wait for the bytes sent so far to be completely sent
+    ///
+    /// The driver already does it before releasing CS at the end of each
+    /// transfer, through [DataCommand::flush](trait.DataCommand.html#method.flush),
+    /// so every call returns with its data sent. This is for code using the
+    /// bus around the driver, for example before changing the SPI clock. It
+    /// does nothing with the blocking SPI traits, see
+    /// [FlushDc](struct.FlushDc.html) for the other ones.
+    pub fn flush(&mut self) -> Result<(), Error<SpiE, PinE>> {
+        self.dc.flush(&mut self.spi)
+    }
+    /// Run `f` with CS held low for all the commands and pixel writes it
+    /// does, instead of releasing it after each of them
+    ///
+    /// This saves the CS toggling between related operations, like setting a
+    /// window and writing its pixels, on buses where it is slow. CS is
+    /// released when `f` returns, even with an error, which is then returned.
+    ///
+    /// ```ignore
+    /// display.with_cs_held(|display| {
+    ///     display.draw_raw(0, 0, 9, 0, &line)?;
+    ///     display.draw_raw(0, 1, 9, 1, &line)
+    /// })?;
+    /// ```
+    ///
+    /// The read methods need CS to be released to end a read: don't use them
+    /// inside `f`. The limit set with
+    /// [set_max_transfer_size](#method.set_max_transfer_size) doesn't release
+    /// CS either while it is held.
+    pub fn with_cs_held<R, F>(&mut self, f: F) -> Result<R, Error<SpiE, PinE>>
+    where
+        F: FnOnce(&mut Self) -> Result<R, Error<SpiE, PinE>>,
+    {
+        if self.state.cs_held {
+            // already held by an outer call
+            return f(self);
+        }
+        self.select()?;
+        self.state.cs_held = true;
+        let res = f(self);
+        self.state.cs_held = false;
+        let end = self.end_transaction();
+        let value = res?;
+        end.map(|_| value)
+    }
+    /// Get the current drawing window as (x0, y0, x1, y1), both corners
+    /// included
+    ///
+    /// This is `None` until both the column and page ranges have been set
+    /// after a reset. Together with
+    /// [pixels_written](#method.pixels_written), it allows resuming an
+    /// interrupted transfer after the display was used for something else.
+    pub fn current_window(&self) -> Option<(u16, u16, u16, u16)> {
+        match (self.state.columns, self.state.pages) {
+            (Some((x0, x1)), Some((y0, y1))) => Some((x0, y0, x1, y1)),
+            _ => None,
+        }
+    }
+    /// Get the number of pixels written into the current window
+    ///
+    /// The count starts at zero when the window is set and at each Memory
+    /// Write, and includes the pixels appended with Memory Write Continue. A
+    /// write stopped by an error only counts the chunks actually sent.
+    pub fn pixels_written(&self) -> usize {
+        self.state.written
+    }
+    /// Get the counters of the traffic sent to the display
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> &Stats {
+        &self.state.stats
+    }
+    /// Reset all the traffic counters to zero
+    #[cfg(feature = "stats")]
+    pub fn reset_stats(&mut self) {
+        self.state.stats = Stats::default();
+    }
+    /// Get the current pixel format
+    pub fn pixel_format(&self) -> PixelFormat {
+        self.state.pixel_format
+    }
+    /// Get the current screen width. It can change based on the current orientation
+    pub fn width(&self) -> usize {
+        self.state.width
+    }
+    /// Get the current screen heighth. It can change based on the current orientation
+    pub fn height(&self) -> usize {
+        self.state.height
+    }
+    /// Get the point (x, y) as coordinates for the drawing methods, if it is
+    /// on the screen in the current orientation, or `None`
+    ///
+    /// ```ignore
+    /// if let Some((x, y)) = display.clamp_point(cursor.x, cursor.y) {
+    ///     display.set_pixel(x, y, 0xffff)?;
+    /// }
+    /// ```
+    pub fn clamp_point(&self, x: i32, y: i32) -> Option<(u16, u16)> {
+        if x < 0 || y < 0 || x as usize >= self.state.width || y as usize >= self.state.height {
+            return None;
+        }
+        Some((x as u16, y as u16))
+    }
+    /// Number of pixels of the screen, the same in every orientation
+    #[inline]
+    pub fn pixel_count(&self) -> usize {
+        self.state.width * self.state.height
+    }
+    /// Size in bytes of a full screen of rgb565 pixels
+    ///
+    /// This is the buffer size needed to hold a whole frame in the default
+    /// pixel format, for example for DMA:
+    ///
+    /// ```ignore
+    /// // the size is known up front: 240 * 320 * 2
+    /// static mut FRAME: [u8; 153_600] = [0; 153_600];
+    /// assert_eq!(display.size_in_bytes(), 153_600);
+    ///
+    /// // or for a line of the screen
+    /// let mut line: heapless::Vec<u8, 640> = heapless::Vec::new();
+    /// line.resize(display.size_in_bytes() / display.height(), 0).unwrap();
+    /// ```
+    ///
+    /// With [PixelFormat::Rgb666](enum.PixelFormat.html), use
+    /// [size_in_bytes_rgb666](#method.size_in_bytes_rgb666) instead.
+    #[inline]
+    pub fn size_in_bytes(&self) -> usize {
+        self.pixel_count() * 2
+    }
+    /// Size in bytes of a full screen of rgb666 pixels, sent as 3 bytes each
+    #[inline]
+    pub fn size_in_bytes_rgb666(&self) -> usize {
+        self.pixel_count() * 3
+    }
+    /// Estimated time in microseconds to draw a window of `width` x `height`
+    /// pixels with [draw_raw](#method.draw_raw), on a bus clocked at `spi_hz`
+    ///
+    /// It counts the bytes sent on the bus: the window commands, the pixels
+    /// in the current pixel format, and the Memory Write Continue command of
+    /// each chunk after the first with
+    /// [set_max_transfer_size](#method.set_max_transfer_size). The gaps
+    /// between the SPI writes are not known to the driver and are left out,
+    /// so the actual time is somewhat longer. A `spi_hz` of 0 is treated as 1,
+    /// and the result saturates at `u32::MAX`.
+    ///
+    /// ```ignore
+    /// // a full screen at 40MHz, about 31ms
+    /// let us = display.transfer_time_us(240, 320, 40_000_000);
+    /// ```
+    pub fn transfer_time_us(&self, width: u16, height: u16, spi_hz: u32) -> u32 {
+        let bpp = self.state.pixel_format.bytes_per_pixel();
+        let pixel_bytes = usize::from(width) * usize::from(height) * bpp;
+        let chunks = match self.state.max_transfer {
+            Some(max) => pixel_bytes.div_ceil(core::cmp::max(max - max % bpp, bpp)),
+            None => 1,
+        };
+        // Column Address Set and Page Address Set with 4 bytes each, Memory
+        // Write, then Memory Write Continue for the other chunks
+        let command_bytes = 5 + 5 + chunks.max(1);
+        let bits = (pixel_bytes + command_bytes) as u64 * 8;
+        let us = bits * 1_000_000 / u64::from(spi_hz.max(1));
+        core::cmp::min(us, u64::from(u32::MAX)) as u32
+    }
+}
+
+impl<SpiE, PinE, SPI, CS, DC, RESET, TE> Ili9341<SPI, CS, DC, RESET, TE>
+where
+    CS: OutputPin<Error = PinE>,
+    DC: DataCommand<SPI, PinE, SpiError = SpiE>,
+    RESET: OutputPin<Error = PinE>,
+    TE: InputPin<Error = PinE>,
+{
+    /// Wait for the start of the next vertical blanking, on the pin given
+    /// with [with_te_pin](#method.with_te_pin)
+    ///
+    /// Drawing right after it returns, for example a whole frame, stays
+    /// behind the refresh of the panel, see [present](#method.present) for
+    /// the conditions. The tearing effect output must be enabled with
+    /// [set_tearing_effect](#method.set_tearing_effect), otherwise this
+    /// returns immediately as the pin would never change.
+    ///
+    /// This busy-waits for up to a whole frame, about 14ms at the default
+    /// 70Hz, polling the pin without a timeout. When the CPU has better
+    /// things to do, configure the pin as an interrupt on its rising edge
+    /// instead, and start drawing from the interrupt handler or from the task
+    /// it wakes up.
+    pub fn wait_for_tearing_effect(&mut self) -> Result<(), Error<SpiE, PinE>> {
+        if !self.state.tearing_effect {
+            return Ok(());
+        }
+        // if the blanking already started, wait for the next one
+        while self.te.is_high().map_err(Error::InputPin)? {}
+        while self.te.is_low().map_err(Error::InputPin)? {}
+        Ok(())
+    }
+}
+
+impl<SpiE, PinE, SPI, CS, RESET> Ili9341<SPI, CS, NoDc, RESET>
+where
+    SPI: spi::Write<u16, Error = SpiE>,
+    CS: OutputPin<Error = PinE>,
+    RESET: OutputPin<Error = PinE>,
+{
+    /// Create and initialize the display on a 3-wire SPI bus, without a D/C pin
+    ///
+    /// The SPI peripheral must be configured for 9-bit words, see
+    /// [NoDc](struct.NoDc.html).
+    pub fn new_3wire<DELAY: DelayMs<u16>>(
+        spi: SPI,
+        cs: CS,
+        reset: RESET,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SpiE, PinE>> {
+        Self::new(spi, cs, NoDc, reset, delay)
+    }
+}
+
+impl<SpiE, PinE, SPI, DC, RESET> Ili9341<SPI, ExternalCs<PinE>, DC, RESET>
+where
+    DC: DataCommand<SPI, PinE, SpiError = SpiE>,
+    RESET: OutputPin<Error = PinE>,
+{
+    /// Create and initialize the display on an SPI device that selects the
+    /// display itself, like the devices of a bus shared between several
+    /// chips
+    ///
+    /// The driver never drives CS, see [ExternalCs](struct.ExternalCs.html).
+    /// As the device releases CS after each write,
+    /// [with_cs_held](#method.with_cs_held) has no effect and the reads,
+    /// which need the command and its answer in the same transaction, are
+    /// not supported.
+    pub fn with_spi_device<DELAY: DelayMs<u16>>(
+        device: SPI,
+        dc: DC,
+        reset: RESET,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SpiE, PinE>> {
+        Self::new(device, ExternalCs::new(), dc, reset, delay)
+    }
+}
+
+impl<SpiE, PinE, SPI, CS, DC, RESET> Ili9341<SPI, CS, Dc16<DC>, RESET>
+where
+    SPI: spi::Write<u16, Error = SpiE>,
+    CS: OutputPin<Error = PinE>,
+    DC: OutputPin<Error = PinE>,
+    RESET: OutputPin<Error = PinE>,
+{
+    /// Create and initialize the display on a 4-wire SPI bus with 16-bit
+    /// frames
+    ///
+    /// The SPI peripheral must be configured for 16-bit words, see
+    /// [Dc16](struct.Dc16.html).
+    pub fn new_16bit<DELAY: DelayMs<u16>>(
+        spi: SPI,
+        cs: CS,
+        dc: DC,
+        reset: RESET,
+        delay: &mut DELAY,
+    ) -> Result<Self, Error<SpiE, PinE>> {
+        Self::new(spi, cs, Dc16(dc), reset, delay)
+    }
+}
+
+/// Reading from the display needs a D/C pin and an SPI bus able to read
+impl<SpiE, PinE, SPI, CS, DC, RESET, TE> Ili9341<SPI, CS, DC, RESET, TE>
+where
+    SPI: spi::Write<u8, Error = SpiE> + spi::Transfer<u8, Error = SpiE>,
+    CS: OutputPin<Error = PinE>,
+    DC: OutputPin<Error = PinE>,
+    RESET: OutputPin<Error = PinE>,
+{
+    /// Read the manufacturer ID, the module version ID and the module ID
+    pub fn read_display_id<C: ReadClock<SPI>>(
+        &mut self,
+        clock: &mut C,
+    ) -> Result<[u8; 3], Error<SpiE, PinE>> {
+        let mut buf = [0; 4];
+        self.read_with_dummy_bit(Command::ReadDisplayId, &mut buf, clock)?;
+        Ok([buf[0], buf[1], buf[2]])
+    }
+    /// Read the 4 bytes of the display status (booster, orientation, pixel
+    /// format, sleep and display on/off, ...)
+    pub fn read_display_status<C: ReadClock<SPI>>(
+        &mut self,
+        clock: &mut C,
+    ) -> Result<[u8; 4], Error<SpiE, PinE>> {
+        let mut buf = [0; 5];
+        self.read_with_dummy_bit(Command::ReadDisplayStatus, &mut buf, clock)?;
+        Ok([buf[0], buf[1], buf[2], buf[3]])
+    }
+    /// Check that the orientation and pixel format registers of the controller
+    /// still hold what the driver set
+    ///
+    /// Memory Access Control and the pixel format are read back and compared
+    /// with the orientation and pixel format set through the driver, to
+    /// detect a register corrupted for example by electromagnetic
+    /// interference. Returns `Ok(false)` if they differ, in which case
+    /// [resync](#method.resync) writes them again.
+    pub fn verify_state<C: ReadClock<SPI>>(
+        &mut self,
+        clock: &mut C,
+    ) -> Result<bool, Error<SpiE, PinE>> {
+        let madctl = self.read_register(Command::ReadDisplayMadctl, clock)?;
+        let colmod = self.read_register(Command::ReadDisplayPixelFormat, clock)?;
+        let expected = self.madctl();
+        // only the MCU interface format is set by the driver
+        Ok(madctl == expected && colmod & 0x07 == self.state.pixel_format.command_arg() & 0x07)
+    }
+    /// Change the orientation of the screen like
+    /// [set_orientation](#method.set_orientation), then read Memory Access
+    /// Control back to check that the controller accepted it
+    ///
+    /// If the register doesn't hold the value sent, for example because the
+    /// command was corrupted on the bus, `Error::Mismatch` is returned. The
+    /// orientation can then be set again.
+    pub fn set_orientation_verified<C: ReadClock<SPI>>(
+        &mut self,
+        mode: Orientation,
+        clock: &mut C,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        self.set_orientation(mode)?;
+        let madctl = self.read_register(Command::ReadDisplayMadctl, clock)?;
+        if madctl != self.madctl() {
+            return Err(Error::Mismatch);
+        }
+        Ok(())
+    }
+    /// Read back a rectangle of the video memory, represented by top-left
+    /// corner (x0, y0) and bottom-right corner (x1, y1), both included
+    ///
+    /// The pixels are stored in `buf` row by row as rgb565 values, whatever the
+    /// pixel format: the controller always sends them as rgb666 on reads, and
+    /// the lowest bit of red and blue is dropped. `buf` must have room for the
+    /// whole rectangle, otherwise `Error::BufferTooSmall` is returned and
+    /// nothing is sent.
+    pub fn read_memory<C: ReadClock<SPI>>(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        buf: &mut [u16],
+        clock: &mut C,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        if x0 > x1 || y0 > y1 || x1 as usize >= self.state.width || y1 as usize >= self.state.height
+        {
+            return Err(Error::OutOfBounds);
+        }
+        let len = (x1 - x0 + 1) as usize * (y1 - y0 + 1) as usize;
+        if buf.len() < len {
+            return Err(Error::BufferTooSmall);
+        }
+        self.set_window(x0, y0, x1, y1)?;
+
+        clock.before_read(&mut self.spi);
+        let res = self.read_memory_pixels(&mut buf[..len]);
+        clock.after_read(&mut self.spi);
+        res
+    }
+    /// Copy the rectangle of size `w` x `h` at (src_x, src_y) to (dst_x,
+    /// dst_y), returning the number of pixels copied
+    ///
+    /// The rectangle is read back and written one row at a time through a
+    /// row buffer on the stack, so no framebuffer is needed, for example to
+    /// scroll the lines of a text terminal. The rows are copied in the order
+    /// that keeps overlapping rectangles right. The pixels go through rgb565
+    /// like with [read_memory](#method.read_memory): with the rgb666 pixel
+    /// format the lowest bit of red and blue is lost.
+    ///
+    /// If either rectangle is not inside the screen, `Error::OutOfBounds` is
+    /// returned and nothing is sent. An empty rectangle copies nothing.
+    #[allow(clippy::too_many_arguments)]
+    pub fn copy_region<C: ReadClock<SPI>>(
+        &mut self,
+        src_x: u16,
+        src_y: u16,
+        dst_x: u16,
+        dst_y: u16,
+        w: u16,
+        h: u16,
+        clock: &mut C,
+    ) -> Result<u32, Error<SpiE, PinE>> {
+        let fits = |x: u16, y: u16| {
+            usize::from(x) + usize::from(w) <= self.state.width
+                && usize::from(y) + usize::from(h) <= self.state.height
+        };
+        if !fits(src_x, src_y) || !fits(dst_x, dst_y) {
+            return Err(Error::OutOfBounds);
+        }
+        if w == 0 || h == 0 {
+            return Ok(0);
+        }
+        let mut row = [0; NATIVE_HEIGHT];
+        let row = &mut row[..usize::from(w)];
+        for i in 0..h {
+            // copy from the bottom up when moving down, so that the source
+            // rows are read before being overwritten
+            let i = if dst_y > src_y { h - 1 - i } else { i };
+            self.set_window(src_x, src_y + i, src_x + w - 1, src_y + i)?;
+            clock.before_read(&mut self.spi);
+            let res = self.read_memory_pixels(row);
+            clock.after_read(&mut self.spi);
+            res?;
+            self.set_window(dst_x, dst_y + i, dst_x + w - 1, dst_y + i)?;
+            self.write_iter(row.iter().cloned())?;
+        }
+        Ok(u32::from(w) * u32::from(h))
+    }
+    fn read_memory_pixels(&mut self, pixels: &mut [u16]) -> Result<(), Error<SpiE, PinE>> {
+        self.begin_read(Command::MemoryRead)?;
+        // the first byte read is a dummy one
+        self.spi.transfer(&mut [0]).map_err(Error::Spi)?;
+        let mut buf = [0; CHUNK_SIZE / 3 * 3];
+        for chunk in pixels.chunks_mut(CHUNK_SIZE / 3) {
+            let bytes = &mut buf[..chunk.len() * 3];
+            for b in bytes.iter_mut() {
+                *b = 0;
+            }
+            self.spi.transfer(bytes).map_err(Error::Spi)?;
+            for (pixel, rgb) in chunk.iter_mut().zip(bytes.chunks(3)) {
+                *pixel = (u16::from(rgb[0] >> 3) << 11)
+                    | (u16::from(rgb[1] >> 2) << 5)
+                    | u16::from(rgb[2] >> 3);
+            }
+        }
+        self.end_transaction()
+    }
+    /// Read the single byte answer to a command, sent without any dummy cycle
+    fn read_register<C: ReadClock<SPI>>(
+        &mut self,
+        cmd: Command,
+        clock: &mut C,
+    ) -> Result<u8, Error<SpiE, PinE>> {
+        let mut buf = [0];
+        clock.before_read(&mut self.spi);
+        let res = self
+            .begin_read(cmd)
+            .and_then(|_| self.spi.transfer(&mut buf).map(|_| ()).map_err(Error::Spi))
+            .and_then(|_| self.end_transaction());
+        clock.after_read(&mut self.spi);
+        res.map(|_| buf[0])
+    }
+    /// Read the answer to a command preceded by a dummy clock cycle, as for the
+    /// 24 and 32 bit reads: `buf` must be one byte longer than the answer,
+    /// which ends up shifted into place at its start
+    fn read_with_dummy_bit<C: ReadClock<SPI>>(
+        &mut self,
+        cmd: Command,
+        buf: &mut [u8],
+        clock: &mut C,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        clock.before_read(&mut self.spi);
+        let res = self
+            .begin_read(cmd)
+            .and_then(|_| self.spi.transfer(buf).map(|_| ()).map_err(Error::Spi))
+            .and_then(|_| self.end_transaction());
+        clock.after_read(&mut self.spi);
+        res?;
+        for i in 0..buf.len() - 1 {
+            buf[i] = (buf[i] << 1) | (buf[i + 1] >> 7);
+        }
+        Ok(())
+    }
+    /// Assert CS and send a read command, the answer following with
+    /// `spi.transfer`
+    fn begin_read(&mut self, cmd: Command) -> Result<(), Error<SpiE, PinE>> {
+        if self.state.powered_down {
+            return Err(Error::PoweredDown);
+        }
+        // the read commands move the address counter
+        #[cfg(feature = "window-cache")]
+        {
+            self.state.last_window = None;
+        }
+        self.select()?;
+        self.write_command(cmd as u8)
+    }
+}
+
+impl<SpiE, PinE, SPI, CS, DC, RESET, TE> Ili9341Surface for Ili9341<SPI, CS, DC, RESET, TE>
+where
+    CS: OutputPin<Error = PinE>,
+    DC: DataCommand<SPI, PinE, SpiError = SpiE>,
+    RESET: OutputPin<Error = PinE>,
+{
+    type Error = Error<SpiE, PinE>;
+
+    fn width(&self) -> usize {
+        Ili9341::width(self)
+    }
+    fn height(&self) -> usize {
+        Ili9341::height(self)
+    }
+    fn set_orientation(&mut self, orientation: Orientation) -> Result<(), Self::Error> {
+        Ili9341::set_orientation(self, orientation)
+    }
+    fn draw_raw(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        Ili9341::draw_raw(self, x0, y0, x1, y1, data)
+    }
+    fn draw_iter<I: IntoIterator<Item = u16>>(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        data: I,
+    ) -> Result<(), Self::Error> {
+        Ili9341::draw_iter(self, x0, y0, x1, y1, data)
+    }
+    fn fill_rect(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        color: u16,
+    ) -> Result<(), Self::Error> {
+        self.fill_pattern(x0, y0, x1, y1, &[color])
+    }
+}
+
+/// Only the state of the display is shown, not the bus and pins
+impl<SPI, CS, DC, RESET, TE> Debug for Ili9341<SPI, CS, DC, RESET, TE>
+where
+    CS: OutputPin,
+    DC: DataCommand<SPI, CS::Error>,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("Ili9341")
+            .field("width", &self.state.width)
+            .field("height", &self.state.height)
+            .field("sleeping", &self.state.sleeping)
+            .field("display_on", &self.state.display_on)
+            .finish()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<SPI, CS, DC, RESET, TE> defmt::Format for Ili9341<SPI, CS, DC, RESET, TE>
+where
+    CS: OutputPin,
+    DC: DataCommand<SPI, CS::Error>,
+{
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "Ili9341 {{ width: {=usize}, height: {=usize}, sleeping: {=bool}, display_on: {=bool} }}",
+            self.state.width,
+            self.state.height,
+            self.state.sleeping,
+            self.state.display_on
+        )
+    }
+}
+
+/// Render text with the built-in [fonts](font/index.html), see
+/// [set_font](struct.Ili9341.html#method.set_font)
+///
+/// Characters are drawn at the cursor, left to right, wrapping to the next
+/// line at the right edge of the screen and back to the top at the bottom.
+/// `'\n'` starts a new line, `'\r'` goes back to the start of the line and
+/// characters missing from the font are drawn as `'?'`. An SPI or pin error
+/// is reported as `core::fmt::Error`.
+#[cfg(feature = "text")]
+impl<SpiE, PinE, SPI, CS, DC, RESET, TE> core::fmt::Write for Ili9341<SPI, CS, DC, RESET, TE>
+where
+    CS: OutputPin<Error = PinE>,
+    DC: DataCommand<SPI, PinE, SpiError = SpiE>,
+    RESET: OutputPin<Error = PinE>,
+{
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for c in s.chars() {
+            self.write_char_at_cursor(c).map_err(|_| core::fmt::Error)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "graphics")]
+use embedded_graphics_core::{
+    pixelcolor::{IntoStorage, Rgb565, Rgb888, RgbColor},
+    primitives::Rectangle,
+};
+
+#[cfg(feature = "graphics")]
+impl<SpiE, PinE, SPI, CS, DC, RESET, TE> Ili9341<SPI, CS, DC, RESET, TE>
+where
+    CS: OutputPin<Error = PinE>,
+    DC: DataCommand<SPI, PinE, SpiError = SpiE>,
+    RESET: OutputPin<Error = PinE>,
+{
+    /// Draw (x, y, rgb565) pixels, skipping the ones off the screen and
+    /// sending each run of contiguous pixels of a row as a single write
+    fn draw_pixel_runs<I>(&mut self, pixels: I) -> Result<(), Error<SpiE, PinE>>
+    where
+        I: IntoIterator<Item = (i32, i32, u16)>,
+    {
+        let mut row = self.pixel_buf();
+        let mut lasty = 0;
+        let mut startx = 0;
+        let mut endx = 0;
+        let width = self.state.width as i32;
+        let height = self.state.height as i32;
+
+        // Filter out pixels that are off the screen
+        let on_screen_pixels = pixels
+            .into_iter()
+            .filter(|&(x, y, _)| x >= 0 && y >= 0 && x < width && y < height);
+
+        for (x, y, color) in on_screen_pixels {
+            // Check if pixel is contiguous with previous pixel
+            if row.is_empty() || (y == lasty && (x == endx + 1) && !row.is_full()) {
+                if row.is_empty() {
+                    // New line of pixels
+                    startx = x;
+                }
+                // Add pixel color to buffer
+                row.push(color);
+                lasty = y;
+                endx = x;
+            } else {
+                // Line of contiguous pixels has ended, so draw it now
+                self.draw_raw(
+                    startx as u16,
+                    lasty as u16,
+                    endx as u16,
+                    lasty as u16,
+                    row.take(),
+                )?;
+
+                // Start new line of contiguous pixels
+                startx = x;
+                row.push(color);
+                lasty = y;
+                endx = x;
+            }
+        }
+        if !row.is_empty() {
+            // Draw remaining pixels in buffer
+            self.draw_raw(
+                startx as u16,
+                lasty as u16,
+                endx as u16,
+                lasty as u16,
+                row.take(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The size follows the current orientation, so it changes after
+/// [set_orientation](struct.Ili9341.html#method.set_orientation).
+///
+/// `Dimensions` is implemented through the blanket implementation for
+/// `OriginDimensions`.
+#[cfg(feature = "graphics")]
+impl<SPI, CS, DC, RESET, TE> embedded_graphics_core::geometry::OriginDimensions
+    for Ili9341<SPI, CS, DC, RESET, TE>
+where
+    CS: OutputPin,
+    DC: DataCommand<SPI, CS::Error>,
+{
+    fn size(&self) -> embedded_graphics_core::geometry::Size {
+        embedded_graphics_core::geometry::Size::new(
+            self.state.width as u32,
+            self.state.height as u32,
+        )
+    }
+}
+
+/// embedded-graphics-core drawing target, so the `DrawTargetExt` adapters
+/// (`translated`, `cropped`, `clipped`, `color_converted`) work on the display
+///
+/// Pixels off the screen are skipped. `fill_solid` and `fill_contiguous` clip
+/// their area to the screen and send it with a single window, so an area
+/// straddling an edge never reaches the controller with addresses out of
+/// range, which would wrap around.
+#[cfg(feature = "graphics")]
+impl<SpiE, PinE, SPI, CS, DC, RESET, TE> embedded_graphics_core::draw_target::DrawTarget
+    for Ili9341<SPI, CS, DC, RESET, TE>
+where
+    CS: OutputPin<Error = PinE>,
+    DC: DataCommand<SPI, PinE, SpiError = SpiE>,
+    RESET: OutputPin<Error = PinE>,
+{
+    type Color = embedded_graphics_core::pixelcolor::Rgb565;
+    type Error = Error<SpiE, PinE>;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = embedded_graphics_core::Pixel<Self::Color>>,
+    {
+        use embedded_graphics_core::pixelcolor::IntoStorage;
+
+        self.draw_pixel_runs(
+            pixels
+                .into_iter()
+                .map(|embedded_graphics_core::Pixel(pos, color)| {
+                    (pos.x, pos.y, color.into_storage())
+                }),
+        )
+    }
+
+    fn fill_contiguous<I>(
+        &mut self,
+        area: &embedded_graphics_core::primitives::Rectangle,
+        colors: I,
+    ) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        use embedded_graphics_core::geometry::Dimensions;
+        use embedded_graphics_core::pixelcolor::IntoStorage;
+        use embedded_graphics_core::primitives::PointsIter;
+
+        let clipped = area.intersection(&self.bounding_box());
+        let br = match clipped.bottom_right() {
+            Some(br) => br,
+            None => return Ok(()),
+        };
+        let (x0, y0) = (clipped.top_left.x as u16, clipped.top_left.y as u16);
+        self.set_window(x0, y0, br.x as u16, br.y as u16)?;
+        if clipped == *area {
+            let n = area.size.width as usize * area.size.height as usize;
+            self.write_iter(colors.into_iter().take(n).map(|c| c.into_storage()))
+        } else {
+            // the colors come in row order over the whole area, keep the
+            // ones inside the window
+            self.write_iter(
+                area.points()
+                    .zip(colors)
+                    .filter(|&(pos, _)| clipped.contains(pos))
+                    .map(|(_, color)| color.into_storage()),
+            )
+        }
+    }
+
+    fn fill_solid(
+        &mut self,
+        area: &embedded_graphics_core::primitives::Rectangle,
+        color: Self::Color,
+    ) -> Result<(), Self::Error> {
+        use embedded_graphics_core::geometry::Dimensions;
+        use embedded_graphics_core::pixelcolor::IntoStorage;
+
+        let area = area.intersection(&self.bounding_box());
+        match area.bottom_right() {
+            Some(br) => self.fill_pattern(
+                area.top_left.x as u16,
+                area.top_left.y as u16,
+                br.x as u16,
+                br.y as u16,
+                &[color.into_storage()],
+            ),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Drawing target accepting `Rgb888` pixels, converting them to `Rgb565` on
+/// the fly
+///
+/// It is created with
+/// [rgb888_target](struct.Ili9341.html#method.rgb888_target).
+#[cfg(feature = "graphics")]
+pub struct Rgb888Adapter<'a, SPI: 'a, CS: 'a, DC: 'a, RESET: 'a, TE: 'a = NoTe>
+where
+    CS: OutputPin,
+    DC: DataCommand<SPI, CS::Error>,
+{
+    display: &'a mut Ili9341<SPI, CS, DC, RESET, TE>,
+}
+
+#[cfg(feature = "graphics")]
+impl<SpiE, PinE, SPI, CS, DC, RESET, TE> Ili9341<SPI, CS, DC, RESET, TE>
+where
+    CS: OutputPin<Error = PinE>,
+    DC: DataCommand<SPI, PinE, SpiError = SpiE>,
+    RESET: OutputPin<Error = PinE>,
+{
+    /// Set the drawing window to exactly the given area, and get a writer
+    /// accepting its pixels in order
+    ///
+    /// If the area is empty or any part of it is outside of the screen,
+    /// `Error::OutOfBounds` is returned.
+    #[allow(clippy::type_complexity)]
+    pub fn area_writer(
+        &mut self,
+        area: &Rectangle,
+    ) -> Result<AreaWriter<'_, SPI, CS, DC, RESET, TE>, Error<SpiE, PinE>> {
+        let (tl, br) = match area.bottom_right() {
+            Some(br) => (area.top_left, br),
+            None => return Err(Error::OutOfBounds),
+        };
+        if tl.x < 0
+            || tl.y < 0
+            || br.x >= self.state.width as i32
+            || br.y >= self.state.height as i32
+        {
+            return Err(Error::OutOfBounds);
+        }
+        self.set_window(tl.x as u16, tl.y as u16, br.x as u16, br.y as u16)?;
+        Ok(AreaWriter {
+            display: self,
+            started: false,
+        })
+    }
+    /// Get a drawing target counting the traffic of each drawing operation,
+    /// see [InstrumentedDisplay](struct.InstrumentedDisplay.html)
+    #[cfg(feature = "instrumentation")]
+    pub fn instrumented(&mut self) -> InstrumentedDisplay<'_, SPI, CS, DC, RESET, TE> {
+        InstrumentedDisplay {
+            inner: self,
+            stats: DrawStats::default(),
+        }
+    }
+    /// Get a drawing target accepting `Rgb888` pixels
+    pub fn rgb888_target(&mut self) -> Rgb888Adapter<'_, SPI, CS, DC, RESET, TE> {
+        Rgb888Adapter { display: self }
+    }
+    /// Get a drawing target collecting the pixels of a line in `buf` before
+    /// sending them, see [ScanlineTarget](struct.ScanlineTarget.html)
+    ///
+    /// `buf` must hold a full line of the screen in the current pixel format,
+    /// that is `width() * bytes_per_pixel` bytes, or `Error::BufferTooSmall`
+    /// is returned.
+    #[allow(clippy::type_complexity)]
+    pub fn scanline_target<'a>(
+        &'a mut self,
+        buf: &'a mut [u8],
+    ) -> Result<ScanlineTarget<'a, SPI, CS, DC, RESET, TE>, Error<SpiE, PinE>> {
+        if buf.len() < self.state.width * self.state.pixel_format.bytes_per_pixel() {
+            return Err(Error::BufferTooSmall);
+        }
+        Ok(ScanlineTarget {
+            display: self,
+            buf,
+            dirty: [0; SCANLINE_DIRTY_SIZE],
+            y: None,
+        })
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl<'a, SPI, CS, DC, RESET, TE> embedded_graphics_core::geometry::OriginDimensions
+    for Rgb888Adapter<'a, SPI, CS, DC, RESET, TE>
+where
+    CS: OutputPin,
+    DC: DataCommand<SPI, CS::Error>,
+{
+    fn size(&self) -> embedded_graphics_core::geometry::Size {
+        self.display.size()
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl<'a, SpiE, PinE, SPI, CS, DC, RESET, TE> embedded_graphics_core::draw_target::DrawTarget
+    for Rgb888Adapter<'a, SPI, CS, DC, RESET, TE>
+where
+    CS: OutputPin<Error = PinE>,
+    DC: DataCommand<SPI, PinE, SpiError = SpiE>,
+    RESET: OutputPin<Error = PinE>,
+{
+    type Color = Rgb888;
+    type Error = Error<SpiE, PinE>;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = embedded_graphics_core::Pixel<Self::Color>>,
+    {
+        self.display.draw_pixel_runs(pixels.into_iter().map(
+            |embedded_graphics_core::Pixel(pos, color)| {
+                let raw = color::rgb888_to_rgb565(color.r(), color.g(), color.b());
+                (pos.x, pos.y, raw)
+            },
+        ))
+    }
+}
+
+/// Size of the bitmap of the pixels set in a scanline, one bit per pixel of
+/// the longest line
+#[cfg(feature = "graphics")]
+const SCANLINE_DIRTY_SIZE: usize = NATIVE_HEIGHT.div_ceil(8);
+
+/// Drawing target buffering the pixels of one line of the screen
+///
+/// It is created with
+/// [scanline_target](struct.Ili9341.html#method.scanline_target).
+///
+/// The pixels are collected in the line buffer as long as they are on the
+/// same line, and sent when a pixel on another line arrives or at the end of
+/// the `draw_iter` call, each run of contiguous pixels of the line as a single
+/// write. This is much faster than drawing to the display directly for items
+/// drawn in row-major order with few contiguous pixels, like text. Pixels
+/// arriving out of row order are still drawn correctly, they just cause more
+/// writes.
+#[cfg(feature = "graphics")]
+pub struct ScanlineTarget<'a, SPI: 'a, CS: 'a, DC: 'a, RESET: 'a, TE: 'a = NoTe>
+where
+    CS: OutputPin,
+    DC: DataCommand<SPI, CS::Error>,
+{
+    display: &'a mut Ili9341<SPI, CS, DC, RESET, TE>,
+    buf: &'a mut [u8],
+    /// Bitmap of the pixels of the line set since the last flush
+    dirty: [u8; SCANLINE_DIRTY_SIZE],
+    /// Line being collected, if any
+    y: Option<u16>,
+}
+
+#[cfg(feature = "graphics")]
+impl<'a, SpiE, PinE, SPI, CS, DC, RESET, TE> ScanlineTarget<'a, SPI, CS, DC, RESET, TE>
+where
+    CS: OutputPin<Error = PinE>,
+    DC: DataCommand<SPI, PinE, SpiError = SpiE>,
+    RESET: OutputPin<Error = PinE>,
+{
+    fn is_dirty(dirty: &[u8; SCANLINE_DIRTY_SIZE], x: usize) -> bool {
+        dirty[x / 8] & (1 << (x % 8)) != 0
+    }
+    /// Send the runs of pixels collected on the current line
+    fn flush(&mut self) -> Result<(), Error<SpiE, PinE>> {
+        let y = match self.y.take() {
+            Some(y) => y,
+            None => return Ok(()),
+        };
+        let dirty = core::mem::replace(&mut self.dirty, [0; SCANLINE_DIRTY_SIZE]);
+        let bpp = self.display.state.pixel_format.bytes_per_pixel();
+        let width = self.display.state.width;
+        let mut x = 0;
+        while x < width {
+            if !Self::is_dirty(&dirty, x) {
+                x += 1;
+                continue;
+            }
+            let start = x;
+            while x < width && Self::is_dirty(&dirty, x) {
+                x += 1;
+            }
+            self.display.draw_raw(
+                start as u16,
+                y,
+                (x - 1) as u16,
+                y,
+                &self.buf[start * bpp..x * bpp],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl<'a, SPI, CS, DC, RESET, TE> embedded_graphics_core::geometry::OriginDimensions
+    for ScanlineTarget<'a, SPI, CS, DC, RESET, TE>
+where
+    CS: OutputPin,
+    DC: DataCommand<SPI, CS::Error>,
+{
+    fn size(&self) -> embedded_graphics_core::geometry::Size {
+        self.display.size()
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl<'a, SpiE, PinE, SPI, CS, DC, RESET, TE> embedded_graphics_core::draw_target::DrawTarget
+    for ScanlineTarget<'a, SPI, CS, DC, RESET, TE>
+where
+    CS: OutputPin<Error = PinE>,
+    DC: DataCommand<SPI, PinE, SpiError = SpiE>,
+    RESET: OutputPin<Error = PinE>,
+{
+    type Color = Rgb565;
+    type Error = Error<SpiE, PinE>;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = embedded_graphics_core::Pixel<Self::Color>>,
+    {
+        let width = self.display.state.width as i32;
+        let height = self.display.state.height as i32;
+        let pixel_format = self.display.state.pixel_format;
+        let lut = self.display.state.brightness_lut;
+        let bpp = pixel_format.bytes_per_pixel();
+
+        let on_screen_pixels =
+            pixels
+                .into_iter()
+                .filter(|embedded_graphics_core::Pixel(point, _)| {
+                    point.x >= 0 && point.y >= 0 && point.x < width && point.y < height
+                });
+
+        for embedded_graphics_core::Pixel(pos, color) in on_screen_pixels {
+            let y = pos.y as u16;
+            if self.y != Some(y) {
+                self.flush()?;
+                self.y = Some(y);
+            }
+            let x = pos.x as usize;
+            let mut color = color.into_storage();
+            if let Some(ref lut) = lut {
+                color = lut.apply(color);
+            }
+            push_pixel(&mut self.buf[x * bpp..], color, pixel_format);
+            self.dirty[x / 8] |= 1 << (x % 8);
+        }
+        self.flush()
+    }
+}
+
+/// Writer streaming pixels into a window, in order from the top left corner
+///
+/// It is created with [area_writer](struct.Ili9341.html#method.area_writer).
+#[cfg(feature = "graphics")]
+pub struct AreaWriter<'a, SPI: 'a, CS: 'a, DC: 'a, RESET: 'a, TE: 'a = NoTe>
+where
+    CS: OutputPin,
+    DC: DataCommand<SPI, CS::Error>,
+{
+    display: &'a mut Ili9341<SPI, CS, DC, RESET, TE>,
+    started: bool,
+}
+
+#[cfg(feature = "graphics")]
+impl<'a, SpiE, PinE, SPI, CS, DC, RESET, TE> AreaWriter<'a, SPI, CS, DC, RESET, TE>
+where
+    CS: OutputPin<Error = PinE>,
+    DC: DataCommand<SPI, PinE, SpiError = SpiE>,
+    RESET: OutputPin<Error = PinE>,
+{
+    /// Write the next pixels of the area
+    ///
+    /// Successive calls continue where the previous one stopped.
+    pub fn write<I: IntoIterator<Item = Rgb565>>(
+        &mut self,
+        pixels: I,
+    ) -> Result<(), Error<SpiE, PinE>> {
+        let cmd = if self.started {
+            Command::MemoryWriteContinue
+        } else {
+            Command::MemoryWrite
+        };
+        self.started = true;
+        self.display
+            .write_memory_iter(cmd, pixels.into_iter().map(|c| c.into_storage()))
+    }
+}
+
+/// Traffic caused by the drawing operations of an
+/// [InstrumentedDisplay](struct.InstrumentedDisplay.html)
+///
+/// Only available with the `instrumentation` feature.
+#[cfg(feature = "instrumentation")]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DrawStats {
+    /// Command bytes sent, including the memory writes
+    pub command_count: u32,
+    /// Parameter and pixel bytes sent after the commands
+    pub bytes_written: u64,
+    /// Drawing windows set up
+    pub window_changes: u32,
+}
+
+/// embedded-graphics drawing target counting what each of its operations
+/// sends to the display, to profile rendering code without a logic analyzer
+///
+/// It is created with
+/// [instrumented](struct.Ili9341.html#method.instrumented), and draws like
+/// the display itself. The counters are taken from the
+/// [Stats](struct.Stats.html) of the display around each operation, so only
+/// the traffic of the operations going through this target is counted.
+///
+/// ```ignore
+/// let mut target = display.instrumented();
+/// Text::new("Hello", Point::new(10, 10), style).draw(&mut target)?;
+/// let stats = target.stats();
+/// ```
+#[cfg(feature = "instrumentation")]
+pub struct InstrumentedDisplay<'a, SPI: 'a, CS: 'a, DC: 'a, RESET: 'a, TE: 'a = NoTe>
+where
+    CS: OutputPin,
+    DC: DataCommand<SPI, CS::Error>,
+{
+    inner: &'a mut Ili9341<SPI, CS, DC, RESET, TE>,
+    stats: DrawStats,
+}
+
+#[cfg(feature = "instrumentation")]
+impl<'a, SpiE, PinE, SPI, CS, DC, RESET, TE> InstrumentedDisplay<'a, SPI, CS, DC, RESET, TE>
+where
+    CS: OutputPin<Error = PinE>,
+    DC: DataCommand<SPI, PinE, SpiError = SpiE>,
+    RESET: OutputPin<Error = PinE>,
+{
+    /// Traffic counted since the target was created or its counters reset
+    pub fn stats(&self) -> &DrawStats {
+        &self.stats
+    }
+    /// Reset the counters to zero
+    pub fn reset_stats(&mut self) {
+        self.stats = DrawStats::default();
+    }
+    /// Run a drawing operation on the display, counting its traffic
+    fn measure<R, F>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut Ili9341<SPI, CS, DC, RESET, TE>) -> R,
+    {
+        let before = *self.inner.stats();
+        let res = f(self.inner);
+        let after = self.inner.stats();
+        let stats = &mut self.stats;
+        stats.command_count = stats
+            .command_count
+            .wrapping_add(after.commands.wrapping_sub(before.commands));
+        stats.bytes_written = stats
+            .bytes_written
+            .wrapping_add(u64::from(after.data_bytes.wrapping_sub(before.data_bytes)));
+        stats.window_changes = stats
+            .window_changes
+            .wrapping_add(after.window_setups.wrapping_sub(before.window_setups));
+        res
+    }
+}
+
+#[cfg(feature = "instrumentation")]
+impl<'a, SPI, CS, DC, RESET, TE> embedded_graphics_core::geometry::OriginDimensions
+    for InstrumentedDisplay<'a, SPI, CS, DC, RESET, TE>
+where
+    CS: OutputPin,
+    DC: DataCommand<SPI, CS::Error>,
+{
+    fn size(&self) -> embedded_graphics_core::geometry::Size {
+        self.inner.size()
+    }
+}
+
+#[cfg(feature = "instrumentation")]
+impl<'a, SpiE, PinE, SPI, CS, DC, RESET, TE> embedded_graphics_core::draw_target::DrawTarget
+    for InstrumentedDisplay<'a, SPI, CS, DC, RESET, TE>
+where
+    CS: OutputPin<Error = PinE>,
+    DC: DataCommand<SPI, PinE, SpiError = SpiE>,
+    RESET: OutputPin<Error = PinE>,
+{
+    type Color = embedded_graphics_core::pixelcolor::Rgb565;
+    type Error = Error<SpiE, PinE>;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = embedded_graphics_core::Pixel<Self::Color>>,
+    {
+        use embedded_graphics_core::draw_target::DrawTarget;
+
+        self.measure(|display| DrawTarget::draw_iter(display, pixels))
+    }
+
+    fn fill_contiguous<I>(
+        &mut self,
+        area: &embedded_graphics_core::primitives::Rectangle,
+        colors: I,
+    ) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.measure(|display| display.fill_contiguous(area, colors))
+    }
+
+    fn fill_solid(
+        &mut self,
+        area: &embedded_graphics_core::primitives::Rectangle,
+        color: Self::Color,
+    ) -> Result<(), Self::Error> {
+        self.measure(|display| display.fill_solid(area, color))
+    }
+}
+
+/// Commands of the ILI9341 controller
+///
+/// They can be sent with
+/// [send_command](struct.Ili9341.html#method.send_command).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    Nop = 0x00,
     SoftwareReset = 0x01,
+    ReadDisplayId = 0x04,
+    ReadDisplayStatus = 0x09,
+    ReadDisplayMadctl = 0x0b,
+    ReadDisplayPixelFormat = 0x0c,
     PowerControlA = 0xcb,
     PowerControlB = 0xcf,
     DriverTimingControlA = 0xe8,
@@ -398,9 +5274,19 @@ enum Command {
     GammaSet = 0x26,
     PositiveGammaCorrection = 0xe0,
     NegativeGammaCorrection = 0xe1,
+    SleepIn = 0x10,
     SleepOut = 0x11,
+    DisplayOff = 0x28,
     DisplayOn = 0x29,
+    TearingEffectLineOff = 0x34,
+    TearingEffectLineOn = 0x35,
     ColumnAddressSet = 0x2a,
     PageAddressSet = 0x2b,
     MemoryWrite = 0x2c,
+    MemoryWriteContinue = 0x3c,
+    MemoryRead = 0x2e,
+    VerticalScrollingDefinition = 0x33,
+    VerticalScrollingStartAddress = 0x37,
+    WriteDisplayBrightness = 0x51,
+    WriteCtrlDisplay = 0x53,
 }
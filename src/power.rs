@@ -0,0 +1,204 @@
+//! Power lifecycle of the display: on, dimmed, off and sleeping
+
+use hal::blocking::delay::DelayMs;
+use hal::digital::v2::OutputPin;
+
+use {Command, DataCommand, Error, Ili9341, NoTe};
+
+/// Value of Write CTRL Display enabling the brightness control and the
+/// backlight control output
+const CTRL_DISPLAY_BRIGHTNESS: u8 = 0x24;
+
+/// Power state of the display
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DisplayState {
+    /// Showing the video memory at full brightness
+    On,
+    /// Showing the video memory at the given brightness, 0 being the darkest
+    Dimming(u8),
+    /// Output turned off, the controller keeps running and can be drawn to
+    Off,
+    /// Output turned off and controller in sleep mode, to save the most power
+    /// while keeping the video memory
+    Sleeping,
+}
+
+/// Display with its power state, moving between the states with the right
+/// commands and after idle times
+///
+/// The valid transitions follow the lifecycle on, dimmed, off, sleeping:
+///
+/// | from       | to                                 |
+/// |------------|------------------------------------|
+/// | `On`       | `Dimming`, `Off`, `Sleeping`       |
+/// | `Dimming`  | `On`, `Dimming`, `Off`, `Sleeping` |
+/// | `Off`      | `On`, `Sleeping`                   |
+/// | `Sleeping` | `On`                               |
+///
+/// Other transitions return `Error::InvalidStateTransition`, and a transition
+/// to the current state does nothing.
+///
+/// Dimming uses the Write Display Brightness command, which drives the
+/// backlight control output (LEDPWM) of the controller: it only has an effect
+/// on modules with the backlight driven from that output.
+///
+/// The display must be on when handed to the state machine, as after
+/// [Ili9341::new](../struct.Ili9341.html#method.new).
+pub struct DisplayStateMachine<SPI, CS, DC, RESET, DELAY, TE = NoTe>
+where
+    CS: OutputPin,
+    DC: DataCommand<SPI, CS::Error>,
+{
+    display: Ili9341<SPI, CS, DC, RESET, TE>,
+    delay: DELAY,
+    state: DisplayState,
+    idle_ticks: u32,
+    /// Whether the current state was reached by `tick`
+    idled: bool,
+    dim_after: Option<(u32, u8)>,
+    sleep_after: Option<u32>,
+    callback: Option<fn(DisplayState, DisplayState)>,
+}
+
+impl<SpiE, PinE, SPI, CS, DC, RESET, DELAY, TE> DisplayStateMachine<SPI, CS, DC, RESET, DELAY, TE>
+where
+    CS: OutputPin<Error = PinE>,
+    DC: DataCommand<SPI, PinE, SpiError = SpiE>,
+    RESET: OutputPin<Error = PinE>,
+    DELAY: DelayMs<u16>,
+{
+    /// Take a display that is on, without any idle timeout
+    pub fn new(display: Ili9341<SPI, CS, DC, RESET, TE>, delay: DELAY) -> Self {
+        DisplayStateMachine {
+            display,
+            delay,
+            state: DisplayState::On,
+            idle_ticks: 0,
+            idled: false,
+            dim_after: None,
+            sleep_after: None,
+            callback: None,
+        }
+    }
+
+    /// Dim the display to `level` after `ticks` calls to
+    /// [tick](#method.tick) without [activity](#method.activity), or never
+    /// with `None`
+    pub fn set_dim_timeout(&mut self, timeout: Option<(u32, u8)>) {
+        self.dim_after = timeout;
+    }
+
+    /// Put the display to sleep after `ticks` calls to [tick](#method.tick)
+    /// without [activity](#method.activity), or never with `None`
+    pub fn set_sleep_timeout(&mut self, ticks: Option<u32>) {
+        self.sleep_after = ticks;
+    }
+
+    /// Set a function called after each state change with the previous and
+    /// the new state
+    pub fn set_callback(&mut self, callback: Option<fn(DisplayState, DisplayState)>) {
+        self.callback = callback;
+    }
+
+    /// Current state
+    pub fn state(&self) -> DisplayState {
+        self.state
+    }
+
+    /// Move to `target`, sending the commands needed
+    pub fn transition_to(&mut self, target: DisplayState) -> Result<(), Error<SpiE, PinE>> {
+        self.change(target)?;
+        self.idled = false;
+        Ok(())
+    }
+
+    fn change(&mut self, target: DisplayState) -> Result<(), Error<SpiE, PinE>> {
+        use self::DisplayState::*;
+
+        let from = self.state;
+        if from == target {
+            return Ok(());
+        }
+        let display = &mut self.display;
+        match (from, target) {
+            (On, Dimming(level)) | (Dimming(_), Dimming(level)) => {
+                if from == On {
+                    display.send_command(Command::WriteCtrlDisplay, &[CTRL_DISPLAY_BRIGHTNESS])?;
+                }
+                display.send_command(Command::WriteDisplayBrightness, &[level])?;
+            }
+            (Dimming(_), On) => {
+                display.send_command(Command::WriteDisplayBrightness, &[0xff])?;
+            }
+            (On, Off) | (Dimming(_), Off) => display.blank(true)?,
+            (On, Sleeping) | (Dimming(_), Sleeping) => {
+                display.blank(true)?;
+                display.sleep(&mut self.delay)?;
+            }
+            (Off, On) => display.blank(false)?,
+            (Off, Sleeping) => display.sleep(&mut self.delay)?,
+            (Sleeping, On) => {
+                display.wake(&mut self.delay)?;
+                display.blank(false)?;
+            }
+            _ => return Err(Error::InvalidStateTransition),
+        }
+        if let Dimming(_) = from {
+            if let Off | Sleeping = target {
+                // come back at full brightness
+                display.send_command(Command::WriteDisplayBrightness, &[0xff])?;
+            }
+        }
+        self.state = target;
+        if let Some(callback) = self.callback {
+            callback(from, target);
+        }
+        Ok(())
+    }
+
+    /// Advance the idle time by one tick, dimming the display or putting it to
+    /// sleep when the timeouts are reached
+    ///
+    /// The new state is returned when it changed.
+    pub fn tick(&mut self) -> Result<Option<DisplayState>, Error<SpiE, PinE>> {
+        self.idle_ticks = self.idle_ticks.saturating_add(1);
+        let target = match (self.sleep_after, self.dim_after) {
+            (Some(ticks), _) if self.idle_ticks >= ticks => DisplayState::Sleeping,
+            (_, Some((ticks, level))) if self.idle_ticks >= ticks => DisplayState::Dimming(level),
+            _ => return Ok(None),
+        };
+        let change = match (self.state, target) {
+            // only a display that is on gets dimmed
+            (DisplayState::On, DisplayState::Dimming(_)) => true,
+            (DisplayState::Sleeping, _) => false,
+            (_, DisplayState::Sleeping) => true,
+            _ => false,
+        };
+        if !change {
+            return Ok(None);
+        }
+        self.change(target)?;
+        self.idled = true;
+        Ok(Some(target))
+    }
+
+    /// Restart the idle time, turning the display back on if it was dimmed or
+    /// sleeping because of it
+    pub fn activity(&mut self) -> Result<(), Error<SpiE, PinE>> {
+        self.idle_ticks = 0;
+        if self.idled {
+            self.transition_to(DisplayState::On)?;
+        }
+        Ok(())
+    }
+
+    /// Get the display, for drawing
+    pub fn display(&mut self) -> &mut Ili9341<SPI, CS, DC, RESET, TE> {
+        &mut self.display
+    }
+
+    /// Get back the display and the delay
+    pub fn release(self) -> (Ili9341<SPI, CS, DC, RESET, TE>, DELAY) {
+        (self.display, self.delay)
+    }
+}
@@ -0,0 +1,332 @@
+//! In-memory model of the display, to run the driver on a host
+//!
+//! [Ili9341Sim](type.Ili9341Sim.html) is the driver itself, talking to a
+//! [SimPanel](struct.SimPanel.html) instead of an SPI bus, so it has the whole
+//! drawing API, including the embedded-graphics target. The panel interprets
+//! the commands like the controller does: window, address counter wrapping,
+//! Memory Write Continue, pixel format and MADCTL (orientation and color
+//! order), and keeps the resulting image in memory.
+//!
+//! ```ignore
+//! let mut display = Ili9341Sim::new_simulator();
+//! display.set_orientation(Orientation::Landscape)?;
+//! display.draw_raw(0, 0, 1, 0, &[0xf8, 0x00, 0x07, 0xe0])?;
+//! let image = display.panel().to_be_bytes();
+//! ```
+
+use core::convert::Infallible;
+
+use hal::blocking::delay::{DelayMs, DelayUs};
+use hal::digital::v2::OutputPin;
+use std::vec::Vec;
+
+use {
+    Command, DataCommand, Error, Ili9341, MADCTL_BGR, MADCTL_MV, MADCTL_MX, MADCTL_MY,
+    NATIVE_HEIGHT, NATIVE_WIDTH,
+};
+
+/// Display driving an in-memory [SimPanel](struct.SimPanel.html)
+pub type Ili9341Sim = Ili9341<SimPanel, SimPin, SimDc, SimPin>;
+
+/// Model of the ILI9341 controller and its panel
+///
+/// The image is kept as rgb565 values, row by row, in the native portrait
+/// frame of the panel (240x320) as seen by someone looking at it: it doesn't
+/// change with the orientation, a landscape image shows up rotated.
+///
+/// The panel can also be fed directly with [command](#method.command) and
+/// [data](#method.data), for example to replay a byte stream recorded from a
+/// real bus.
+pub struct SimPanel {
+    frame: Vec<u16>,
+    madctl: u8,
+    colmod: u8,
+    columns: (u16, u16),
+    pages: (u16, u16),
+    /// Address counter, as (column, page)
+    counter: (u16, u16),
+    cmd: u8,
+    args: [u8; 4],
+    nargs: usize,
+    pixel: [u8; 3],
+    npixel: usize,
+    sleeping: bool,
+    display_on: bool,
+}
+
+impl SimPanel {
+    /// Create a panel in its power-on state, with a black image
+    pub fn new() -> Self {
+        let mut panel = SimPanel {
+            frame: vec![0; NATIVE_WIDTH * NATIVE_HEIGHT],
+            madctl: 0,
+            colmod: 0,
+            columns: (0, 0),
+            pages: (0, 0),
+            counter: (0, 0),
+            cmd: 0,
+            args: [0; 4],
+            nargs: 0,
+            pixel: [0; 3],
+            npixel: 0,
+            sleeping: true,
+            display_on: false,
+        };
+        panel.reset();
+        panel
+    }
+
+    /// Bring the registers back to their defaults, the image is retained
+    pub fn reset(&mut self) {
+        self.madctl = 0;
+        self.colmod = 0x66;
+        self.columns = (0, NATIVE_WIDTH as u16 - 1);
+        self.pages = (0, NATIVE_HEIGHT as u16 - 1);
+        self.counter = (0, 0);
+        self.cmd = 0;
+        self.nargs = 0;
+        self.npixel = 0;
+        self.sleeping = true;
+        self.display_on = false;
+    }
+
+    /// Receive a command byte
+    pub fn command(&mut self, cmd: u8) {
+        self.cmd = cmd;
+        self.nargs = 0;
+        self.npixel = 0;
+        match cmd {
+            x if x == Command::SoftwareReset as u8 => self.reset(),
+            x if x == Command::SleepIn as u8 => self.sleeping = true,
+            x if x == Command::SleepOut as u8 => self.sleeping = false,
+            x if x == Command::DisplayOff as u8 => self.display_on = false,
+            x if x == Command::DisplayOn as u8 => self.display_on = true,
+            x if x == Command::MemoryWrite as u8 => {
+                self.counter = (self.columns.0, self.pages.0);
+            }
+            _ => {}
+        }
+    }
+
+    /// Receive parameter or pixel bytes for the last command
+    pub fn data(&mut self, data: &[u8]) {
+        for &b in data {
+            match self.cmd {
+                x if x == Command::MemoryWrite as u8 || x == Command::MemoryWriteContinue as u8 => {
+                    self.pixel[self.npixel] = b;
+                    self.npixel += 1;
+                    if self.npixel == self.bytes_per_pixel() {
+                        self.npixel = 0;
+                        let color = self.decode_pixel();
+                        self.push_pixel(color);
+                    }
+                }
+                x if x == Command::ColumnAddressSet as u8 || x == Command::PageAddressSet as u8 => {
+                    if self.nargs < 4 {
+                        self.args[self.nargs] = b;
+                        self.nargs += 1;
+                    }
+                    if self.nargs == 4 {
+                        let start = u16::from(self.args[0]) << 8 | u16::from(self.args[1]);
+                        let end = u16::from(self.args[2]) << 8 | u16::from(self.args[3]);
+                        if x == Command::ColumnAddressSet as u8 {
+                            self.columns = (start, end);
+                        } else {
+                            self.pages = (start, end);
+                        }
+                    }
+                }
+                x if x == Command::MemoryAccessControl as u8 && self.nargs == 0 => {
+                    self.madctl = b;
+                    self.nargs = 1;
+                }
+                x if x == Command::PixelFormatSet as u8 && self.nargs == 0 => {
+                    self.colmod = b;
+                    self.nargs = 1;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn bytes_per_pixel(&self) -> usize {
+        // only the MCU interface format matters on SPI
+        if self.colmod & 0x07 == 0x06 {
+            3
+        } else {
+            2
+        }
+    }
+
+    fn decode_pixel(&self) -> u16 {
+        let p = self.pixel;
+        let color = if self.bytes_per_pixel() == 3 {
+            u16::from(p[0] >> 3) << 11 | u16::from(p[1] >> 2) << 5 | u16::from(p[2] >> 3)
+        } else {
+            u16::from(p[0]) << 8 | u16::from(p[1])
+        };
+        if self.madctl & MADCTL_BGR != 0 {
+            color
+        } else {
+            // the panel is BGR: without the BGR bit red and blue are swapped
+            (color & 0x07e0) | (color >> 11) | (color << 11)
+        }
+    }
+
+    fn push_pixel(&mut self, color: u16) {
+        let (column, page) = self.counter;
+        if let Some(i) = self.index(column, page) {
+            self.frame[i] = color;
+        }
+        self.counter = if column >= self.columns.1 {
+            let page = if page >= self.pages.1 {
+                self.pages.0
+            } else {
+                page + 1
+            };
+            (self.columns.0, page)
+        } else {
+            (column + 1, page)
+        };
+    }
+
+    /// Index in the frame of the pixel at an address
+    fn index(&self, column: u16, page: u16) -> Option<usize> {
+        let (column, page) = (usize::from(column), usize::from(page));
+        let (mut x, mut y) = if self.madctl & MADCTL_MV != 0 {
+            (page, column)
+        } else {
+            (column, page)
+        };
+        if x >= NATIVE_WIDTH || y >= NATIVE_HEIGHT {
+            return None;
+        }
+        if self.madctl & MADCTL_MX != 0 {
+            x = NATIVE_WIDTH - 1 - x;
+        }
+        if self.madctl & MADCTL_MY != 0 {
+            y = NATIVE_HEIGHT - 1 - y;
+        }
+        // the panel is mounted mirrored: column 0 is on the right without MX
+        let x = NATIVE_WIDTH - 1 - x;
+        Some(y * NATIVE_WIDTH + x)
+    }
+
+    /// Get the image, row by row in the native portrait frame
+    pub fn frame(&self) -> &[u16] {
+        &self.frame
+    }
+
+    /// Get the image as big endian rgb565 bytes, row by row in the native
+    /// portrait frame
+    pub fn to_be_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.frame.len() * 2);
+        for &color in &self.frame {
+            bytes.push((color >> 8) as u8);
+            bytes.push((color & 0xff) as u8);
+        }
+        bytes
+    }
+
+    /// Get the pixel at (x, y) in the native portrait frame
+    pub fn native_pixel(&self, x: usize, y: usize) -> Option<u16> {
+        if x < NATIVE_WIDTH && y < NATIVE_HEIGHT {
+            Some(self.frame[y * NATIVE_WIDTH + x])
+        } else {
+            None
+        }
+    }
+
+    /// Get the pixel at (x, y) in the coordinates of the current orientation,
+    /// that is where a draw call at (x, y) would put it
+    pub fn pixel(&self, x: u16, y: u16) -> Option<u16> {
+        self.index(x, y).map(|i| self.frame[i])
+    }
+
+    /// Whether the panel is in sleep mode
+    pub fn is_sleeping(&self) -> bool {
+        self.sleeping
+    }
+
+    /// Whether the display output is on
+    pub fn is_display_on(&self) -> bool {
+        self.display_on
+    }
+}
+
+impl Default for SimPanel {
+    fn default() -> Self {
+        SimPanel::new()
+    }
+}
+
+/// D/C line of the simulated bus, passing the bytes to the
+/// [SimPanel](struct.SimPanel.html)
+pub struct SimDc;
+
+impl DataCommand<SimPanel, Infallible> for SimDc {
+    type SpiError = Infallible;
+
+    fn write_command(
+        &mut self,
+        panel: &mut SimPanel,
+        cmd: u8,
+    ) -> Result<(), Error<Infallible, Infallible>> {
+        panel.command(cmd);
+        Ok(())
+    }
+    fn write_data(
+        &mut self,
+        panel: &mut SimPanel,
+        data: &[u8],
+    ) -> Result<(), Error<Infallible, Infallible>> {
+        panel.data(data);
+        Ok(())
+    }
+}
+
+/// Pin of the simulated bus, doing nothing
+pub struct SimPin;
+
+impl OutputPin for SimPin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+/// Delay for the simulated display, returning immediately
+pub struct SimDelay;
+
+impl DelayMs<u16> for SimDelay {
+    fn delay_ms(&mut self, _ms: u16) {}
+}
+
+impl DelayUs<u16> for SimDelay {
+    fn delay_us(&mut self, _us: u16) {}
+}
+
+impl Ili9341Sim {
+    /// Create a simulated display, initialized like with
+    /// [new](struct.Ili9341.html#method.new)
+    pub fn new_simulator() -> Self {
+        match Ili9341::new(SimPanel::new(), SimPin, SimDc, SimPin, &mut SimDelay) {
+            Ok(display) => display,
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Get the simulated panel, holding the image
+    pub fn panel(&self) -> &SimPanel {
+        &self.spi
+    }
+
+    /// Get the simulated panel mutably, to feed it bytes directly
+    pub fn panel_mut(&mut self) -> &mut SimPanel {
+        &mut self.spi
+    }
+}
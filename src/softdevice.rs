@@ -0,0 +1,39 @@
+//! SPI wrapper for use alongside the nRF SoftDevice
+//!
+//! See the "nRF SoftDevice" section of the README for the tradeoffs.
+
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+use hal::blocking::spi;
+
+/// SPI bus shared through a mutex, written inside a critical section
+///
+/// Each write runs in `critical_section::with`, so no interrupt handler can
+/// use the bus in the middle of the transaction. With the
+/// `critical-section-impl` feature of nrf-softdevice enabled in the
+/// application, the critical section is a SoftDevice critical region
+/// (`sd_nvic_critical_region_enter`): the application interrupts are masked
+/// while the SoftDevice keeps servicing its own ones. The bus itself stays
+/// available to the rest of the application through the mutex.
+pub struct SoftdeviceSpiWrapper<'a, SPI: 'a> {
+    spi: &'a Mutex<RefCell<SPI>>,
+}
+
+impl<'a, SPI> SoftdeviceSpiWrapper<'a, SPI> {
+    /// Wrap a shared SPI bus
+    pub fn new(spi: &'a Mutex<RefCell<SPI>>) -> Self {
+        SoftdeviceSpiWrapper { spi }
+    }
+}
+
+impl<'a, SPI> spi::Write<u8> for SoftdeviceSpiWrapper<'a, SPI>
+where
+    SPI: spi::Write<u8>,
+{
+    type Error = SPI::Error;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        critical_section::with(|cs| self.spi.borrow(cs).borrow_mut().write(words))
+    }
+}
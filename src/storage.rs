@@ -0,0 +1,141 @@
+//! `embedded-storage` view of the video memory
+
+use core::cmp::min;
+use core::iter::once;
+
+use embedded_storage::{ReadStorage, Storage};
+use hal::blocking::spi;
+use hal::digital::v2::OutputPin;
+
+use {Error, Ili9341, Ili9341Surface};
+
+/// Number of pixels read at once
+const READ_CHUNK: usize = 32;
+
+/// The video memory as a flat byte storage, for the frameworks writing their
+/// output through a `Storage`, for example with DMA
+///
+/// Each pixel takes 2 bytes, holding its big endian rgb565 value, and the
+/// pixels are laid out row by row in the current orientation: the byte at
+/// offset `A` belongs to the pixel at column `(A / 2) % width` and row
+/// `(A / 2) / width`. The bytes are converted to the current pixel format when
+/// written.
+///
+/// The display can only be written a pixel at a time: a write starting or
+/// ending in the middle of a pixel first reads back the other byte of that
+/// pixel. The reads are done without any
+/// [ReadClock](trait.ReadClock.html) hook, so the SPI clock must be low
+/// enough for them.
+impl<SpiE, PinE, SPI, CS, DC, RESET, TE> ReadStorage for Ili9341<SPI, CS, DC, RESET, TE>
+where
+    SPI: spi::Write<u8, Error = SpiE> + spi::Transfer<u8, Error = SpiE>,
+    CS: OutputPin<Error = PinE>,
+    DC: OutputPin<Error = PinE>,
+    RESET: OutputPin<Error = PinE>,
+{
+    type Error = Error<SpiE, PinE>;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let offset = offset as usize;
+        if offset + bytes.len() > self.capacity() {
+            return Err(Error::OutOfBounds);
+        }
+        let width = self.width();
+        let end = offset + bytes.len();
+        let mut buf = [0; READ_CHUNK];
+        let mut pixel = offset / 2;
+        while pixel * 2 < end {
+            // stay on one row
+            let (x, y) = (pixel % width, pixel / width);
+            let n = min(min(end.div_ceil(2) - pixel, width - x), READ_CHUNK);
+            let (x, y) = (x as u16, y as u16);
+            self.read_memory(x, y, x + n as u16 - 1, y, &mut buf[..n], &mut ())?;
+            for (i, color) in buf[..n].iter().enumerate() {
+                for (j, &b) in color.to_be_bytes().iter().enumerate() {
+                    let addr = (pixel + i) * 2 + j;
+                    if addr >= offset && addr < end {
+                        bytes[addr - offset] = b;
+                    }
+                }
+            }
+            pixel += n;
+        }
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.width() * self.height() * 2
+    }
+}
+
+impl<SpiE, PinE, SPI, CS, DC, RESET, TE> Storage for Ili9341<SPI, CS, DC, RESET, TE>
+where
+    SPI: spi::Write<u8, Error = SpiE> + spi::Transfer<u8, Error = SpiE>,
+    CS: OutputPin<Error = PinE>,
+    DC: OutputPin<Error = PinE>,
+    RESET: OutputPin<Error = PinE>,
+{
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let mut offset = offset as usize;
+        if offset + bytes.len() > self.capacity() {
+            return Err(Error::OutOfBounds);
+        }
+        let mut bytes = bytes;
+        if offset % 2 == 1 && !bytes.is_empty() {
+            let mut hi = [0];
+            self.read(offset as u32 - 1, &mut hi)?;
+            let color = u16::from_be_bytes([hi[0], bytes[0]]);
+            write_pixels(self, offset / 2, 1, once(color))?;
+            offset += 1;
+            bytes = &bytes[1..];
+        }
+        let whole = bytes.len() - bytes.len() % 2;
+        let pixels = bytes[..whole]
+            .chunks(2)
+            .map(|p| u16::from_be_bytes([p[0], p[1]]));
+        write_pixels(self, offset / 2, whole / 2, pixels)?;
+        if whole < bytes.len() {
+            let mut lo = [0];
+            self.read((offset + whole + 1) as u32, &mut lo)?;
+            let color = u16::from_be_bytes([bytes[whole], lo[0]]);
+            write_pixels(self, (offset + whole) / 2, 1, once(color))?;
+        }
+        Ok(())
+    }
+}
+
+/// Write `len` pixels starting at the pixel `start` of the flat layout, with
+/// a window for the partial rows and a single one for the full rows between
+fn write_pixels<S, I>(
+    display: &mut S,
+    start: usize,
+    len: usize,
+    mut pixels: I,
+) -> Result<(), S::Error>
+where
+    S: Ili9341Surface,
+    I: Iterator<Item = u16>,
+{
+    let width = display.width();
+    let end = start + len;
+    let mut pixel = start;
+    while pixel < end {
+        let (x, y) = (pixel % width, pixel / width);
+        let (x1, y1, n) = if x != 0 || end - pixel < width {
+            let x1 = min(width, x + end - pixel) - 1;
+            (x1, y, x1 - x + 1)
+        } else {
+            let rows = (end - pixel) / width;
+            (width - 1, y + rows - 1, rows * width)
+        };
+        display.draw_iter(
+            x as u16,
+            y as u16,
+            x1 as u16,
+            y1 as u16,
+            pixels.by_ref().take(n),
+        )?;
+        pixel += n;
+    }
+    Ok(())
+}
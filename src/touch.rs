@@ -0,0 +1,224 @@
+//! Driver for the XPT2046 resistive touch controller
+//!
+//! Most ILI9341 modules carry an XPT2046 on the same SPI header, with its own
+//! CS (`T_CS`) and pen interrupt (`T_IRQ`) lines. Both chips use SPI mode 0,
+//! but the XPT2046 is limited to about 2.5MHz, much less than the display:
+//! when the bus is shared, for example through a `RefCell` or a bus sharing
+//! crate, its clock must be lowered for the touch reads. Only one of the two
+//! CS lines must be low at a time, which both drivers ensure by releasing CS
+//! at the end of each of their transactions.
+//!
+//! ```ignore
+//! let mut touch = Xpt2046::new(spi, t_cs, t_irq);
+//! let calibration = Calibration::new(3800, 250, 300, 3750);
+//! if let Some(raw) = touch.read()? {
+//!     let (x, y) = calibration.to_display(raw, &display);
+//! }
+//! ```
+
+use hal::blocking::spi;
+use hal::digital::v2::{InputPin, OutputPin};
+
+use {DataCommand, Error, Ili9341, Orientation, NATIVE_HEIGHT, NATIVE_WIDTH};
+
+/// Largest number of samples taken for each reading
+pub const MAX_SAMPLES: usize = 15;
+
+/// Control bytes starting a 12-bit differential conversion of each channel,
+/// powering down between conversions so the pen interrupt stays enabled
+const READ_X: u8 = 0xd0;
+const READ_Y: u8 = 0x90;
+const READ_Z1: u8 = 0xb0;
+const READ_Z2: u8 = 0xc0;
+
+/// Raw 12-bit reading of the touch controller
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RawTouch {
+    pub x: u16,
+    pub y: u16,
+    /// Pressure, higher when pressing harder
+    pub z: u16,
+}
+
+/// XPT2046 on an SPI bus, with its CS and pen interrupt lines
+pub struct Xpt2046<SPI, CS, IRQ> {
+    spi: SPI,
+    cs: CS,
+    irq: IRQ,
+    samples: usize,
+}
+
+impl<SpiE, PinE, SPI, CS, IRQ> Xpt2046<SPI, CS, IRQ>
+where
+    SPI: spi::Transfer<u8, Error = SpiE>,
+    CS: OutputPin<Error = PinE>,
+    IRQ: InputPin<Error = PinE>,
+{
+    /// Create the driver, taking the median of 5 samples for each reading
+    pub fn new(spi: SPI, cs: CS, irq: IRQ) -> Self {
+        Xpt2046 {
+            spi,
+            cs,
+            irq,
+            samples: 5,
+        }
+    }
+
+    /// Set the number of samples taken for each reading, from 1 to
+    /// [MAX_SAMPLES](constant.MAX_SAMPLES.html), their median being returned
+    ///
+    /// More samples filter out more noise, at the cost of longer readings.
+    pub fn set_samples(&mut self, samples: usize) {
+        self.samples = samples.clamp(1, MAX_SAMPLES);
+    }
+
+    /// Whether the panel is touched, from the pen interrupt line
+    ///
+    /// The line is pulled low by the controller while the panel is touched.
+    pub fn is_touched(&self) -> Result<bool, Error<SpiE, PinE>> {
+        self.irq.is_low().map_err(Error::InputPin)
+    }
+
+    /// Read the position and pressure if the panel is touched
+    pub fn read(&mut self) -> Result<Option<RawTouch>, Error<SpiE, PinE>> {
+        if !self.is_touched()? {
+            return Ok(None);
+        }
+        self.read_raw().map(Some)
+    }
+
+    /// Read the position and pressure, whether the panel is touched or not
+    ///
+    /// Each value is the median of the samples taken, see
+    /// [set_samples](#method.set_samples).
+    pub fn read_raw(&mut self) -> Result<RawTouch, Error<SpiE, PinE>> {
+        let x = self.read_median(READ_X)?;
+        let y = self.read_median(READ_Y)?;
+        let z1 = self.read_median(READ_Z1)?;
+        let z2 = self.read_median(READ_Z2)?;
+        Ok(RawTouch {
+            x,
+            y,
+            z: (z1 + 4095).saturating_sub(z2),
+        })
+    }
+
+    /// Get back the bus and the pins
+    pub fn release(self) -> (SPI, CS, IRQ) {
+        (self.spi, self.cs, self.irq)
+    }
+
+    fn read_median(&mut self, cmd: u8) -> Result<u16, Error<SpiE, PinE>> {
+        let mut values = [0; MAX_SAMPLES];
+        let values = &mut values[..self.samples];
+        self.cs.set_low().map_err(Error::OutputPin)?;
+        let res = values.iter_mut().try_for_each(|v| {
+            *v = self.read_channel(cmd)?;
+            Ok(())
+        });
+        self.cs.set_high().map_err(Error::OutputPin)?;
+        res?;
+        values.sort_unstable();
+        Ok(values[values.len() / 2])
+    }
+
+    fn read_channel(&mut self, cmd: u8) -> Result<u16, Error<SpiE, PinE>> {
+        let mut buf = [cmd, 0, 0];
+        let res = self.spi.transfer(&mut buf).map_err(Error::Spi)?;
+        // 12 bits, starting after the busy bit following the control byte
+        Ok((u16::from(res[1]) << 8 | u16::from(res[2])) >> 3)
+    }
+}
+
+/// Mapping of the raw readings to the coordinates of the display
+///
+/// It is given as the raw `x` and `y` readings at the edges of the panel, in
+/// its native portrait frame as seen by someone looking at it: `x_left` and
+/// `x_right` for its left and right columns, `y_top` and `y_bottom` for its
+/// top and bottom rows. They depend on how the touch panel is wired and
+/// differ from one module to another, so they are usually measured by
+/// touching the corners. A pair may be in any order, which inverts the axis.
+/// The X and Y channels of some modules are swapped relative to the display,
+/// see [swap_axes](#method.swap_axes).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Calibration {
+    pub x_left: u16,
+    pub x_right: u16,
+    pub y_top: u16,
+    pub y_bottom: u16,
+    /// Whether the X channel measures the vertical axis of the panel
+    pub swap_axes: bool,
+}
+
+impl Calibration {
+    /// Create a calibration from the readings at the edges of the panel
+    pub fn new(x_left: u16, x_right: u16, y_top: u16, y_bottom: u16) -> Self {
+        Calibration {
+            x_left,
+            x_right,
+            y_top,
+            y_bottom,
+            swap_axes: false,
+        }
+    }
+
+    /// The same calibration, for a module whose X channel measures the
+    /// vertical axis of the panel and Y the horizontal one
+    pub fn swap_axes(mut self) -> Self {
+        self.swap_axes = true;
+        self
+    }
+
+    /// Map a reading to the native portrait frame of the panel, clamped to
+    /// the panel
+    pub fn to_native(&self, raw: RawTouch) -> (u16, u16) {
+        let (rx, ry) = if self.swap_axes {
+            (raw.y, raw.x)
+        } else {
+            (raw.x, raw.y)
+        };
+        (
+            scale(rx, self.x_left, self.x_right, NATIVE_WIDTH),
+            scale(ry, self.y_top, self.y_bottom, NATIVE_HEIGHT),
+        )
+    }
+
+    /// Map a reading to the coordinates of the display, in its current
+    /// orientation, as used by the drawing methods
+    pub fn to_display<SPI, CS, DC, RESET, TE>(
+        &self,
+        raw: RawTouch,
+        display: &Ili9341<SPI, CS, DC, RESET, TE>,
+    ) -> (u16, u16)
+    where
+        CS: OutputPin,
+        DC: DataCommand<SPI, CS::Error>,
+    {
+        let (x, y) = self.to_native(raw);
+        let (w, h) = (NATIVE_WIDTH as u16, NATIVE_HEIGHT as u16);
+        let (x, y) = match display.state.orientation.rotated(display.state.rotation) {
+            Orientation::Portrait => (x, y),
+            Orientation::Landscape => (y, w - 1 - x),
+            Orientation::PortraitFlipped => (w - 1 - x, h - 1 - y),
+            Orientation::LandscapeFlipped => (h - 1 - y, x),
+        };
+        let (w, h) = (display.state.width as u16, display.state.height as u16);
+        match display.state.mirror {
+            (false, false) => (x, y),
+            (true, false) => (w - 1 - x, y),
+            (false, true) => (x, h - 1 - y),
+            (true, true) => (w - 1 - x, h - 1 - y),
+        }
+    }
+}
+
+/// Map `raw` from the range between `from` and `to` to `0..len`
+fn scale(raw: u16, from: u16, to: u16, len: usize) -> u16 {
+    let (raw, from, to) = (i32::from(raw), i32::from(from), i32::from(to));
+    if from == to {
+        return 0;
+    }
+    let max = len as i32 - 1;
+    let v = (raw - from) * max / (to - from);
+    v.clamp(0, max) as u16
+}
@@ -0,0 +1,72 @@
+//! Rotation of images held as rgb565 pixel buffers
+//!
+//! The buffers hold the pixels row by row, like the ones given to
+//! [draw_raw_slice](../struct.Ili9341.html#method.draw_raw_slice). This
+//! allows storing an image once and drawing it in any orientation.
+
+/// The buffers given don't match the size of the image
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SizeMismatch;
+
+fn check(src: &[u16], dst: &[u16], width: usize, height: usize) -> Result<(), SizeMismatch> {
+    match width.checked_mul(height) {
+        Some(len) if src.len() == len && dst.len() == len => Ok(()),
+        _ => Err(SizeMismatch),
+    }
+}
+
+/// Rotate an image of `width` x `height` pixels by 90 degrees clockwise
+///
+/// `dst` receives an image of `height` x `width` pixels.
+pub fn rotate_90_cw(
+    src: &[u16],
+    dst: &mut [u16],
+    width: usize,
+    height: usize,
+) -> Result<(), SizeMismatch> {
+    check(src, dst, width, height)?;
+    for (i, &color) in src.iter().enumerate() {
+        let (x, y) = (i % width, i / width);
+        dst[x * height + (height - 1 - y)] = color;
+    }
+    Ok(())
+}
+
+/// Rotate an image of `width` x `height` pixels by 180 degrees
+pub fn rotate_180(
+    src: &[u16],
+    dst: &mut [u16],
+    width: usize,
+    height: usize,
+) -> Result<(), SizeMismatch> {
+    check(src, dst, width, height)?;
+    for (d, &color) in dst.iter_mut().rev().zip(src) {
+        *d = color;
+    }
+    Ok(())
+}
+
+/// Rotate an image by 180 degrees, in place
+///
+/// This only reverses the order of the pixels, so it works for any size.
+pub fn rotate_180_inplace(data: &mut [u16]) {
+    data.reverse();
+}
+
+/// Rotate an image of `width` x `height` pixels by 270 degrees clockwise
+/// (90 degrees counterclockwise)
+///
+/// `dst` receives an image of `height` x `width` pixels.
+pub fn rotate_270_cw(
+    src: &[u16],
+    dst: &mut [u16],
+    width: usize,
+    height: usize,
+) -> Result<(), SizeMismatch> {
+    check(src, dst, width, height)?;
+    for (i, &color) in src.iter().enumerate() {
+        let (x, y) = (i % width, i / width);
+        dst[(width - 1 - x) * height + y] = color;
+    }
+    Ok(())
+}
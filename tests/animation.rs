@@ -0,0 +1,87 @@
+//! Frame pacing against the caller's counter
+
+#![cfg(feature = "animation")]
+
+extern crate embedded_hal as hal;
+extern crate ili9341;
+
+mod common;
+
+use common::{commands, initialized_display, take_ops};
+use ili9341::animation::FramePacer;
+
+#[test]
+fn paces_frames() {
+    // 20ms period, 1kHz counter
+    let mut pacer = FramePacer::new(20);
+    assert!(pacer.should_present(1000, 1000));
+    assert!(!pacer.should_present(1010, 1000));
+    assert!(!pacer.should_present(1019, 1000));
+    assert!(pacer.should_present(1023, 1000));
+    // the next period starts at the frame presented
+    assert!(!pacer.should_present(1040, 1000));
+    assert!(pacer.should_present(1090, 1000));
+
+    let stats = pacer.stats();
+    assert_eq!(stats.frames, 3);
+    assert_eq!(stats.skipped, 3);
+    assert_eq!(stats.late_frames, 1);
+    assert_eq!(stats.last_frame_us, 67_000);
+    assert_eq!(stats.max_frame_us, 67_000);
+    assert_eq!(stats.last_lateness_us, 47_000);
+}
+
+#[test]
+fn counter_wraps() {
+    let mut pacer = FramePacer::new(1);
+    assert!(pacer.should_present(u32::MAX - 500, 1_000_000));
+    assert!(!pacer.should_present(u32::MAX, 1_000_000));
+    assert!(pacer.should_present(499, 1_000_000));
+    assert_eq!(pacer.stats().last_frame_us, 1000);
+}
+
+#[test]
+fn present_window() {
+    let (mut display, bus) = initialized_display();
+    let frame = vec![0xa5; display.size_in_bytes()];
+    let mut pacer = FramePacer::new(10);
+    assert!(pacer.present_window(&mut display, 0, 1000, &frame).unwrap());
+    assert!(!pacer.present_window(&mut display, 5, 1000, &frame).unwrap());
+    let commands = commands(&take_ops(&bus));
+    assert_eq!(commands.len(), 3);
+    assert_eq!(commands[0], (0x2a, vec![0x00, 0x00, 0x00, 0xef]));
+    assert_eq!(commands[1], (0x2b, vec![0x00, 0x00, 0x01, 0x3f]));
+    assert_eq!(commands[2], (0x2c, frame));
+}
+
+#[test]
+fn long_gaps_saturate() {
+    // 10000s at 1Hz do not fit in u32 microseconds
+    let mut pacer = FramePacer::new(20);
+    assert!(pacer.should_present(0, 1));
+    assert!(pacer.should_present(10_000, 1));
+    assert_eq!(pacer.stats().last_frame_us, u32::MAX);
+    assert_eq!(pacer.stats().late_frames, 1);
+}
+
+#[test]
+fn transfer_time_stretches_period() {
+    let mut pacer = FramePacer::new(10);
+    pacer.set_transfer_time_us(30_000);
+    assert!(pacer.should_present(0, 1000));
+    assert!(!pacer.should_present(20, 1000));
+    assert!(pacer.should_present(31, 1000));
+    assert_eq!(pacer.stats().late_frames, 0);
+    assert_eq!(pacer.stats().last_lateness_us, 1000);
+}
+
+#[test]
+fn transfer_time() {
+    let (mut display, _bus) = initialized_display();
+    // 153600 pixel bytes and 11 command bytes at 40MHz
+    assert_eq!(display.transfer_time_us(240, 320, 40_000_000), 30_722);
+    // 10 pixels in chunks of 4: Memory Write and 2 Memory Write Continue
+    display.set_max_transfer_size(Some(8));
+    assert_eq!(display.transfer_time_us(10, 1, 1_000_000), (20 + 13) * 8);
+    assert_eq!(display.transfer_time_us(240, 320, 0), u32::MAX);
+}
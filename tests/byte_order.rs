@@ -0,0 +1,181 @@
+//! Bytes sent for known colors through each of the drawing methods
+
+extern crate embedded_hal as hal;
+extern crate ili9341;
+
+mod common;
+
+use common::{initialized_display, pixel_bytes, take_ops, MockDisplay};
+use ili9341::color::BrightnessLut;
+use ili9341::PixelFormat;
+
+const RED: u16 = 0xf800;
+const GREEN: u16 = 0x07e0;
+const BLUE: u16 = 0x001f;
+
+const RGB565_BYTES: [u8; 6] = [0xf8, 0x00, 0x07, 0xe0, 0x00, 0x1f];
+const RGB666_BYTES: [u8; 9] = [0xfc, 0x00, 0x00, 0x00, 0xfc, 0x00, 0x00, 0x00, 0xfc];
+
+/// Check the bytes sent by `draw` for red, green and blue pixels, in both
+/// pixel formats
+fn check<F>(draw: F)
+where
+    F: Fn(&mut MockDisplay, &[u16]),
+{
+    let colors = [RED, GREEN, BLUE];
+    for &(format, expected) in &[
+        (PixelFormat::Rgb565, &RGB565_BYTES[..]),
+        (PixelFormat::Rgb666, &RGB666_BYTES[..]),
+    ] {
+        let (mut display, bus) = initialized_display();
+        display.set_pixel_format(format).unwrap();
+        take_ops(&bus);
+        draw(&mut display, &colors);
+        assert_eq!(pixel_bytes(&take_ops(&bus)), expected, "{:?}", format);
+    }
+}
+
+#[test]
+fn draw_iter() {
+    check(|display, colors| {
+        display
+            .draw_iter(0, 0, 2, 0, colors.iter().cloned())
+            .unwrap()
+    });
+}
+
+#[test]
+fn draw_raw_slice() {
+    check(|display, colors| display.draw_raw_slice(0, 0, 2, 0, colors).unwrap());
+}
+
+#[test]
+fn draw_indexed() {
+    check(|display, colors| {
+        display
+            .draw_indexed(0, 0, 2, 0, vec![0, 1, 2], colors)
+            .unwrap()
+    });
+}
+
+#[test]
+fn set_pixel() {
+    check(|display, colors| {
+        for (x, &color) in colors.iter().enumerate() {
+            display.set_pixel(x as u16, 0, color).unwrap();
+        }
+    });
+}
+
+#[test]
+fn fill_pattern() {
+    check(|display, colors| display.fill_pattern(0, 0, 2, 0, colors).unwrap());
+}
+
+#[test]
+fn draw_raw_is_unchanged() {
+    let (mut display, bus) = initialized_display();
+    display.draw_raw(0, 0, 2, 0, &RGB565_BYTES).unwrap();
+    assert_eq!(pixel_bytes(&take_ops(&bus)), RGB565_BYTES);
+}
+
+#[test]
+fn rgb666_words() {
+    let (mut display, bus) = initialized_display();
+    display.set_pixel_format(PixelFormat::Rgb666).unwrap();
+    take_ops(&bus);
+    let words = [[0xfc, 0, 0], [0, 0xfc, 0], [0, 0, 0xfc]];
+    display
+        .draw_words(0, 0, 2, 0, words.iter().cloned())
+        .unwrap();
+    assert_eq!(pixel_bytes(&take_ops(&bus)), RGB666_BYTES);
+}
+
+/// Tables swapping the levels of each channel end for end
+fn reversed_lut() -> BrightnessLut {
+    let mut r = [0; 32];
+    let mut g = [0; 64];
+    let mut b = [0; 32];
+    for i in 0..64 {
+        if i < 32 {
+            r[i] = 31 - i as u8;
+            b[i] = 31 - i as u8;
+        }
+        g[i] = 63 - i as u8;
+    }
+    BrightnessLut::from_arrays(r, g, b)
+}
+
+/// Red, green and blue through the reversed tables
+const REVERSED_BYTES: [u8; 6] = [0x07, 0xff, 0xf8, 0x1f, 0xff, 0xe0];
+
+/// Check that `draw` applies the brightness tables to red, green and blue
+/// pixels
+fn check_lut<F>(draw: F)
+where
+    F: Fn(&mut MockDisplay, &[u16]),
+{
+    let (mut display, bus) = initialized_display();
+    display.set_brightness_lut(Some(reversed_lut()));
+    draw(&mut display, &[RED, GREEN, BLUE]);
+    assert_eq!(pixel_bytes(&take_ops(&bus)), REVERSED_BYTES);
+}
+
+#[test]
+fn brightness_lut_on_every_entry_point() {
+    check_lut(|display, colors| {
+        display
+            .draw_iter(0, 0, 2, 0, colors.iter().cloned())
+            .unwrap()
+    });
+    check_lut(|display, colors| display.draw_raw_slice(0, 0, 2, 0, colors).unwrap());
+    check_lut(|display, colors| {
+        display
+            .draw_indexed(0, 0, 2, 0, vec![0, 1, 2], colors)
+            .unwrap()
+    });
+    check_lut(|display, colors| {
+        for (x, &color) in colors.iter().enumerate() {
+            display.set_pixel(x as u16, 0, color).unwrap();
+        }
+    });
+    check_lut(|display, colors| display.fill_pattern(0, 0, 2, 0, colors).unwrap());
+    check_lut(|display, colors| {
+        display
+            .draw_iter_with_color_key(0, 0, 2, 0, colors.iter().cloned(), 0x1234)
+            .unwrap()
+    });
+}
+
+#[test]
+fn brightness_lut() {
+    let (mut display, bus) = initialized_display();
+    display.set_brightness_lut(Some(reversed_lut()));
+    display
+        .draw_iter(0, 0, 2, 0, vec![RED, GREEN, BLUE])
+        .unwrap();
+    assert_eq!(pixel_bytes(&take_ops(&bus)), REVERSED_BYTES);
+    // raw bytes are not adjusted
+    display.draw_raw(0, 0, 2, 0, &RGB565_BYTES).unwrap();
+    assert_eq!(pixel_bytes(&take_ops(&bus)), RGB565_BYTES);
+
+    display.set_brightness_lut(Some(BrightnessLut::identity()));
+    display
+        .draw_iter(0, 0, 2, 0, vec![RED, GREEN, BLUE])
+        .unwrap();
+    assert_eq!(pixel_bytes(&take_ops(&bus)), RGB565_BYTES);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn gamma_corrected_lut() {
+    assert_eq!(
+        BrightnessLut::gamma_corrected(1.0),
+        BrightnessLut::identity()
+    );
+    let lut = BrightnessLut::gamma_corrected(2.0);
+    // the extremes don't move, the mid tones get darker
+    assert_eq!(lut.apply(0xffff), 0xffff);
+    assert_eq!(lut.apply(0), 0);
+    assert_eq!(lut.apply(16 << 11), 8 << 11);
+}
@@ -0,0 +1,126 @@
+//! Pixels are never split across SPI writes, whatever the number of pixels
+
+extern crate embedded_hal as hal;
+extern crate ili9341;
+
+mod common;
+
+use common::{initialized_display, pixel_bytes, take_ops, Op};
+use ili9341::PixelFormat;
+
+/// Decode the pixel bytes sent in `format` back to rgb565 values
+fn decode(bytes: &[u8], format: PixelFormat) -> Vec<u16> {
+    match format {
+        PixelFormat::Rgb565 => bytes
+            .chunks(2)
+            .map(|p| u16::from(p[0]) << 8 | u16::from(p[1]))
+            .collect(),
+        PixelFormat::Rgb666 => bytes
+            .chunks(3)
+            .map(|p| u16::from(p[0] >> 3) << 11 | u16::from(p[1] >> 2) << 5 | u16::from(p[2] >> 3))
+            .collect(),
+    }
+}
+
+/// Draw `count` distinct pixels on a row and check that every SPI write of
+/// pixel data holds whole pixels, and that the pixels come out unchanged
+fn check(format: PixelFormat, count: usize, max_transfer: Option<usize>) {
+    let (mut display, bus) = initialized_display();
+    display.set_pixel_format(format).unwrap();
+    display.set_max_transfer_size(max_transfer);
+    take_ops(&bus);
+
+    // every color survives the rgb666 round trip
+    let pixels: Vec<u16> = (0..count as u16)
+        .map(|i| (i % 32) << 11 | (i * 7 % 64) << 5 | (31 - i % 32))
+        .collect();
+    display
+        .draw_iter(0, 0, count as u16 - 1, 0, pixels.iter().cloned())
+        .unwrap();
+
+    let ops = take_ops(&bus);
+    let bpp = format.bytes_per_pixel();
+    let mut cmd = 0;
+    for op in &ops {
+        match op {
+            Op::Command(c) => cmd = *c,
+            Op::Data(data) if cmd == 0x2c || cmd == 0x3c => assert_eq!(
+                data.len() % bpp,
+                0,
+                "{:?}, {} pixels: write of {} bytes",
+                format,
+                count,
+                data.len()
+            ),
+            _ => {}
+        }
+    }
+    assert_eq!(
+        decode(&pixel_bytes(&ops), format),
+        pixels,
+        "{:?}, {} pixels",
+        format,
+        count
+    );
+}
+
+// The pixels are batched in a buffer of 64 bytes, that is 32 rgb565 pixels or
+// 21 rgb666 ones (63 bytes, one byte short of full).
+
+#[test]
+fn rgb565_buffer_boundaries() {
+    for &count in &[1, 31, 32, 33, 63, 64, 65, 100] {
+        check(PixelFormat::Rgb565, count, None);
+    }
+}
+
+#[test]
+fn rgb666_buffer_boundaries() {
+    for &count in &[1, 20, 21, 22, 42, 43, 64, 100] {
+        check(PixelFormat::Rgb666, count, None);
+    }
+}
+
+#[test]
+fn odd_max_transfer_size() {
+    for &max in &[1, 5, 7, 63, 65] {
+        for &count in &[1, 21, 32, 33, 100] {
+            check(PixelFormat::Rgb565, count, Some(max));
+            check(PixelFormat::Rgb666, count, Some(max));
+        }
+    }
+}
+
+#[test]
+fn raw_chunks_split_pixels() {
+    let (mut display, bus) = initialized_display();
+    display.set_pixel_format(PixelFormat::Rgb666).unwrap();
+    take_ops(&bus);
+    let data: Vec<u8> = (0..30).collect();
+    let sizes = [1, 4, 0, 7, 2, 16];
+    let mut chunks = Vec::new();
+    let mut rest = &data[..];
+    for &n in &sizes {
+        chunks.push(&rest[..n]);
+        rest = &rest[n..];
+    }
+    display.draw_raw_chunks(0, 0, 9, 0, chunks).unwrap();
+
+    let ops = take_ops(&bus);
+    for op in &ops[ops.iter().position(|op| *op == Op::Command(0x2c)).unwrap()..] {
+        if let Op::Data(data) = op {
+            assert_eq!(data.len() % 3, 0, "write of {} bytes", data.len());
+        }
+    }
+    assert_eq!(pixel_bytes(&ops), data);
+
+    // the last pixel is incomplete
+    let chunks = [&data[..4], &data[4..8]];
+    assert!(display
+        .draw_raw_chunks(0, 0, 9, 0, chunks.iter().cloned())
+        .is_err());
+    assert_eq!(pixel_bytes(&take_ops(&bus)), &data[..6]);
+
+    display.draw_raw_chunks(0, 0, 9, 0, vec![&[][..]]).unwrap();
+    assert_eq!(take_ops(&bus), vec![]);
+}
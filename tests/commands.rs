@@ -0,0 +1,502 @@
+//! Exact command sequences sent by the driver
+
+extern crate embedded_hal as hal;
+extern crate ili9341;
+
+mod common;
+
+use std::cell::RefCell;
+use std::convert::Infallible;
+
+use hal::blocking::delay::DelayMs;
+use hal::digital::v2::InputPin;
+
+use common::{
+    commands, initialized_display, new_display, take_ops, MockCs, MockDc, MockDelay, MockPin,
+    MockSpi, Op, SharedBus,
+};
+use ili9341::{
+    AddressMode, Command, Config, Ili9341, InitDelays, Orientation, SpiMode, StreamingDc,
+    DATASHEET_DELAYS, DEFAULT_INIT_SEQUENCE,
+};
+
+#[test]
+fn init_sequence() {
+    let (_display, bus) = new_display();
+    let expected: Vec<(u8, Vec<u8>)> = vec![
+        // software reset
+        (0x01, vec![]),
+        // power
+        (0xcb, vec![0x39, 0x2c, 0x00, 0x34, 0x02]),
+        (0xcf, vec![0x00, 0xc1, 0x30]),
+        (0xe8, vec![0x85, 0x00, 0x78]),
+        (0xea, vec![0x00, 0x00]),
+        (0xed, vec![0x64, 0x03, 0x12, 0x81]),
+        (0xf7, vec![0x20]),
+        (0xc0, vec![0x23]),
+        (0xc1, vec![0x10]),
+        (0xc5, vec![0x3e, 0x28]),
+        (0xc7, vec![0x86]),
+        // display
+        (0x36, vec![0x48]),
+        (0x3a, vec![0x55]),
+        (0xb1, vec![0x00, 0x18]),
+        (0xb6, vec![0x08, 0x82, 0x27]),
+        // gamma
+        (0xf2, vec![0x00]),
+        (0x26, vec![0x01]),
+        (
+            0xe0,
+            vec![
+                0x0f, 0x31, 0x2b, 0x0c, 0x0e, 0x08, 0x4e, 0xf1, 0x37, 0x07, 0x10, 0x03, 0x0e, 0x09,
+                0x00,
+            ],
+        ),
+        (
+            0xe1,
+            vec![
+                0x00, 0x0e, 0x14, 0x03, 0x11, 0x07, 0x31, 0xc1, 0x48, 0x08, 0x0f, 0x0c, 0x31, 0x36,
+                0x0f,
+            ],
+        ),
+        // sleep out, display on
+        (0x11, vec![]),
+        (0x29, vec![]),
+    ];
+    assert_eq!(commands(&take_ops(&bus)), expected);
+}
+
+/// Delay recording the waits asked for
+#[derive(Default)]
+struct RecordingDelay(Vec<u16>);
+
+impl DelayMs<u16> for RecordingDelay {
+    fn delay_ms(&mut self, ms: u16) {
+        self.0.push(ms);
+    }
+}
+
+#[test]
+fn init_delays() {
+    let bus = SharedBus::default();
+    let mut delay = RecordingDelay::default();
+    Ili9341::new(
+        MockSpi(bus.clone()),
+        MockPin,
+        MockDc(bus.clone()),
+        MockPin,
+        &mut delay,
+    )
+    .unwrap();
+    assert_eq!(delay.0, vec![200, 200, 200, 200, 120]);
+    let expected = commands(&take_ops(&bus));
+
+    let mut delay = RecordingDelay::default();
+    Ili9341::new_with_delays(
+        MockSpi(bus.clone()),
+        MockPin,
+        MockDc(bus.clone()),
+        MockPin,
+        &mut delay,
+        DATASHEET_DELAYS,
+    )
+    .unwrap();
+    assert_eq!(delay.0, vec![10, 10, 120, 5, 120]);
+    assert_eq!(commands(&take_ops(&bus)), expected);
+
+    let mut delay = RecordingDelay::default();
+    Ili9341::new_with_delays(
+        MockSpi(bus.clone()),
+        MockPin,
+        MockDc(bus.clone()),
+        MockPin,
+        &mut delay,
+        InitDelays::<20, 50, 150, 10, 130>,
+    )
+    .unwrap();
+    assert_eq!(delay.0, vec![20, 50, 150, 10, 130]);
+}
+
+#[test]
+fn init_sequence_matches_table() {
+    let (_display, bus) = new_display();
+    let sent = commands(&take_ops(&bus));
+    let table: Vec<(u8, Vec<u8>)> = DEFAULT_INIT_SEQUENCE
+        .iter()
+        .map(|&(cmd, args, _)| (cmd, args.to_vec()))
+        .collect();
+    assert_eq!(sent, table);
+}
+
+#[test]
+fn config_pump_ratio() {
+    for &(ratio, valid) in &[
+        (0x00, false),
+        (0x10, false),
+        (0x20, true),
+        (0x25, false),
+        (0x30, true),
+    ] {
+        let config = Config {
+            pump_ratio_control: ratio,
+            ..Config::default()
+        };
+        assert_eq!(config.is_valid(), valid, "{:#04x}", ratio);
+    }
+}
+
+#[test]
+fn orientation() {
+    let (mut display, bus) = initialized_display();
+    let cases = [
+        (Orientation::Portrait, 0x48, (240, 320)),
+        (Orientation::Landscape, 0x28, (320, 240)),
+        (Orientation::PortraitFlipped, 0x88, (240, 320)),
+        (Orientation::LandscapeFlipped, 0xe8, (320, 240)),
+    ];
+    for &(orientation, madctl, size) in &cases {
+        display.set_orientation(orientation).unwrap();
+        assert_eq!(
+            take_ops(&bus),
+            vec![Op::Command(0x36), Op::Data(vec![madctl])]
+        );
+        assert_eq!((display.width(), display.height()), size);
+    }
+}
+
+#[test]
+fn clamp_point() {
+    let (mut display, _) = initialized_display();
+    assert_eq!(display.clamp_point(0, 0), Some((0, 0)));
+    assert_eq!(display.clamp_point(239, 319), Some((239, 319)));
+    assert_eq!(display.clamp_point(240, 0), None);
+    assert_eq!(display.clamp_point(0, 320), None);
+    assert_eq!(display.clamp_point(-1, 5), None);
+    assert_eq!(display.clamp_point(5, -1), None);
+    display.set_orientation(Orientation::Landscape).unwrap();
+    assert_eq!(display.clamp_point(319, 239), Some((319, 239)));
+    assert_eq!(display.clamp_point(239, 240), None);
+}
+
+#[test]
+fn draw_columns() {
+    let (mut display, bus) = initialized_display();
+    let columns = [1, 2, 3, 4, 5, 6, 7, 8];
+    display.draw_columns(10, 20, 21, &columns, 2).unwrap();
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![
+            (0x36, vec![0x68]),
+            (0x2a, vec![0x00, 0x14, 0x00, 0x15]),
+            (0x2b, vec![0x00, 0x0a, 0x00, 0x0b]),
+            (0x2c, columns.to_vec()),
+            (0x36, vec![0x48]),
+        ]
+    );
+
+    assert!(display.draw_columns(239, 0, 1, &columns, 2).is_err());
+    assert!(display.draw_columns(0, 0, 2, &columns, 2).is_err());
+    assert_eq!(take_ops(&bus), vec![]);
+}
+
+/// The columns land where they belong in every orientation
+#[cfg(feature = "simulator")]
+#[test]
+fn draw_columns_simulated() {
+    use ili9341::simulator::Ili9341Sim;
+
+    for &orientation in &[
+        Orientation::Portrait,
+        Orientation::Landscape,
+        Orientation::PortraitFlipped,
+        Orientation::LandscapeFlipped,
+    ] {
+        let mut display = Ili9341Sim::new_simulator();
+        display.set_orientation(orientation).unwrap();
+        // 3 columns of 2 pixels
+        let columns = [0, 1, 0, 2, 0, 3, 0, 4, 0, 5, 0, 6];
+        display.draw_columns(5, 7, 8, &columns, 3).unwrap();
+        let panel = display.panel();
+        for (i, &(x, y)) in [(5, 7), (5, 8), (6, 7), (6, 8), (7, 7), (7, 8)]
+            .iter()
+            .enumerate()
+        {
+            assert_eq!(panel.pixel(x, y), Some(i as u16 + 1), "{:?}", orientation);
+        }
+    }
+}
+
+#[test]
+fn address_mode() {
+    let (mut display, bus) = initialized_display();
+    let mode = AddressMode {
+        vertical_refresh_reversed: true,
+        horizontal_refresh_reversed: false,
+    };
+    display.set_address_mode(mode).unwrap();
+    assert_eq!(
+        take_ops(&bus),
+        vec![Op::Command(0x36), Op::Data(vec![0x58])]
+    );
+
+    // kept across orientation changes
+    display.set_orientation(Orientation::Landscape).unwrap();
+    assert_eq!(
+        take_ops(&bus),
+        vec![Op::Command(0x36), Op::Data(vec![0x38])]
+    );
+    display
+        .set_address_mode(AddressMode {
+            horizontal_refresh_reversed: true,
+            ..mode
+        })
+        .unwrap();
+    assert_eq!(
+        take_ops(&bus),
+        vec![Op::Command(0x36), Op::Data(vec![0x3c])]
+    );
+    assert_eq!((display.width(), display.height()), (320, 240));
+}
+
+#[test]
+fn spi_mode() {
+    let (display, bus) = initialized_display();
+    assert_eq!(display.spi_mode(), SpiMode::Mode0);
+    let display = display.with_spi_mode(SpiMode::Mode3);
+    assert_eq!(display.spi_mode(), SpiMode::Mode3);
+    assert!(SpiMode::Mode3.mode() == ili9341::MODE_3);
+    assert_eq!(take_ops(&bus), vec![]);
+}
+
+#[test]
+fn mirror() {
+    let (mut display, bus) = initialized_display();
+    display.set_h_mirror(true).unwrap();
+    assert_eq!(commands(&take_ops(&bus)), vec![(0x36, vec![0x08])]);
+    display.set_v_mirror(true).unwrap();
+    assert_eq!(commands(&take_ops(&bus)), vec![(0x36, vec![0x88])]);
+    // kept across orientation changes, on the exchanged axes
+    display.set_orientation(Orientation::Landscape).unwrap();
+    assert_eq!(commands(&take_ops(&bus)), vec![(0x36, vec![0xe8])]);
+    display.set_v_mirror(false).unwrap();
+    assert_eq!(commands(&take_ops(&bus)), vec![(0x36, vec![0xa8])]);
+    assert_eq!(display.mirror(), (true, false));
+}
+
+/// A mirrored pixel lands where the pixel at the other end of the axis does
+/// without mirroring, in every orientation
+#[cfg(feature = "simulator")]
+#[test]
+fn mirror_simulated() {
+    use ili9341::simulator::Ili9341Sim;
+
+    for &orientation in &[
+        Orientation::Portrait,
+        Orientation::Landscape,
+        Orientation::PortraitFlipped,
+        Orientation::LandscapeFlipped,
+    ] {
+        for &(h, v) in &[(true, false), (false, true), (true, true)] {
+            let mut plain = Ili9341Sim::new_simulator();
+            plain.set_orientation(orientation).unwrap();
+            let (w, hh) = (plain.width() as u16, plain.height() as u16);
+            let x = if h { w - 1 - 3 } else { 3 };
+            let y = if v { hh - 1 - 5 } else { 5 };
+            plain.draw_iter(x, y, x, y, vec![0xffff]).unwrap();
+
+            let mut mirrored = Ili9341Sim::new_simulator();
+            mirrored.set_orientation(orientation).unwrap();
+            mirrored.set_h_mirror(h).unwrap();
+            mirrored.set_v_mirror(v).unwrap();
+            mirrored.draw_iter(3, 5, 3, 5, vec![0xffff]).unwrap();
+
+            assert!(
+                plain.panel().frame() == mirrored.panel().frame(),
+                "{:?} {:?}",
+                orientation,
+                (h, v)
+            );
+        }
+    }
+}
+
+#[test]
+fn draw_raw_sets_window() {
+    let (mut display, bus) = initialized_display();
+    display
+        .draw_raw(0x102, 0x0a, 0x103, 0x0a, &[0x12, 0x34, 0x56, 0x78])
+        .unwrap();
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![
+            (0x2a, vec![0x01, 0x02, 0x01, 0x03]),
+            (0x2b, vec![0x00, 0x0a, 0x00, 0x0a]),
+            (0x2c, vec![0x12, 0x34, 0x56, 0x78]),
+        ]
+    );
+}
+
+#[test]
+fn draw_raw_sends_data_unchanged() {
+    let (mut display, bus) = initialized_display();
+    let data: Vec<u8> = (0..=255).collect();
+    display.draw_raw(0, 0, 127, 0, &data).unwrap();
+    let sent = commands(&take_ops(&bus));
+    assert_eq!(sent.last(), Some(&(0x2c, data)));
+}
+
+#[test]
+fn max_transfer_size() {
+    let (mut display, bus) = initialized_display();
+    display.set_max_transfer_size(Some(5));
+    display
+        .draw_iter(0, 0, 4, 0, vec![0x0102, 0x0304, 0x0506, 0x0708, 0x090a])
+        .unwrap();
+    // rounded down to 2 pixels per transfer
+    assert_eq!(
+        commands(&take_ops(&bus))[2..].to_vec(),
+        vec![
+            (0x2c, vec![0x01, 0x02, 0x03, 0x04]),
+            (0x3c, vec![0x05, 0x06, 0x07, 0x08]),
+            (0x3c, vec![0x09, 0x0a]),
+        ]
+    );
+}
+
+#[test]
+fn streaming_dc_writes_single_bytes() {
+    let bus = SharedBus::default();
+    let mut display = Ili9341::new(
+        MockSpi(bus.clone()),
+        MockPin,
+        StreamingDc(MockDc(bus.clone())),
+        MockPin,
+        &mut MockDelay,
+    )
+    .unwrap();
+    display.draw_raw(0, 0, 1, 0, &[1, 2, 3, 4]).unwrap();
+    let ops = take_ops(&bus);
+    assert!(ops.iter().all(|op| match op {
+        Op::Data(data) => data.len() == 1,
+        _ => true,
+    }));
+    assert_eq!(commands(&ops).last(), Some(&(0x2c, vec![1, 2, 3, 4])));
+}
+
+#[test]
+fn nop() {
+    let (mut display, bus) = initialized_display();
+    display.nop().unwrap();
+    assert_eq!(commands(&take_ops(&bus)), vec![(0x00, vec![])]);
+}
+
+/// Commands without parameters are a single byte write, without a
+/// zero-length data write that some SPI implementations reject
+#[test]
+fn no_empty_data_writes() {
+    let (mut display, bus) = new_display();
+    assert!(take_ops(&bus).iter().all(|op| op != &Op::Data(Vec::new())));
+
+    display.send_command(Command::DisplayOn, &[]).unwrap();
+    assert_eq!(take_ops(&bus), vec![Op::Command(0x29)]);
+}
+
+/// TE pin going through the given levels, one per read
+struct TePin(RefCell<Vec<bool>>);
+
+impl InputPin for TePin {
+    type Error = Infallible;
+
+    fn is_high(&self) -> Result<bool, Infallible> {
+        Ok(self.0.borrow_mut().remove(0))
+    }
+    fn is_low(&self) -> Result<bool, Infallible> {
+        self.is_high().map(|high| !high)
+    }
+}
+
+#[test]
+fn wait_for_tearing_effect() {
+    let (display, bus) = initialized_display();
+    // in the middle of a blanking, then a frame, then the next blanking
+    let levels = vec![true, true, false, false, false, true, true];
+    let mut display = display.with_te_pin(TePin(RefCell::new(levels)));
+
+    // without the TE output the pin is not even read
+    display.wait_for_tearing_effect().unwrap();
+
+    display.set_tearing_effect(true).unwrap();
+    display.wait_for_tearing_effect().unwrap();
+    assert_eq!(commands(&take_ops(&bus)), vec![(0x35, vec![0x00])]);
+}
+
+/// The device selects the display itself: the same commands, nothing on CS
+#[test]
+fn spi_device() {
+    let bus = SharedBus::default();
+    let mut display = Ili9341::with_spi_device(
+        MockSpi(bus.clone()),
+        MockDc(bus.clone()),
+        MockPin,
+        &mut MockDelay,
+    )
+    .unwrap();
+    let (_, expected) = new_display();
+    assert_eq!(commands(&take_ops(&bus)), commands(&take_ops(&expected)));
+
+    display.draw_raw(0, 0, 0, 0, &[1, 2]).unwrap();
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![
+            (0x2a, vec![0x00, 0x00, 0x00, 0x00]),
+            (0x2b, vec![0x00, 0x00, 0x00, 0x00]),
+            (0x2c, vec![1, 2]),
+        ]
+    );
+}
+
+#[test]
+fn cs_held() {
+    let bus = SharedBus::default();
+    let mut display = Ili9341::new(
+        MockSpi(bus.clone()),
+        MockCs(bus.clone()),
+        MockDc(bus.clone()),
+        MockPin,
+        &mut MockDelay,
+    )
+    .unwrap();
+    take_ops(&bus);
+
+    display.draw_raw(0, 0, 0, 0, &[1, 2]).unwrap();
+    let cs = |ops: Vec<Op>| -> Vec<bool> {
+        ops.into_iter()
+            .filter_map(|op| match op {
+                Op::Cs(high) => Some(high),
+                _ => None,
+            })
+            .collect()
+    };
+    // released after each of the three commands
+    assert_eq!(cs(take_ops(&bus)), [false, true, false, true, false, true]);
+
+    display
+        .with_cs_held(|display| {
+            display.draw_raw(0, 0, 0, 0, &[1, 2])?;
+            display.draw_raw(1, 1, 1, 1, &[3, 4])
+        })
+        .unwrap();
+    let ops = take_ops(&bus);
+    assert_eq!(commands(&ops).len(), 6);
+    assert_eq!(ops.first(), Some(&Op::Cs(false)));
+    assert_eq!(ops.last(), Some(&Op::Cs(true)));
+    assert_eq!(cs(ops), [false, true]);
+
+    // released on errors too
+    let res: Result<(), _> = display.with_cs_held(|display| {
+        display.draw_raw(0, 0, 0, 0, &[1, 2])?;
+        Err(ili9341::Error::OutOfBounds)
+    });
+    assert!(res.is_err());
+    assert_eq!(cs(take_ops(&bus)), [false, true]);
+}
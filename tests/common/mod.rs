@@ -0,0 +1,201 @@
+//! Mock bus recording what the driver sends
+
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::mem;
+use std::rc::Rc;
+
+use hal::blocking::delay::DelayMs;
+use hal::blocking::spi;
+use hal::digital::v2::OutputPin;
+
+use ili9341::Ili9341;
+
+/// A write seen on the bus
+#[derive(Clone, Debug, PartialEq)]
+pub enum Op {
+    /// A command byte, sent with D/C low
+    Command(u8),
+    /// The bytes of a single SPI write, sent with D/C high
+    Data(Vec<u8>),
+    /// A change of the CS line, low to select the display, when recorded by
+    /// [MockCs](struct.MockCs.html)
+    Cs(bool),
+}
+
+/// State shared by the SPI bus and the D/C pin
+#[derive(Default)]
+pub struct Bus {
+    dc_high: bool,
+    ops: Vec<Op>,
+    /// Bytes answered to the reads, zeros once empty
+    reads: VecDeque<u8>,
+}
+
+pub type SharedBus = Rc<RefCell<Bus>>;
+
+/// SPI bus recording the writes, as commands or data depending on the D/C pin
+pub struct MockSpi(pub SharedBus);
+
+impl spi::Write<u8> for MockSpi {
+    type Error = Infallible;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Infallible> {
+        let mut bus = self.0.borrow_mut();
+        if bus.dc_high {
+            bus.ops.push(Op::Data(words.to_vec()));
+        } else {
+            bus.ops.extend(words.iter().map(|&b| Op::Command(b)));
+        }
+        Ok(())
+    }
+}
+
+/// Reads answer the bytes queued with [queue_reads](fn.queue_reads.html),
+/// they are not recorded
+impl spi::Transfer<u8> for MockSpi {
+    type Error = Infallible;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Infallible> {
+        let mut bus = self.0.borrow_mut();
+        for w in words.iter_mut() {
+            *w = bus.reads.pop_front().unwrap_or(0);
+        }
+        Ok(words)
+    }
+}
+
+/// D/C pin driving the [MockSpi](struct.MockSpi.html)
+pub struct MockDc(pub SharedBus);
+
+impl OutputPin for MockDc {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.0.borrow_mut().dc_high = false;
+        Ok(())
+    }
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        self.0.borrow_mut().dc_high = true;
+        Ok(())
+    }
+}
+
+/// CS pin recording its changes on the bus
+pub struct MockCs(pub SharedBus);
+
+impl OutputPin for MockCs {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.0.borrow_mut().ops.push(Op::Cs(false));
+        Ok(())
+    }
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        self.0.borrow_mut().ops.push(Op::Cs(true));
+        Ok(())
+    }
+}
+
+/// SPI bus discarding the writes, for the tests where the display must not
+/// hold the shared `Rc` bus, like the `Send` and `Sync` checks
+pub struct NullSpi;
+
+impl spi::Write<u8> for NullSpi {
+    type Error = Infallible;
+
+    fn write(&mut self, _words: &[u8]) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+impl spi::Write<u16> for NullSpi {
+    type Error = Infallible;
+
+    fn write(&mut self, _words: &[u16]) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+/// CS and RESET pins, doing nothing
+pub struct MockPin;
+
+impl OutputPin for MockPin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+/// Delay returning immediately
+pub struct MockDelay;
+
+impl DelayMs<u16> for MockDelay {
+    fn delay_ms(&mut self, _ms: u16) {}
+}
+
+pub type MockDisplay = Ili9341<MockSpi, MockPin, MockDc, MockPin>;
+
+/// Create a display on a new mock bus, returning it with the bus
+pub fn new_display() -> (MockDisplay, SharedBus) {
+    let bus = SharedBus::default();
+    let display = Ili9341::new(
+        MockSpi(bus.clone()),
+        MockPin,
+        MockDc(bus.clone()),
+        MockPin,
+        &mut MockDelay,
+    )
+    .unwrap();
+    (display, bus)
+}
+
+/// Create a display and forget what was sent during its init
+pub fn initialized_display() -> (MockDisplay, SharedBus) {
+    let (display, bus) = new_display();
+    take_ops(&bus);
+    (display, bus)
+}
+
+/// Get the writes recorded so far, clearing them
+pub fn take_ops(bus: &SharedBus) -> Vec<Op> {
+    mem::take(&mut bus.borrow_mut().ops)
+}
+
+/// Queue bytes to be answered to the next reads
+pub fn queue_reads(bus: &SharedBus, bytes: &[u8]) {
+    bus.borrow_mut().reads.extend(bytes);
+}
+
+/// Group the writes as commands followed by all their parameter bytes
+pub fn commands(ops: &[Op]) -> Vec<(u8, Vec<u8>)> {
+    let mut commands: Vec<(u8, Vec<u8>)> = Vec::new();
+    for op in ops {
+        match op {
+            Op::Command(cmd) => commands.push((*cmd, Vec::new())),
+            Op::Data(data) => commands
+                .last_mut()
+                .expect("data sent before any command")
+                .1
+                .extend_from_slice(data),
+            Op::Cs(_) => {}
+        }
+    }
+    commands
+}
+
+/// Pixel bytes sent after Memory Write and Memory Write Continue
+pub fn pixel_bytes(ops: &[Op]) -> Vec<u8> {
+    commands(ops)
+        .into_iter()
+        .filter(|&(cmd, _)| cmd == 0x2c || cmd == 0x3c)
+        .flat_map(|(_, data)| data)
+        .collect()
+}
@@ -0,0 +1,66 @@
+//! Copies of a rectangle of the video memory, read back row by row
+
+extern crate embedded_hal as hal;
+extern crate ili9341;
+
+mod common;
+
+use common::{commands, initialized_display, queue_reads, take_ops};
+
+/// Memory Read answer for a row: a dummy byte, then rgb666 pixels
+fn row(pixels: &[u16]) -> Vec<u8> {
+    let mut bytes = vec![0];
+    for &p in pixels {
+        bytes.push(((p >> 11) << 3) as u8);
+        bytes.push((((p >> 5) & 0x3f) << 2) as u8);
+        bytes.push(((p & 0x1f) << 3) as u8);
+    }
+    bytes
+}
+
+#[test]
+fn copies_rows() {
+    let (mut display, bus) = initialized_display();
+    // moving up: top row first
+    queue_reads(&bus, &row(&[0xf800, 0x07e0]));
+    queue_reads(&bus, &row(&[0x001f, 0xffff]));
+    assert_eq!(display.copy_region(5, 11, 5, 10, 2, 2, &mut ()).unwrap(), 4);
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![
+            (0x2a, vec![0x00, 0x05, 0x00, 0x06]),
+            (0x2b, vec![0x00, 0x0b, 0x00, 0x0b]),
+            (0x2e, vec![]),
+            (0x2a, vec![0x00, 0x05, 0x00, 0x06]),
+            (0x2b, vec![0x00, 0x0a, 0x00, 0x0a]),
+            (0x2c, vec![0xf8, 0x00, 0x07, 0xe0]),
+            (0x2a, vec![0x00, 0x05, 0x00, 0x06]),
+            (0x2b, vec![0x00, 0x0c, 0x00, 0x0c]),
+            (0x2e, vec![]),
+            (0x2a, vec![0x00, 0x05, 0x00, 0x06]),
+            (0x2b, vec![0x00, 0x0b, 0x00, 0x0b]),
+            (0x2c, vec![0x00, 0x1f, 0xff, 0xff]),
+        ]
+    );
+}
+
+#[test]
+fn moving_down_starts_from_the_bottom() {
+    let (mut display, bus) = initialized_display();
+    assert_eq!(display.copy_region(0, 0, 0, 1, 1, 2, &mut ()).unwrap(), 2);
+    let rows: Vec<_> = commands(&take_ops(&bus))
+        .into_iter()
+        .filter(|&(cmd, _)| cmd == 0x2b)
+        .map(|(_, args)| args[1])
+        .collect();
+    assert_eq!(rows, [1, 2, 0, 1]);
+}
+
+#[test]
+fn checks_bounds() {
+    let (mut display, bus) = initialized_display();
+    assert!(display.copy_region(230, 0, 0, 0, 11, 1, &mut ()).is_err());
+    assert!(display.copy_region(0, 0, 0, 310, 1, 11, &mut ()).is_err());
+    assert_eq!(display.copy_region(0, 0, 10, 10, 0, 5, &mut ()).unwrap(), 0);
+    assert_eq!(take_ops(&bus), vec![]);
+}
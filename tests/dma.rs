@@ -0,0 +1,29 @@
+//! Buffers aligned for DMA
+
+#![cfg(feature = "aligned-dma")]
+
+extern crate embedded_hal as hal;
+extern crate ili9341;
+
+mod common;
+
+use common::{commands, initialized_display, take_ops};
+use ili9341::dma::{AlignedBuffer, AlignedSlice, DMA_ALIGN};
+
+#[test]
+fn aligned_buffer() {
+    let mut buf = AlignedBuffer::<64>::new();
+    assert_eq!(buf.0.as_ptr() as usize % DMA_ALIGN, 0);
+    buf.0[..4].copy_from_slice(&[0xf8, 0x00, 0x07, 0xe0]);
+
+    let (mut display, bus) = initialized_display();
+    display
+        .draw_raw_aligned(0, 0, 31, 0, buf.as_aligned())
+        .unwrap();
+    let commands = commands(&take_ops(&bus));
+    assert_eq!(commands[2], (0x2c, buf.0.to_vec()));
+
+    assert!(AlignedSlice::new(&buf.0).is_some());
+    assert!(AlignedSlice::new(&buf.0[1..]).is_none());
+    assert!(AlignedSlice::new(&buf.0[32..]).is_some());
+}
@@ -0,0 +1,307 @@
+//! The embedded-graphics `DrawTarget` and its `DrawTargetExt` adapters
+
+#![cfg(feature = "graphics")]
+
+extern crate embedded_graphics_08 as embedded_graphics;
+extern crate embedded_hal as hal;
+extern crate ili9341;
+
+mod common;
+
+use common::{commands, initialized_display, pixel_bytes, take_ops};
+use embedded_graphics::image::ImageRaw;
+use embedded_graphics::pixelcolor::{Rgb565, Rgb888};
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+use ili9341::color::BrightnessLut;
+
+#[test]
+fn fill_solid_sets_window() {
+    let (mut display, bus) = initialized_display();
+    let area = Rectangle::new(Point::new(1, 2), Size::new(2, 1));
+    display.fill_solid(&area, Rgb565::RED).unwrap();
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![
+            (0x2a, vec![0x00, 0x01, 0x00, 0x02]),
+            (0x2b, vec![0x00, 0x02, 0x00, 0x02]),
+            (0x2c, vec![0xf8, 0x00, 0xf8, 0x00]),
+        ]
+    );
+}
+
+#[test]
+fn fill_solid_is_clipped() {
+    let (mut display, bus) = initialized_display();
+    let area = Rectangle::new(Point::new(-1, 319), Size::new(2, 2));
+    display.fill_solid(&area, Rgb565::BLUE).unwrap();
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![
+            (0x2a, vec![0x00, 0x00, 0x00, 0x00]),
+            (0x2b, vec![0x01, 0x3f, 0x01, 0x3f]),
+            (0x2c, vec![0x00, 0x1f]),
+        ]
+    );
+}
+
+#[test]
+fn translated() {
+    let (mut display, bus) = initialized_display();
+    display
+        .translated(Point::new(10, 20))
+        .draw_iter([Pixel(Point::new(1, 1), Rgb565::GREEN)])
+        .unwrap();
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![
+            (0x2a, vec![0x00, 0x0b, 0x00, 0x0b]),
+            (0x2b, vec![0x00, 0x15, 0x00, 0x15]),
+            (0x2c, vec![0x07, 0xe0]),
+        ]
+    );
+}
+
+#[test]
+fn cropped() {
+    let (mut display, bus) = initialized_display();
+    let area = Rectangle::new(Point::new(100, 50), Size::new(10, 10));
+    let mut cropped = display.cropped(&area);
+    assert_eq!(cropped.bounding_box().size, Size::new(10, 10));
+    cropped
+        .fill_solid(&Rectangle::new(Point::zero(), Size::new(1, 1)), Rgb565::RED)
+        .unwrap();
+    assert_eq!(
+        commands(&take_ops(&bus))[..2],
+        [
+            (0x2a, vec![0x00, 0x64, 0x00, 0x64]),
+            (0x2b, vec![0x00, 0x32, 0x00, 0x32]),
+        ]
+    );
+}
+
+#[test]
+fn clipped() {
+    let (mut display, bus) = initialized_display();
+    let area = Rectangle::new(Point::new(0, 0), Size::new(2, 1));
+    display
+        .clipped(&area)
+        .draw_iter((0..4).map(|x| Pixel(Point::new(x, 0), Rgb565::WHITE)))
+        .unwrap();
+    assert_eq!(pixel_bytes(&take_ops(&bus)), [0xff; 4]);
+}
+
+#[test]
+fn color_converted() {
+    let (mut display, bus) = initialized_display();
+    display
+        .color_converted::<Rgb888>()
+        .draw_iter([
+            Pixel(Point::new(0, 0), Rgb888::RED),
+            Pixel(Point::new(1, 0), Rgb888::GREEN),
+            Pixel(Point::new(2, 0), Rgb888::BLUE),
+        ])
+        .unwrap();
+    assert_eq!(
+        pixel_bytes(&take_ops(&bus)),
+        [0xf8, 0x00, 0x07, 0xe0, 0x00, 0x1f]
+    );
+}
+
+#[test]
+fn fill_solid_clipped_at_each_edge() {
+    let (mut display, bus) = initialized_display();
+    let windows = [
+        // left, top, right, bottom
+        ((-5, 10), (0x00, 0x00, 0x00, 0x04), (0x00, 0x0a, 0x00, 0x13)),
+        ((10, -5), (0x00, 0x0a, 0x00, 0x13), (0x00, 0x00, 0x00, 0x04)),
+        (
+            (235, 10),
+            (0x00, 0xeb, 0x00, 0xef),
+            (0x00, 0x0a, 0x00, 0x13),
+        ),
+        (
+            (10, 315),
+            (0x00, 0x0a, 0x00, 0x13),
+            (0x01, 0x3b, 0x01, 0x3f),
+        ),
+    ];
+    for &((x, y), cols, rows) in &windows {
+        let area = Rectangle::new(Point::new(x, y), Size::new(10, 10));
+        display.fill_solid(&area, Rgb565::RED).unwrap();
+        let commands = commands(&take_ops(&bus));
+        assert_eq!(commands[0], (0x2a, vec![cols.0, cols.1, cols.2, cols.3]));
+        assert_eq!(commands[1], (0x2b, vec![rows.0, rows.1, rows.2, rows.3]));
+        assert_eq!(commands[2].1.len(), 5 * 10 * 2);
+    }
+
+    // nothing is sent for an area entirely off the screen
+    let area = Rectangle::new(Point::new(240, 0), Size::new(10, 10));
+    display.fill_solid(&area, Rgb565::RED).unwrap();
+    assert_eq!(take_ops(&bus), vec![]);
+}
+
+#[test]
+fn fill_contiguous_off_screen() {
+    let (mut display, bus) = initialized_display();
+    // the last column of the area is off the screen
+    let area = Rectangle::new(Point::new(238, 0), Size::new(3, 1));
+    display
+        .fill_contiguous(&area, [Rgb565::RED, Rgb565::GREEN, Rgb565::BLUE])
+        .unwrap();
+    assert_eq!(pixel_bytes(&take_ops(&bus)), [0xf8, 0x00, 0x07, 0xe0]);
+}
+
+#[test]
+fn fill_contiguous_clipped_window() {
+    let (mut display, bus) = initialized_display();
+    // 3x2 area with its first column and row off the screen
+    let area = Rectangle::new(Point::new(-1, -1), Size::new(3, 2));
+    let colors = (0..6).map(|i| Rgb565::new(0, 0, i));
+    display.fill_contiguous(&area, colors).unwrap();
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![
+            (0x2a, vec![0x00, 0x00, 0x00, 0x01]),
+            (0x2b, vec![0x00, 0x00, 0x00, 0x00]),
+            (0x2c, vec![0x00, 0x04, 0x00, 0x05]),
+        ]
+    );
+}
+
+#[test]
+fn rgb888_target() {
+    let (mut display, bus) = initialized_display();
+    display
+        .rgb888_target()
+        .draw_iter([
+            Pixel(Point::new(3, 4), Rgb888::new(0xff, 0, 0)),
+            Pixel(Point::new(4, 4), Rgb888::new(0, 0, 0xff)),
+        ])
+        .unwrap();
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![
+            (0x2a, vec![0x00, 0x03, 0x00, 0x04]),
+            (0x2b, vec![0x00, 0x04, 0x00, 0x04]),
+            (0x2c, vec![0xf8, 0x00, 0x00, 0x1f]),
+        ]
+    );
+}
+
+#[test]
+fn scanline_target() {
+    let (mut display, bus) = initialized_display();
+    let mut buf = [0; 480];
+    let mut target = display.scanline_target(&mut buf).unwrap();
+    assert_eq!(target.bounding_box().size, Size::new(240, 320));
+    // out of order on the line, with a gap and a pixel off the screen
+    target
+        .draw_iter([
+            Pixel(Point::new(5, 1), Rgb565::RED),
+            Pixel(Point::new(2, 1), Rgb565::GREEN),
+            Pixel(Point::new(1, 1), Rgb565::BLUE),
+            Pixel(Point::new(-1, 1), Rgb565::RED),
+            Pixel(Point::new(0, 2), Rgb565::RED),
+        ])
+        .unwrap();
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![
+            (0x2a, vec![0x00, 0x01, 0x00, 0x02]),
+            (0x2b, vec![0x00, 0x01, 0x00, 0x01]),
+            (0x2c, vec![0x00, 0x1f, 0x07, 0xe0]),
+            (0x2a, vec![0x00, 0x05, 0x00, 0x05]),
+            (0x2b, vec![0x00, 0x01, 0x00, 0x01]),
+            (0x2c, vec![0xf8, 0x00]),
+            (0x2a, vec![0x00, 0x00, 0x00, 0x00]),
+            (0x2b, vec![0x00, 0x02, 0x00, 0x02]),
+            (0x2c, vec![0xf8, 0x00]),
+        ]
+    );
+
+    let (mut display, _) = initialized_display();
+    assert!(display.scanline_target(&mut [0; 479]).is_err());
+}
+
+#[test]
+fn area_writer() {
+    let (mut display, bus) = initialized_display();
+    let area = Rectangle::new(Point::new(2, 3), Size::new(2, 1));
+    let mut writer = display.area_writer(&area).unwrap();
+    writer.write([Rgb565::RED]).unwrap();
+    writer.write([Rgb565::BLUE]).unwrap();
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![
+            (0x2a, vec![0x00, 0x02, 0x00, 0x03]),
+            (0x2b, vec![0x00, 0x03, 0x00, 0x03]),
+            (0x2c, vec![0xf8, 0x00]),
+            (0x3c, vec![0x00, 0x1f]),
+        ]
+    );
+
+    for area in [
+        Rectangle::new(Point::new(2, 3), Size::new(0, 1)),
+        Rectangle::new(Point::new(-1, 3), Size::new(2, 1)),
+        Rectangle::new(Point::new(239, 319), Size::new(2, 1)),
+    ] {
+        assert!(display.area_writer(&area).is_err());
+    }
+}
+
+#[test]
+fn draw_image_drawable() {
+    let (mut display, bus) = initialized_display();
+    // 3x2 rgb565 image, big endian
+    let data = [
+        0xf8, 0x00, 0x07, 0xe0, 0x00, 0x1f, //
+        0x00, 0x01, 0x00, 0x02, 0x00, 0x03,
+    ];
+    let image = ImageRaw::<Rgb565>::new(&data, 3);
+    display.draw_image(10, 20, &image).unwrap();
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![
+            (0x2a, vec![0x00, 0x0a, 0x00, 0x0c]),
+            (0x2b, vec![0x00, 0x14, 0x00, 0x15]),
+            (0x2c, data.to_vec()),
+        ]
+    );
+
+    // colors converted to rgb565
+    let data = [0xff, 0x00, 0x00, 0x00, 0x00, 0xff];
+    let image = ImageRaw::<Rgb888>::new(&data, 1);
+    display.draw_image(0, 0, &image).unwrap();
+    assert_eq!(pixel_bytes(&take_ops(&bus)), vec![0xf8, 0x00, 0x00, 0x1f]);
+
+    let image = ImageRaw::<Rgb565>::new(&[0; 242 * 2], 242);
+    assert!(display.draw_image(0, 0, &image).is_err());
+}
+
+#[test]
+fn brightness_lut() {
+    // tables turning every color black
+    let black = BrightnessLut::from_arrays([0; 32], [0; 64], [0; 32]);
+    let area = Rectangle::new(Point::new(1, 2), Size::new(2, 1));
+    let pixels = [
+        Pixel(Point::new(1, 2), Rgb565::WHITE),
+        Pixel(Point::new(2, 2), Rgb565::WHITE),
+    ];
+
+    let (mut display, bus) = initialized_display();
+    display.set_brightness_lut(Some(black));
+    display.fill_solid(&area, Rgb565::WHITE).unwrap();
+    assert_eq!(pixel_bytes(&take_ops(&bus)), [0; 4]);
+    display
+        .fill_contiguous(&area, [Rgb565::WHITE; 2].iter().cloned())
+        .unwrap();
+    assert_eq!(pixel_bytes(&take_ops(&bus)), [0; 4]);
+    DrawTarget::draw_iter(&mut display, pixels.iter().cloned()).unwrap();
+    assert_eq!(pixel_bytes(&take_ops(&bus)), [0; 4]);
+
+    let mut buf = [0; 480];
+    let mut target = display.scanline_target(&mut buf).unwrap();
+    target.draw_iter(pixels.iter().cloned()).unwrap();
+    assert_eq!(pixel_bytes(&take_ops(&bus)), [0; 4]);
+}
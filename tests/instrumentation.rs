@@ -0,0 +1,49 @@
+//! Traffic counted by the instrumented drawing target
+
+#![cfg(feature = "instrumentation")]
+
+extern crate embedded_graphics_08 as embedded_graphics;
+extern crate embedded_hal as hal;
+extern crate ili9341;
+
+mod common;
+
+use common::initialized_display;
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+use ili9341::DrawStats;
+
+#[test]
+fn counts_operations() {
+    let (mut display, _bus) = initialized_display();
+    // traffic before the target is created is not counted
+    display.draw_raw(0, 0, 0, 0, &[0, 0]).unwrap();
+
+    let mut target = display.instrumented();
+    let area = Rectangle::new(Point::new(0, 0), Size::new(4, 2));
+    target.fill_solid(&area, Rgb565::RED).unwrap();
+    assert_eq!(
+        *target.stats(),
+        DrawStats {
+            command_count: 3,
+            bytes_written: 8 + 4 * 2 * 2,
+            window_changes: 1,
+        }
+    );
+
+    // two runs on two rows
+    target
+        .draw_iter([
+            Pixel(Point::new(0, 5), Rgb565::BLUE),
+            Pixel(Point::new(1, 5), Rgb565::BLUE),
+            Pixel(Point::new(0, 6), Rgb565::BLUE),
+        ])
+        .unwrap();
+    assert_eq!(target.stats().command_count, 9);
+    assert_eq!(target.stats().window_changes, 3);
+    assert_eq!(target.stats().bytes_written, 24 + 16 + 6);
+
+    target.reset_stats();
+    assert_eq!(*target.stats(), DrawStats::default());
+}
@@ -0,0 +1,81 @@
+//! Commands sent by the power state machine
+
+#![cfg(feature = "power")]
+
+extern crate embedded_hal as hal;
+extern crate ili9341;
+
+mod common;
+
+use common::{commands, initialized_display, take_ops, MockDelay};
+use ili9341::power::{DisplayState, DisplayStateMachine};
+
+#[test]
+fn transitions() {
+    let (display, bus) = initialized_display();
+    let mut machine = DisplayStateMachine::new(display, MockDelay);
+
+    machine.transition_to(DisplayState::Dimming(0x40)).unwrap();
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![(0x53, vec![0x24]), (0x51, vec![0x40])]
+    );
+    machine.transition_to(DisplayState::Off).unwrap();
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![(0x28, vec![]), (0x51, vec![0xff])]
+    );
+    machine.transition_to(DisplayState::Sleeping).unwrap();
+    assert_eq!(commands(&take_ops(&bus)), vec![(0x10, vec![])]);
+    machine.transition_to(DisplayState::On).unwrap();
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![(0x11, vec![]), (0x29, vec![])]
+    );
+    assert_eq!(machine.state(), DisplayState::On);
+}
+
+#[test]
+fn invalid_transitions() {
+    let (display, bus) = initialized_display();
+    let mut machine = DisplayStateMachine::new(display, MockDelay);
+    machine.transition_to(DisplayState::Sleeping).unwrap();
+    take_ops(&bus);
+    assert!(machine.transition_to(DisplayState::Dimming(0)).is_err());
+    assert!(machine.transition_to(DisplayState::Off).is_err());
+    assert_eq!(machine.state(), DisplayState::Sleeping);
+    assert_eq!(take_ops(&bus), vec![]);
+}
+
+#[test]
+fn idle_timeouts() {
+    let (display, bus) = initialized_display();
+    let mut machine = DisplayStateMachine::new(display, MockDelay);
+    machine.set_dim_timeout(Some((2, 0x10)));
+    machine.set_sleep_timeout(Some(4));
+
+    assert_eq!(machine.tick().unwrap(), None);
+    assert_eq!(machine.tick().unwrap(), Some(DisplayState::Dimming(0x10)));
+    assert_eq!(machine.tick().unwrap(), None);
+    assert_eq!(machine.tick().unwrap(), Some(DisplayState::Sleeping));
+    assert_eq!(machine.tick().unwrap(), None);
+    take_ops(&bus);
+
+    // activity wakes the display up and restarts the idle time
+    machine.activity().unwrap();
+    assert_eq!(machine.state(), DisplayState::On);
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![(0x11, vec![]), (0x29, vec![])]
+    );
+    assert_eq!(machine.tick().unwrap(), None);
+}
+
+#[test]
+fn activity_keeps_explicit_state() {
+    let (display, _bus) = initialized_display();
+    let mut machine = DisplayStateMachine::new(display, MockDelay);
+    machine.transition_to(DisplayState::Off).unwrap();
+    machine.activity().unwrap();
+    assert_eq!(machine.state(), DisplayState::Off);
+}
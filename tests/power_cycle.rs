@@ -0,0 +1,131 @@
+//! Powering the display down and up again
+
+extern crate embedded_hal as hal;
+extern crate ili9341;
+
+mod common;
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use hal::blocking::spi;
+use hal::digital::v2::OutputPin;
+
+use common::{commands, take_ops, MockDc, MockDelay, MockPin, MockSpi, SharedBus};
+use ili9341::{Error, Ili9341, InitSteps};
+
+/// A short custom init sequence: Software Reset, Power Control 1, Sleep Out
+/// and Display On
+static INIT: [(u8, &[u8], u16); 4] = [
+    (0x01, &[], 5),
+    (0xc0, &[0x21], 0),
+    (0x11, &[], 120),
+    (0x29, &[], 0),
+];
+
+/// Panel with its own gamma step
+struct Panel;
+
+impl InitSteps for Panel {
+    fn gamma(&self) -> &[(u8, &[u8], u16)] {
+        &[(0x26, &[0x02], 0)]
+    }
+}
+
+/// Check that `power_up` sends the commands sent at construction again,
+/// before restoring the orientation and the pixel format
+fn check_power_cycle(display: &mut common::MockDisplay, bus: &SharedBus) {
+    let init = commands(&take_ops(bus));
+    display.power_down(&mut MockDelay).unwrap();
+    take_ops(bus);
+    display.power_up(&mut MockDelay).unwrap();
+    let sent = commands(&take_ops(bus));
+    assert_eq!(sent[..init.len()], init[..]);
+    assert_eq!(
+        sent[init.len()..].iter().map(|c| c.0).collect::<Vec<_>>(),
+        [0x36, 0x3a]
+    );
+}
+
+#[test]
+fn power_up_sends_custom_sequence() {
+    let bus = SharedBus::default();
+    let mut display = Ili9341::new_with_init_sequence(
+        MockSpi(bus.clone()),
+        MockPin,
+        MockDc(bus.clone()),
+        MockPin,
+        &mut MockDelay,
+        &INIT,
+    )
+    .unwrap();
+    check_power_cycle(&mut display, &bus);
+}
+
+#[test]
+fn power_up_sends_custom_steps() {
+    let bus = SharedBus::default();
+    let mut display = Ili9341::new_with_init_steps(
+        MockSpi(bus.clone()),
+        MockPin,
+        MockDc(bus.clone()),
+        MockPin,
+        &mut MockDelay,
+        &Panel,
+    )
+    .unwrap();
+    check_power_cycle(&mut display, &bus);
+}
+
+/// SPI bus failing its writes while the shared flag is set
+struct FlakySpi(Rc<Cell<bool>>);
+
+impl spi::Write<u8> for FlakySpi {
+    type Error = ();
+
+    fn write(&mut self, _words: &[u8]) -> Result<(), ()> {
+        if self.0.get() {
+            Err(())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+struct Pin;
+
+impl OutputPin for Pin {
+    type Error = ();
+
+    fn set_low(&mut self) -> Result<(), ()> {
+        Ok(())
+    }
+    fn set_high(&mut self) -> Result<(), ()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn failed_power_up_stays_powered_down() {
+    let failing = Rc::new(Cell::new(false));
+    let mut display =
+        Ili9341::new(FlakySpi(failing.clone()), Pin, Pin, Pin, &mut MockDelay).unwrap();
+    display.power_down(&mut MockDelay).unwrap();
+    assert!(display.is_powered_down());
+
+    failing.set(true);
+    assert!(matches!(
+        display.power_up(&mut MockDelay),
+        Err(Error::Spi(()))
+    ));
+    assert!(display.is_powered_down());
+    assert!(matches!(
+        display.set_pixel(0, 0, 0),
+        Err(Error::PoweredDown)
+    ));
+
+    failing.set(false);
+    display.power_up(&mut MockDelay).unwrap();
+    assert!(!display.is_powered_down());
+    display.set_pixel(0, 0, 0).unwrap();
+}
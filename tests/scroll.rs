@@ -0,0 +1,82 @@
+//! Vertical scrolling offsets wrap around the scrolling area
+
+extern crate embedded_hal as hal;
+extern crate ili9341;
+
+mod common;
+
+use common::{commands, initialized_display, take_ops};
+
+#[test]
+fn scroll_area() {
+    let (mut display, bus) = initialized_display();
+    display.set_scroll_area(10, 20).unwrap();
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![
+            (0x33, vec![0x00, 0x0a, 0x01, 0x22, 0x00, 0x14]),
+            (0x37, vec![0x00, 0x0a]),
+        ]
+    );
+    assert!(display.set_scroll_area(160, 160).is_err());
+    assert_eq!(take_ops(&bus), vec![]);
+}
+
+#[test]
+fn offset_below_area_height() {
+    let (mut display, bus) = initialized_display();
+    display.set_vertical_scroll_offset(0).unwrap();
+    display.set_vertical_scroll_offset(319).unwrap();
+    assert_eq!(display.scroll_offset(), 319);
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![(0x37, vec![0x00, 0x00]), (0x37, vec![0x01, 0x3f])]
+    );
+}
+
+#[test]
+fn offset_wraps_around_full_screen() {
+    let (mut display, bus) = initialized_display();
+    display.set_vertical_scroll_offset(320).unwrap();
+    assert_eq!(display.scroll_offset(), 0);
+    display.set_vertical_scroll_offset(645).unwrap();
+    assert_eq!(display.scroll_offset(), 5);
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![(0x37, vec![0x00, 0x00]), (0x37, vec![0x00, 0x05])]
+    );
+}
+
+#[test]
+fn offset_wraps_around_area() {
+    let (mut display, bus) = initialized_display();
+    // 300 lines scrolling, starting at line 10
+    display.set_scroll_area(10, 10).unwrap();
+    take_ops(&bus);
+
+    display.set_vertical_scroll_offset(299).unwrap();
+    assert_eq!(display.scroll_offset(), 299);
+    display.set_vertical_scroll_offset(300).unwrap();
+    assert_eq!(display.scroll_offset(), 0);
+    display.set_vertical_scroll_offset(301).unwrap();
+    assert_eq!(display.scroll_offset(), 1);
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![
+            (0x37, vec![0x01, 0x35]),
+            (0x37, vec![0x00, 0x0a]),
+            (0x37, vec![0x00, 0x0b]),
+        ]
+    );
+}
+
+#[test]
+fn offset_kept_within_new_area() {
+    let (mut display, bus) = initialized_display();
+    display.set_vertical_scroll_offset(250).unwrap();
+    take_ops(&bus);
+    // the area shrinks to 200 lines, below the offset
+    display.set_scroll_area(60, 60).unwrap();
+    assert_eq!(display.scroll_offset(), 50);
+    assert_eq!(commands(&take_ops(&bus))[1], (0x37, vec![0x00, 0x6e]));
+}
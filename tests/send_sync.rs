@@ -0,0 +1,20 @@
+//! The display is `Send` and `Sync` when its parts are
+
+extern crate embedded_hal as hal;
+extern crate ili9341;
+
+mod common;
+
+use ili9341::{Dc16, Ili9341, NoDc, StreamingDc};
+
+use common::{MockPin, NullSpi};
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn send_and_sync() {
+    assert_send_sync::<Ili9341<NullSpi, MockPin, MockPin, MockPin>>();
+    assert_send_sync::<Ili9341<NullSpi, MockPin, NoDc, MockPin>>();
+    assert_send_sync::<Ili9341<NullSpi, MockPin, Dc16<MockPin>, MockPin>>();
+    assert_send_sync::<Ili9341<NullSpi, MockPin, StreamingDc<MockPin>, MockPin>>();
+}
@@ -0,0 +1,41 @@
+//! SPI bus shared through a critical section
+
+#![cfg(feature = "softdevice")]
+
+extern crate critical_section;
+extern crate embedded_hal as hal;
+extern crate ili9341;
+
+mod common;
+
+use std::cell::RefCell;
+
+use critical_section::Mutex;
+
+use common::{commands, take_ops, MockDc, MockDelay, MockPin, MockSpi, SharedBus};
+use ili9341::softdevice::SoftdeviceSpiWrapper;
+use ili9341::Ili9341;
+
+#[test]
+fn shared_bus() {
+    let bus = SharedBus::default();
+    let spi = Mutex::new(RefCell::new(MockSpi(bus.clone())));
+    let mut display = Ili9341::new(
+        SoftdeviceSpiWrapper::new(&spi),
+        MockPin,
+        MockDc(bus.clone()),
+        MockPin,
+        &mut MockDelay,
+    )
+    .unwrap();
+    take_ops(&bus);
+
+    display.draw_raw(0, 0, 0, 0, &[0xf8, 0x00]).unwrap();
+    let commands = commands(&take_ops(&bus));
+    assert_eq!(commands[2], (0x2c, vec![0xf8, 0x00]));
+
+    // the bus stays usable by the rest of the application
+    critical_section::with(|cs| {
+        let _spi = spi.borrow(cs).borrow_mut();
+    });
+}
@@ -0,0 +1,100 @@
+//! Writes on a bus with 16-bit frames, through [Dc16](struct.Dc16.html)
+
+extern crate embedded_hal as hal;
+extern crate ili9341;
+
+mod common;
+
+use std::cell::RefCell;
+use std::convert::Infallible;
+use std::rc::Rc;
+
+use hal::blocking::spi;
+use hal::digital::v2::OutputPin;
+
+use common::{MockDelay, MockPin};
+use ili9341::{Ili9341, Ili9341Spi16};
+
+/// State shared by the SPI bus and the D/C pin
+#[derive(Default)]
+struct Bus {
+    dc_high: bool,
+    /// The words of each SPI write, with the level of the D/C pin
+    writes: Vec<(bool, Vec<u16>)>,
+}
+
+struct Spi16(Rc<RefCell<Bus>>);
+
+impl spi::Write<u16> for Spi16 {
+    type Error = Infallible;
+
+    fn write(&mut self, words: &[u16]) -> Result<(), Infallible> {
+        let mut bus = self.0.borrow_mut();
+        let dc_high = bus.dc_high;
+        bus.writes.push((dc_high, words.to_vec()));
+        Ok(())
+    }
+}
+
+struct Dc(Rc<RefCell<Bus>>);
+
+impl OutputPin for Dc {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.0.borrow_mut().dc_high = false;
+        Ok(())
+    }
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        self.0.borrow_mut().dc_high = true;
+        Ok(())
+    }
+}
+
+fn display() -> (Ili9341Spi16<Spi16, MockPin, Dc, MockPin>, Rc<RefCell<Bus>>) {
+    let bus = Rc::new(RefCell::new(Bus::default()));
+    let display = Ili9341::new_16bit(
+        Spi16(bus.clone()),
+        MockPin,
+        Dc(bus.clone()),
+        MockPin,
+        &mut MockDelay,
+    )
+    .unwrap();
+    bus.borrow_mut().writes.clear();
+    (display, bus)
+}
+
+/// The data words written after each Memory Write (0x2c) or Memory Write
+/// Continue (0x3c) command
+fn pixel_writes(bus: &Rc<RefCell<Bus>>) -> Vec<Vec<u16>> {
+    let writes = bus.borrow_mut().writes.split_off(0);
+    let mut pixels = Vec::new();
+    let mut in_memory_write = false;
+    for (dc_high, words) in writes {
+        if !dc_high {
+            in_memory_write = words == [0x2c] || words == [0x3c];
+        } else if in_memory_write {
+            pixels.push(words);
+        }
+    }
+    pixels
+}
+
+#[test]
+fn draw_raw_slice_writes_words() {
+    let (mut display, bus) = display();
+    let data: Vec<u16> = (0..64).map(|i| i * 0x0101).collect();
+    display.draw_raw_slice(0, 0, 7, 7, &data).unwrap();
+    assert_eq!(pixel_writes(&bus), vec![data]);
+}
+
+#[test]
+fn draw_raw_slice_chunks_words() {
+    let (mut display, bus) = display();
+    display.set_max_transfer_size(Some(32));
+    let data: Vec<u16> = (0..40).collect();
+    display.draw_raw_slice(0, 0, 7, 4, &data).unwrap();
+    let expected: Vec<Vec<u16>> = data.chunks(16).map(|c| c.to_vec()).collect();
+    assert_eq!(pixel_writes(&bus), expected);
+}
@@ -0,0 +1,285 @@
+//! Color-keyed images are split into runs of opaque pixels, transposed images
+//! are gathered in tiles, 1bpp bitmaps are expanded bit by bit
+
+extern crate embedded_hal as hal;
+extern crate ili9341;
+
+mod common;
+
+use std::collections::HashMap;
+
+use common::{commands, initialized_display, take_ops, Op};
+use ili9341::color::BrightnessLut;
+
+const KEY: u16 = 0xf81f;
+
+/// Pixels drawn by the recorded writes by position, following the windows
+fn drawn_pixels(ops: &[Op]) -> HashMap<(u16, u16), u16> {
+    let range = |d: &[u8]| {
+        (
+            u16::from_be_bytes([d[0], d[1]]),
+            u16::from_be_bytes([d[2], d[3]]),
+        )
+    };
+    let mut drawn = HashMap::new();
+    let (mut columns, mut pages) = ((0, 0), (0, 0));
+    for (cmd, data) in commands(ops) {
+        match cmd {
+            0x2a => columns = range(&data),
+            0x2b => pages = range(&data),
+            0x2c => {
+                let w = columns.1 - columns.0 + 1;
+                for (i, p) in data.chunks(2).enumerate() {
+                    let i = i as u16;
+                    let pos = (columns.0 + i % w, pages.0 + i / w);
+                    assert!(pos.1 <= pages.1, "pixel past the window");
+                    drawn.insert(pos, u16::from_be_bytes([p[0], p[1]]));
+                }
+            }
+            _ => {}
+        }
+    }
+    drawn
+}
+
+/// Big endian bytes of rgb565 pixels
+fn bytes(pixels: &[u16]) -> Vec<u8> {
+    pixels
+        .iter()
+        .flat_map(|p| p.to_be_bytes().to_vec())
+        .collect()
+}
+
+#[test]
+fn opaque_image_single_window() {
+    let (mut display, bus) = initialized_display();
+    let image = bytes(&[1, 2, 3, 4, 5, 6]);
+    display.draw_image_keyed(10, 20, 3, 2, &image, KEY).unwrap();
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![
+            (0x2a, vec![0x00, 0x0a, 0x00, 0x0c]),
+            (0x2b, vec![0x00, 0x14, 0x00, 0x15]),
+            (0x2c, image),
+        ]
+    );
+}
+
+#[test]
+fn transparent_rows_send_nothing() {
+    let (mut display, bus) = initialized_display();
+    let image = bytes(&[KEY; 8]);
+    display.draw_image_keyed(0, 0, 4, 2, &image, KEY).unwrap();
+    assert_eq!(take_ops(&bus), vec![]);
+}
+
+#[test]
+fn rows_split_in_runs() {
+    let (mut display, bus) = initialized_display();
+    #[rustfmt::skip]
+    let image = bytes(&[
+        1, KEY, 2, 3,
+        KEY, KEY, KEY, KEY,
+        KEY, 4, 5, KEY,
+    ]);
+    display.draw_image_keyed(0, 0, 4, 3, &image, KEY).unwrap();
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![
+            (0x2a, vec![0x00, 0x00, 0x00, 0x00]),
+            (0x2b, vec![0x00, 0x00, 0x00, 0x00]),
+            (0x2c, vec![0x00, 0x01]),
+            (0x2a, vec![0x00, 0x02, 0x00, 0x03]),
+            (0x2b, vec![0x00, 0x00, 0x00, 0x00]),
+            (0x2c, vec![0x00, 0x02, 0x00, 0x03]),
+            (0x2a, vec![0x00, 0x01, 0x00, 0x02]),
+            (0x2b, vec![0x00, 0x02, 0x00, 0x02]),
+            (0x2c, vec![0x00, 0x04, 0x00, 0x05]),
+        ]
+    );
+}
+
+#[test]
+fn opaque_rows_then_runs() {
+    let (mut display, bus) = initialized_display();
+    #[rustfmt::skip]
+    let image = bytes(&[
+        1, 2, 3,
+        4, KEY, 5,
+    ]);
+    display.draw_image_keyed(0, 0, 3, 2, &image, KEY).unwrap();
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![
+            (0x2a, vec![0x00, 0x00, 0x00, 0x02]),
+            (0x2b, vec![0x00, 0x00, 0x00, 0x01]),
+            (0x2c, vec![0x00, 0x01, 0x00, 0x02, 0x00, 0x03]),
+            (0x2a, vec![0x00, 0x00, 0x00, 0x00]),
+            (0x2b, vec![0x00, 0x01, 0x00, 0x01]),
+            (0x2c, vec![0x00, 0x04]),
+            (0x2a, vec![0x00, 0x02, 0x00, 0x02]),
+            (0x2b, vec![0x00, 0x01, 0x00, 0x01]),
+            (0x2c, vec![0x00, 0x05]),
+        ]
+    );
+}
+
+#[test]
+fn keyed_image_and_iter_match() {
+    #[rustfmt::skip]
+    let pixels = [
+        1, KEY, 2, 3,
+        4, 5, 6, 7,
+        KEY, KEY, 8, KEY,
+    ];
+    let (mut display, bus) = initialized_display();
+    // both go through the brightness tables, halving each level
+    let (mut r, mut g, mut b) = ([0; 32], [0; 64], [0; 32]);
+    for i in 0..64 {
+        if i < 32 {
+            r[i] = i as u8 / 2;
+            b[i] = i as u8 / 2;
+        }
+        g[i] = i as u8 / 2;
+    }
+    display.set_brightness_lut(Some(BrightnessLut::from_arrays(r, g, b)));
+    display
+        .draw_image_keyed(5, 6, 4, 3, &bytes(&pixels), KEY)
+        .unwrap();
+    let image_ops = take_ops(&bus);
+    display
+        .draw_iter_with_color_key(5, 6, 8, 8, pixels.iter().cloned(), KEY)
+        .unwrap();
+    assert_eq!(take_ops(&bus), image_ops);
+}
+
+#[test]
+fn checks_size() {
+    let (mut display, bus) = initialized_display();
+    assert!(display.draw_image_keyed(0, 0, 2, 2, &[0; 6], KEY).is_err());
+    assert!(display
+        .draw_image_keyed(239, 0, 2, 1, &[0; 4], KEY)
+        .is_err());
+    assert_eq!(take_ops(&bus), vec![]);
+}
+
+#[test]
+fn transposed() {
+    let (mut display, bus) = initialized_display();
+    // 2 rows of 3 pixels, drawn as 3 rows of 2 pixels
+    let image = bytes(&[1, 2, 3, 4, 5, 6]);
+    display
+        .draw_raw_transposed(10, 20, 11, 22, &image, 2, 3)
+        .unwrap();
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![
+            (0x2a, vec![0x00, 0x0a, 0x00, 0x0b]),
+            (0x2b, vec![0x00, 0x14, 0x00, 0x16]),
+            (0x2c, bytes(&[1, 4, 2, 5, 3, 6])),
+        ]
+    );
+}
+
+#[test]
+fn transposed_tiles() {
+    // sizes that are multiples of the 16x16 tiles or not, with partial tiles
+    // on the right, at the bottom or both
+    for &(rows, cols) in &[(16u16, 32u16), (37, 45), (17, 5), (5, 17), (1, 1)] {
+        let (mut display, bus) = initialized_display();
+        let pixels: Vec<u16> = (0..rows * cols).collect();
+        display
+            .draw_raw_transposed(
+                10,
+                20,
+                10 + rows - 1,
+                20 + cols - 1,
+                &bytes(&pixels),
+                rows,
+                cols,
+            )
+            .unwrap();
+        let ops = take_ops(&bus);
+        let tiles = commands(&ops)
+            .iter()
+            .filter(|(cmd, _)| *cmd == 0x2c)
+            .count();
+        assert_eq!(
+            tiles,
+            usize::from(rows.div_ceil(16) * cols.div_ceil(16)),
+            "{}x{}",
+            rows,
+            cols
+        );
+        let drawn = drawn_pixels(&ops);
+        assert_eq!(drawn.len(), usize::from(rows * cols), "{}x{}", rows, cols);
+        for r in 0..rows {
+            for c in 0..cols {
+                assert_eq!(
+                    drawn[&(10 + r, 20 + c)],
+                    r * cols + c,
+                    "{}x{} at {},{}",
+                    rows,
+                    cols,
+                    r,
+                    c
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn transposed_checks_size() {
+    let (mut display, bus) = initialized_display();
+    let image = bytes(&[1, 2, 3, 4, 5, 6]);
+    assert!(display
+        .draw_raw_transposed(0, 0, 2, 1, &image, 2, 3)
+        .is_err());
+    assert!(display
+        .draw_raw_transposed(0, 0, 1, 2, &image[..10], 2, 3)
+        .is_err());
+    assert_eq!(take_ops(&bus), vec![]);
+}
+
+#[test]
+fn bitmap_1bpp() {
+    let (mut display, bus) = initialized_display();
+    const FG: u16 = 0xffff;
+    const BG: u16 = 0x0001;
+    // 10 pixels per row, in 2 bytes: the 6 padding bits are set and must not
+    // show up anywhere
+    #[rustfmt::skip]
+    let bits = [
+        0b1010_0000, 0b01_111111,
+        0b1111_1111, 0b10_111111,
+    ];
+    display
+        .draw_bitmap_1bpp(10, 20, 10, 2, &bits, FG, BG)
+        .unwrap();
+    #[rustfmt::skip]
+    let expected = bytes(&[
+        FG, BG, FG, BG, BG, BG, BG, BG, BG, FG,
+        FG, FG, FG, FG, FG, FG, FG, FG, FG, BG,
+    ]);
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![
+            (0x2a, vec![0x00, 0x0a, 0x00, 0x13]),
+            (0x2b, vec![0x00, 0x14, 0x00, 0x15]),
+            (0x2c, expected),
+        ]
+    );
+}
+
+#[test]
+fn bitmap_1bpp_checks_size() {
+    let (mut display, bus) = initialized_display();
+    // 9 pixels per row take 2 bytes
+    assert!(display.draw_bitmap_1bpp(0, 0, 9, 2, &[0; 3], 1, 0).is_err());
+    assert!(display
+        .draw_bitmap_1bpp(232, 0, 9, 2, &[0; 4], 1, 0)
+        .is_err());
+    display.draw_bitmap_1bpp(0, 0, 0, 2, &[], 1, 0).unwrap();
+    assert_eq!(take_ops(&bus), vec![]);
+}
@@ -0,0 +1,127 @@
+//! Drawing code generic over the display and a host framebuffer
+
+extern crate embedded_hal as hal;
+extern crate ili9341;
+
+mod common;
+
+use common::{commands, initialized_display, take_ops};
+use ili9341::{ClearOperation, Ili9341Surface, Orientation, Window};
+
+/// Framebuffer of rgb565 pixels, in portrait only
+struct Framebuffer {
+    pixels: Vec<u16>,
+}
+
+impl Ili9341Surface for Framebuffer {
+    type Error = ();
+
+    fn width(&self) -> usize {
+        240
+    }
+    fn height(&self) -> usize {
+        320
+    }
+    fn set_orientation(&mut self, orientation: Orientation) -> Result<(), ()> {
+        match orientation {
+            Orientation::Portrait => Ok(()),
+            _ => Err(()),
+        }
+    }
+    fn draw_raw(&mut self, x0: u16, y0: u16, x1: u16, y1: u16, data: &[u8]) -> Result<(), ()> {
+        let pixels = data.chunks(2).map(|p| u16::from_be_bytes([p[0], p[1]]));
+        self.draw_iter(x0, y0, x1, y1, pixels)
+    }
+    fn draw_iter<I: IntoIterator<Item = u16>>(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        data: I,
+    ) -> Result<(), ()> {
+        let points = (y0..=y1).flat_map(|y| (x0..=x1).map(move |x| (x, y)));
+        for ((x, y), color) in points.zip(data) {
+            self.pixels[usize::from(y) * 240 + usize::from(x)] = color;
+        }
+        Ok(())
+    }
+    fn fill_rect(&mut self, x0: u16, y0: u16, x1: u16, y1: u16, color: u16) -> Result<(), ()> {
+        self.draw_iter(x0, y0, x1, y1, std::iter::repeat(color))
+    }
+}
+
+/// A title bar and a button
+fn draw_ui<S: Ili9341Surface>(surface: &mut S) -> Result<(), S::Error> {
+    surface.set_orientation(Orientation::Portrait)?;
+    let w = surface.width() as u16;
+    Window::from_corners(0, 0, w - 1, 1).fill(surface, 0x001f)?;
+    Window::at(10, 10)
+        .size(2, 1)
+        .draw_iter(surface, vec![0xf800, 0x07e0])
+}
+
+#[test]
+fn same_ui_on_both_surfaces() {
+    let mut fb = Framebuffer {
+        pixels: vec![0; 240 * 320],
+    };
+    draw_ui(&mut fb).unwrap();
+    assert_eq!(fb.pixels[..240 * 2], [0x001f; 240 * 2][..]);
+    assert_eq!(fb.pixels[10 * 240 + 10..10 * 240 + 12], [0xf800, 0x07e0]);
+
+    let (mut display, bus) = initialized_display();
+    draw_ui(&mut display).unwrap();
+    let commands = commands(&take_ops(&bus));
+    assert_eq!(commands[0], (0x36, vec![0x48]));
+    assert_eq!(commands[1], (0x2a, vec![0x00, 0x00, 0x00, 0xef]));
+    assert_eq!(commands[2], (0x2b, vec![0x00, 0x00, 0x00, 0x01]));
+    assert_eq!(commands[6], (0x2c, vec![0xf8, 0x00, 0x07, 0xe0]));
+}
+
+#[test]
+fn clear_in_steps() {
+    let mut fb = Framebuffer {
+        pixels: vec![1; 240 * 320],
+    };
+    let mut clear = ClearOperation::new(0xffff);
+    let mut steps = 0;
+    while !clear.step(&mut fb, 1000).unwrap() {
+        steps += 1;
+        // the cleared pixels are always the first ones, without gaps
+        let cleared = clear.pixels_cleared();
+        assert_eq!(cleared, steps * 1000);
+        assert!(fb.pixels[..cleared].iter().all(|&p| p == 0xffff));
+        assert!(fb.pixels[cleared..].iter().all(|&p| p == 1));
+    }
+    assert_eq!(steps, 76);
+    assert!(fb.pixels.iter().all(|&p| p == 0xffff));
+    assert!(clear.step(&mut fb, 1000).unwrap());
+}
+
+#[test]
+fn clear_step_windows() {
+    let (mut display, bus) = initialized_display();
+    let mut clear = ClearOperation::new(0xffff);
+    clear.step(&mut display, 100).unwrap();
+    take_ops(&bus);
+    // the end of the first row, the second row, the start of the third one
+    clear.step(&mut display, 600).unwrap();
+    let windows: Vec<_> = commands(&take_ops(&bus))
+        .into_iter()
+        .filter(|(cmd, _)| *cmd == 0x2a || *cmd == 0x2b)
+        .map(|(_, args)| args)
+        .collect();
+    assert_eq!(
+        windows,
+        vec![
+            vec![0x00, 0x64, 0x00, 0xef],
+            vec![0x00, 0x00, 0x00, 0x00],
+            vec![0x00, 0x00, 0x00, 0xef],
+            vec![0x00, 0x01, 0x00, 0x01],
+            vec![0x00, 0x00, 0x00, 0xdb],
+            vec![0x00, 0x02, 0x00, 0x02],
+        ]
+    );
+    assert_eq!(clear.pixels_cleared(), 700);
+}
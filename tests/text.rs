@@ -0,0 +1,73 @@
+//! Text written with `core::fmt::Write` follows the cursor
+
+#![cfg(feature = "text")]
+
+extern crate embedded_hal as hal;
+extern crate ili9341;
+
+mod common;
+
+use std::fmt::Write;
+
+use common::{commands, initialized_display, take_ops};
+use ili9341::font::Font;
+
+#[test]
+fn cursor_wraps() {
+    let (mut display, bus) = initialized_display();
+    display.write_str("A").unwrap();
+    display.write_str("\nB").unwrap();
+    let windows: Vec<_> = commands(&take_ops(&bus))
+        .into_iter()
+        .filter(|(cmd, _)| *cmd == 0x2a || *cmd == 0x2b)
+        .map(|(_, data)| data)
+        .collect();
+    assert_eq!(
+        windows,
+        vec![
+            vec![0x00, 0x00, 0x00, 0x07],
+            vec![0x00, 0x00, 0x00, 0x07],
+            vec![0x00, 0x00, 0x00, 0x07],
+            vec![0x00, 0x08, 0x00, 0x0f],
+        ]
+    );
+}
+
+#[test]
+fn cursor_far_off_the_screen() {
+    for &(x, y) in &[(65530, 0), (0, 65530), (65535, 65535)] {
+        let (mut display, bus) = initialized_display();
+        display.set_cursor(x, y);
+        display.write_str("A\nB\rC").unwrap();
+        let chars = commands(&take_ops(&bus))
+            .into_iter()
+            .filter(|(cmd, _)| *cmd == 0x2c)
+            .count();
+        assert_eq!(chars, 3, "{},{}", x, y);
+    }
+}
+
+#[test]
+fn font_5x7() {
+    let (mut display, bus) = initialized_display();
+    display.set_font(Font::Font5x7);
+    display.draw_str(10, 20, "AA", 0xffff, 0x0000).unwrap();
+    let commands = commands(&take_ops(&bus));
+    // 6x8 cells, the second one right after the first
+    assert_eq!(commands[0], (0x2a, vec![0x00, 0x0a, 0x00, 0x0f]));
+    assert_eq!(commands[1], (0x2b, vec![0x00, 0x14, 0x00, 0x1b]));
+    assert_eq!(commands[3], (0x2a, vec![0x00, 0x10, 0x00, 0x15]));
+    let rows: Vec<String> = commands[2]
+        .1
+        .chunks(12)
+        .map(|row| {
+            row.chunks(2)
+                .map(|p| if p == [0xff, 0xff] { '#' } else { '.' })
+                .collect()
+        })
+        .collect();
+    assert_eq!(
+        rows,
+        vec![".###..", "#...#.", "#...#.", "#...#.", "#####.", "#...#.", "#...#.", "......",]
+    );
+}
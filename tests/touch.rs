@@ -0,0 +1,154 @@
+//! XPT2046 readings and their mapping to the display coordinates
+
+#![cfg(feature = "touch")]
+
+extern crate embedded_hal as hal;
+extern crate ili9341;
+
+use std::convert::Infallible;
+
+use hal::blocking::spi;
+use hal::digital::v2::{InputPin, OutputPin};
+
+use ili9341::touch::{Calibration, RawTouch, Xpt2046};
+
+/// Touch controller answering each conversion with the next value of its
+/// channel
+struct MockTouch {
+    x: Vec<u16>,
+    y: Vec<u16>,
+    z1: Vec<u16>,
+    z2: Vec<u16>,
+}
+
+impl spi::Transfer<u8> for MockTouch {
+    type Error = Infallible;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Infallible> {
+        let channel = match words[0] {
+            0xd0 => &mut self.x,
+            0x90 => &mut self.y,
+            0xb0 => &mut self.z1,
+            0xc0 => &mut self.z2,
+            cmd => panic!("unexpected control byte {:#x}", cmd),
+        };
+        let value = channel.remove(0) << 3;
+        words.copy_from_slice(&[0, (value >> 8) as u8, value as u8]);
+        Ok(words)
+    }
+}
+
+struct Pin(bool);
+
+impl OutputPin for Pin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+impl InputPin for Pin {
+    type Error = Infallible;
+
+    fn is_high(&self) -> Result<bool, Infallible> {
+        Ok(self.0)
+    }
+    fn is_low(&self) -> Result<bool, Infallible> {
+        Ok(!self.0)
+    }
+}
+
+#[test]
+fn median_of_samples() {
+    let spi = MockTouch {
+        x: vec![100, 4000, 120, 110, 0],
+        y: vec![200, 210, 205, 3000, 190],
+        z1: vec![500; 5],
+        z2: vec![3500; 5],
+    };
+    let mut touch = Xpt2046::new(spi, Pin(true), Pin(false));
+    assert_eq!(
+        touch.read().unwrap(),
+        Some(RawTouch {
+            x: 110,
+            y: 205,
+            z: 1095
+        })
+    );
+}
+
+#[test]
+fn not_touched() {
+    let spi = MockTouch {
+        x: vec![],
+        y: vec![],
+        z1: vec![],
+        z2: vec![],
+    };
+    let mut touch = Xpt2046::new(spi, Pin(true), Pin(true));
+    assert_eq!(touch.read().unwrap(), None);
+}
+
+#[test]
+fn calibration_to_native() {
+    // inverted X axis
+    let calibration = Calibration::new(3900, 100, 200, 3390);
+    let raw = |x, y| RawTouch { x, y, z: 0 };
+    assert_eq!(calibration.to_native(raw(3900, 200)), (0, 0));
+    assert_eq!(calibration.to_native(raw(100, 3390)), (239, 319));
+    assert_eq!(calibration.to_native(raw(4095, 0)), (0, 0));
+    assert_eq!(calibration.to_native(raw(0, 4095)), (239, 319));
+    assert_eq!(calibration.swap_axes().to_native(raw(200, 3900)), (0, 0));
+}
+
+/// The mapping to the display coordinates lands on the pixel drawn there
+#[cfg(feature = "simulator")]
+#[test]
+fn calibration_to_display() {
+    use ili9341::simulator::Ili9341Sim;
+    use ili9341::{Orientation, NATIVE_WIDTH};
+
+    // one raw unit per pixel
+    let calibration = Calibration::new(0, 239, 0, 319);
+    for &orientation in &[
+        Orientation::Portrait,
+        Orientation::Landscape,
+        Orientation::PortraitFlipped,
+        Orientation::LandscapeFlipped,
+    ] {
+        let mut display = Ili9341Sim::new_simulator();
+        display.set_orientation(orientation).unwrap();
+        display.draw_iter(30, 40, 30, 40, vec![0xffff]).unwrap();
+        let i = display
+            .panel()
+            .frame()
+            .iter()
+            .position(|&p| p == 0xffff)
+            .unwrap();
+        let raw = RawTouch {
+            x: (i % NATIVE_WIDTH) as u16,
+            y: (i / NATIVE_WIDTH) as u16,
+            z: 0,
+        };
+        assert_eq!(
+            calibration.to_display(raw, &display),
+            (30, 40),
+            "{:?}",
+            orientation
+        );
+
+        // and on the mirrored image
+        display.set_h_mirror(true).unwrap();
+        let w = display.width() as u16;
+        assert_eq!(
+            calibration.to_display(raw, &display),
+            (w - 1 - 30, 40),
+            "{:?}",
+            orientation
+        );
+    }
+}
@@ -0,0 +1,202 @@
+//! Empty and single-pixel windows behave the same for every drawing method
+
+extern crate embedded_hal as hal;
+extern crate ili9341;
+
+mod common;
+
+use std::convert::Infallible;
+
+use common::{commands, initialized_display, take_ops, MockDisplay};
+use ili9341::{Error, RawImage, Window};
+
+type Result = std::result::Result<(), Error<Infallible, Infallible>>;
+
+/// Draw `pixels` into the window (x0, y0, x1, y1)
+type Draw = fn(&mut MockDisplay, (u16, u16, u16, u16), &[u16]) -> Result;
+
+fn bytes(pixels: &[u16]) -> Vec<u8> {
+    pixels
+        .iter()
+        .flat_map(|p| p.to_be_bytes().to_vec())
+        .collect()
+}
+
+const DRAWS: &[(&str, Draw)] = &[
+    ("draw_iter", |d, (x0, y0, x1, y1), p| {
+        d.draw_iter(x0, y0, x1, y1, p.iter().cloned())
+    }),
+    ("draw_words", |d, (x0, y0, x1, y1), p| {
+        d.draw_words(x0, y0, x1, y1, p.iter().cloned())
+    }),
+    ("draw_indexed", |d, (x0, y0, x1, y1), p| {
+        d.draw_indexed(x0, y0, x1, y1, (0..p.len()).map(|i| i as u8), p)
+    }),
+    ("draw_iter_counted", |d, (x0, y0, x1, y1), p| {
+        d.draw_iter_counted(x0, y0, x1, y1, p.iter().cloned())
+            .map(|_| ())
+    }),
+    ("draw_iter_bytes", |d, (x0, y0, x1, y1), p| {
+        d.draw_iter_bytes(x0, y0, x1, y1, bytes(p))
+    }),
+    ("draw_iter_bytes_counted", |d, (x0, y0, x1, y1), p| {
+        d.draw_iter_bytes_counted(x0, y0, x1, y1, bytes(p))
+            .map(|_| ())
+    }),
+    ("draw_raw", |d, (x0, y0, x1, y1), p| {
+        d.draw_raw(x0, y0, x1, y1, &bytes(p))
+    }),
+    ("blit_dma", |d, (x0, y0, x1, y1), p| {
+        d.blit_dma(x0, y0, x1, y1, &bytes(p))
+    }),
+    ("draw_raw_slice", |d, (x0, y0, x1, y1), p| {
+        d.draw_raw_slice(x0, y0, x1, y1, p)
+    }),
+    ("draw_iter_with_color_key", |d, (x0, y0, x1, y1), p| {
+        d.draw_iter_with_color_key(x0, y0, x1, y1, p.iter().cloned(), 0xf81f)
+    }),
+    ("Window::draw_raw", |d, (x0, y0, x1, y1), p| {
+        Window::from_corners(x0, y0, x1, y1).draw_raw(d, &bytes(p))
+    }),
+    ("Window::draw_iter", |d, (x0, y0, x1, y1), p| {
+        Window::from_corners(x0, y0, x1, y1).draw_iter(d, p.iter().cloned())
+    }),
+];
+
+/// Methods drawing a whole window from a single color
+const FILLS: &[(&str, Draw)] = &[
+    ("fill_pattern", |d, (x0, y0, x1, y1), p| {
+        d.fill_pattern(x0, y0, x1, y1, p)
+    }),
+    ("Window::fill", |d, (x0, y0, x1, y1), p| {
+        Window::from_corners(x0, y0, x1, y1).fill(d, p[0])
+    }),
+];
+
+const INVERTED: &[(u16, u16, u16, u16)] = &[(5, 0, 4, 0), (0, 5, 0, 4), (10, 10, 0, 0)];
+
+#[test]
+fn inverted_windows_send_nothing() {
+    let (mut display, bus) = initialized_display();
+    for &(name, draw) in DRAWS.iter().chain(FILLS) {
+        for &window in INVERTED {
+            let res = draw(&mut display, window, &[0x1234, 0x5678]);
+            assert!(res.is_ok(), "{} {:?}: {:?}", name, window, res);
+            assert_eq!(take_ops(&bus), vec![], "{} {:?}", name, window);
+        }
+    }
+}
+
+#[test]
+fn empty_data_sends_nothing() {
+    let (mut display, bus) = initialized_display();
+    for &(name, draw) in DRAWS {
+        let res = draw(&mut display, (0, 0, 9, 9), &[]);
+        assert!(res.is_ok(), "{}: {:?}", name, res);
+        assert_eq!(take_ops(&bus), vec![], "{}", name);
+    }
+
+    display.memory_write_raw(&[]).unwrap();
+    display.memory_write_iter(None).unwrap();
+    display.draw_raw(0, 0, 0, 0, &[0, 0]).unwrap();
+    take_ops(&bus);
+    display.continue_pixel_write(&[]).unwrap();
+    assert_eq!(take_ops(&bus), vec![]);
+}
+
+#[test]
+fn single_pixel_windows() {
+    let (mut display, bus) = initialized_display();
+    for &(name, draw) in DRAWS.iter().chain(FILLS) {
+        draw(&mut display, (7, 300, 7, 300), &[0x1234]).unwrap();
+        assert_eq!(
+            commands(&take_ops(&bus)),
+            vec![
+                (0x2a, vec![0x00, 0x07, 0x00, 0x07]),
+                (0x2b, vec![0x01, 0x2c, 0x01, 0x2c]),
+                (0x2c, vec![0x12, 0x34]),
+            ],
+            "{}",
+            name
+        );
+    }
+
+    // 1x1 image, after its header
+    let image = [0, 1, 0, 1, 0x12, 0x34];
+    let image = RawImage::new(&image[..]).unwrap();
+    display.draw_image_raw(7, 300, &image).unwrap();
+    display
+        .draw_image_keyed(7, 300, 1, 1, &[0x12, 0x34], 0)
+        .unwrap();
+    display
+        .draw_sub_image((7, 300), &[0x1234], 1, (0, 0, 1, 1))
+        .unwrap();
+    let ops = commands(&take_ops(&bus));
+    assert_eq!(ops[..3], ops[3..6]);
+    assert_eq!(ops[..3], ops[6..]);
+}
+
+#[test]
+fn empty_images_send_nothing() {
+    let (mut display, bus) = initialized_display();
+    display.draw_image_keyed(7, 300, 0, 5, &[], 0).unwrap();
+    display
+        .draw_sub_image((7, 300), &[0x1234], 1, (0, 0, 0, 1))
+        .unwrap();
+    display
+        .draw_framebuffer_region(&[0; 2], 1, 0, 0, 1, 0)
+        .unwrap();
+    assert_eq!(take_ops(&bus), vec![]);
+}
+
+#[test]
+fn exact_length() {
+    let (mut display, bus) = initialized_display();
+    let pixels = [1, 2, 3, 4, 5, 6];
+    display
+        .draw_iter_rgb565(0, 0, 2, 1, pixels.iter().cloned())
+        .unwrap();
+    assert_eq!(
+        commands(&take_ops(&bus)),
+        vec![
+            (0x2a, vec![0x00, 0x00, 0x00, 0x02]),
+            (0x2b, vec![0x00, 0x00, 0x00, 0x01]),
+            (0x2c, bytes(&pixels)),
+        ]
+    );
+
+    // too short, too long, nothing for a window
+    for &len in &[5, 7, 0] {
+        let res = display.draw_iter_rgb565(0, 0, 2, 1, (0..len).map(|p| p as u16));
+        assert!(matches!(res, Err(Error::LengthMismatch)), "{}", len);
+    }
+    assert_eq!(take_ops(&bus), vec![]);
+
+    // an empty window is still a no-op
+    display.draw_iter_rgb565(5, 0, 4, 0, vec![1, 2]).unwrap();
+    assert_eq!(take_ops(&bus), vec![]);
+}
+
+#[test]
+fn window_size() {
+    assert_eq!(
+        Window::at(10, 20).size(3, 2),
+        Window::from_corners(10, 20, 12, 21)
+    );
+    assert_eq!(Window::size(1, 1), Window::from_corners(0, 0, 0, 0));
+    assert!(!Window::size(1, 1).is_empty());
+    assert_eq!(
+        Window::at(65535, 0).size(2, 1),
+        Window::from_corners(65535, 0, 65535, 0)
+    );
+
+    let (mut display, bus) = initialized_display();
+    for &(w, h) in &[(0, 0), (0, 5), (5, 0)] {
+        for window in [Window::size(w, h), Window::at(10, 20).size(w, h)] {
+            assert!(window.is_empty(), "{}x{}", w, h);
+            window.fill(&mut display, 0xffff).unwrap();
+            window.draw_raw(&mut display, &[0xff, 0xff]).unwrap();
+        }
+    }
+    assert_eq!(take_ops(&bus), vec![]);
+}